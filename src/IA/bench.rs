@@ -0,0 +1,76 @@
+use crate::chessboard::chessboard::Chessboard;
+
+use super::move_generator::find_move;
+use super::search::{Engine, SearchConfig, SearchLimits, SearchStats};
+
+// Posiciones de prueba, descritas como partidas desde la posición inicial
+const BENCH_LINES: [&str; 4] = [
+    "",
+    "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6",
+    "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3 e8g8",
+    "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+];
+
+/**
+ Ejecuta la búsqueda sobre las posiciones de prueba e imprime los nodos, la eficiencia del
+ ordenamiento de movimientos y cuánto podó la búsqueda selectiva.
+ # Arguments
+ * `depth` - La profundidad de búsqueda para cada posición.
+ * `config` - La configuración del motor que se va a medir.
+ * `threads` - La cantidad de hilos de búsqueda.
+*/
+pub fn run_bench(depth: u32, config: SearchConfig, threads: usize) {
+    let mut total = SearchStats::default();
+    let mut total_millis = 0;
+
+    for line in BENCH_LINES {
+        let mut chessboard = Chessboard::new();
+
+        for coordinates in line.split_whitespace() {
+            match find_move(&chessboard, coordinates) {
+                Some((_, new_chessboard)) => chessboard = new_chessboard,
+                None => panic!("Movimiento inválido en el bench: {}", coordinates),
+            }
+        }
+
+        // Cada posición empieza con la memoria del motor vacía
+        let mut engine = Engine::new(config);
+        engine.set_threads(threads);
+        engine.clear();
+
+        let result = engine.search(&chessboard, SearchLimits { depth, time: None, nodes: None });
+        let best_move = result
+            .best_move
+            .map(|chess_move| chess_move.to_coordinates())
+            .unwrap_or("-".to_string());
+
+        println!(
+            "{:<6} puntuación {:>6}  nodos {:>9}  cortes {:>7}  primer movimiento {:>5.1}%  {} ms",
+            best_move,
+            result.score,
+            result.stats.nodes,
+            result.stats.beta_cutoffs,
+            result.stats.first_move_cutoff_rate(),
+            result.elapsed.as_millis()
+        );
+
+        total.add(&result.stats);
+        total_millis += result.elapsed.as_millis();
+    }
+
+    println!(
+        "Total: {} nodos ({} de quiescencia), {:.1}% de cortes con el primer movimiento, {} ms, {} nodos/s",
+        total.nodes,
+        total.quiescence_nodes,
+        total.first_move_cutoff_rate(),
+        total_millis,
+        total.nodes as u128 * 1000 / total_millis.max(1)
+    );
+    println!(
+        "Cortes por movimiento nulo: {}, reducciones tardías: {}, podas de futilidad: {}, cortes por razoring: {}",
+        total.null_move_cutoffs,
+        total.late_move_reductions,
+        total.futility_prunes,
+        total.razoring_cutoffs
+    );
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::ChessPieceColor;
+
+use super::pgn::{GameResult, PgnGame};
+use super::polyglot::{encode_move, polyglot_key};
+use super::san::parse_san;
+
+// Opciones para construir el libro
+#[derive(Clone, Copy)]
+pub struct BookBuilderOptions {
+    pub max_ply: usize,
+    pub min_games: u32,
+    pub win_weight: u32,
+    pub draw_weight: u32,
+    pub loss_weight: u32,
+}
+
+impl Default for BookBuilderOptions {
+    fn default() -> Self {
+        BookBuilderOptions {
+            max_ply: 20,
+            min_games: 1,
+            win_weight: 2,
+            draw_weight: 1,
+            loss_weight: 0,
+        }
+    }
+}
+
+// Resumen de lo que se procesó al construir el libro
+#[derive(Default)]
+pub struct BookBuilderSummary {
+    pub games: usize,
+    pub skipped_games: usize,
+    pub truncated_games: usize,
+    pub entries: usize,
+}
+
+#[derive(Default)]
+struct MoveStats {
+    games: u32,
+    points: u32,
+}
+
+/**
+ Construye un libro de aperturas Polyglot a partir de partidas PGN y lo escribe en disco.
+ # Arguments
+ * `games` - Las partidas leídas del archivo PGN.
+ * `output` - La ruta del archivo `.bin` que se va a escribir.
+ * `options` - La cantidad máxima de jugadas, el mínimo de partidas y los pesos por resultado.
+ # Returns
+ Retorna un resumen de las partidas procesadas y las entradas escritas, o un error de escritura.
+*/
+pub fn build_book<P: AsRef<Path>>(
+    games: &[PgnGame],
+    output: P,
+    options: BookBuilderOptions,
+) -> io::Result<BookBuilderSummary> {
+    let mut summary = BookBuilderSummary::default();
+    let mut stats: HashMap<(u64, u16), MoveStats> = HashMap::new();
+
+    for game in games {
+        // Solo usamos partidas con resultado que empiezan desde la posición inicial
+        if game.result == GameResult::Unknown || game.tag("FEN").is_some() {
+            summary.skipped_games += 1;
+            continue;
+        }
+
+        summary.games += 1;
+        let mut chessboard = Chessboard::new();
+
+        for san in game.moves.iter().take(options.max_ply) {
+            let (chess_move, new_chessboard) = match parse_san(&chessboard, san) {
+                Some(parsed) => parsed,
+                None => {
+                    // Movimientos que el tablero no admite, como la captura al paso
+                    summary.truncated_games += 1;
+                    break;
+                }
+            };
+
+            let points = match (game.result, chessboard.player_turn) {
+                (GameResult::Draw, _) => options.draw_weight,
+                (GameResult::WhiteWins, ChessPieceColor::White)
+                | (GameResult::BlackWins, ChessPieceColor::Black) => options.win_weight,
+                _ => options.loss_weight,
+            };
+
+            let entry = stats
+                .entry((polyglot_key(&chessboard), encode_move(&chessboard, chess_move)))
+                .or_default();
+            entry.games += 1;
+            entry.points += points;
+
+            chessboard = new_chessboard;
+        }
+    }
+
+    let mut entries: Vec<(u64, u16, u32)> = stats
+        .into_iter()
+        .filter(|(_, move_stats)| move_stats.games >= options.min_games && move_stats.points > 0)
+        .map(|((key, encoded_move), move_stats)| (key, encoded_move, move_stats.points))
+        .collect();
+
+    // Los pesos de Polyglot son de 16 bits, así que escalamos si alguno se pasa
+    let max_points = entries.iter().map(|(_, _, points)| *points).max().unwrap_or(0);
+    let scale = (max_points as f64 / u16::MAX as f64).max(1.0);
+
+    // Polyglot exige las entradas ordenadas por clave, y dentro de cada clave por peso
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)));
+
+    let mut bytes = Vec::with_capacity(entries.len() * 16);
+    for (key, encoded_move, points) in entries.iter() {
+        let weight = ((*points as f64 / scale).round() as u16).max(1);
+
+        bytes.extend_from_slice(&key.to_be_bytes());
+        bytes.extend_from_slice(&encoded_move.to_be_bytes());
+        bytes.extend_from_slice(&weight.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+    }
+
+    fs::write(output, bytes)?;
+
+    summary.entries = entries.len();
+    Ok(summary)
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+// Nombres de las secciones del archivo de pesos
+const WEIGHT_NAMES: [&str; 6] = ["peon", "caballo", "alfil", "torre", "dama", "rey"];
+
+// Pesos cargados con `load_weights`; si no se cargó ninguno se usan los de por defecto
+static WEIGHTS: OnceLock<EvaluationWeights> = OnceLock::new();
+
+// Tablas de posición desde el punto de vista de las blancas (la fila 0 es la octava fila)
+#[rustfmt::skip]
+const PAWN_TABLE: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [ 50,  50,  50,  50,  50,  50,  50,  50],
+    [ 10,  10,  20,  30,  30,  20,  10,  10],
+    [  5,   5,  10,  25,  25,  10,   5,   5],
+    [  0,   0,   0,  20,  20,   0,   0,   0],
+    [  5,  -5, -10,   0,   0, -10,  -5,   5],
+    [  5,  10,  10, -20, -20,  10,  10,   5],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [[i32; 8]; 8] = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20,   0,   0,   0,   0, -20, -40],
+    [-30,   0,  10,  15,  15,  10,   0, -30],
+    [-30,   5,  15,  20,  20,  15,   5, -30],
+    [-30,   0,  15,  20,  20,  15,   0, -30],
+    [-30,   5,  10,  15,  15,  10,   5, -30],
+    [-40, -20,   0,   5,   5,   0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [[i32; 8]; 8] = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,  10,  10,   5,   0, -10],
+    [-10,   5,   5,  10,  10,   5,   5, -10],
+    [-10,   0,  10,  10,  10,  10,   0, -10],
+    [-10,  10,  10,  10,  10,  10,  10, -10],
+    [-10,   5,   0,   0,   0,   0,   5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  5,  10,  10,  10,  10,  10,  10,   5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [  0,   0,   0,   5,   5,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [[i32; 8]; 8] = [
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,   5,   5,   5,   0, -10],
+    [ -5,   0,   5,   5,   5,   5,   0,  -5],
+    [  0,   0,   5,   5,   5,   5,   0,  -5],
+    [-10,   5,   5,   5,   5,   5,   0, -10],
+    [-10,   0,   5,   0,   0,   0,   0, -10],
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [[i32; 8]; 8] = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [ 20,  20,   0,   0,   0,   0,  20,  20],
+    [ 20,  30,  10,   0,   0,  10,  30,  20],
+];
+
+// Valor material de cada pieza en centipeones
+pub fn piece_value(piece: ChessPieceType) -> i32 {
+    match piece {
+        ChessPieceType::Pawn => 100,
+        ChessPieceType::Knight => 320,
+        ChessPieceType::Bishop => 330,
+        ChessPieceType::Rook => 500,
+        ChessPieceType::Queen => 900,
+        ChessPieceType::King => 20_000,
+    }
+}
+
+/**
+ Pesos de la evaluación: el material de cada pieza (el rey no cuenta) y sus tablas de posición.
+ Se pueden ajustar con partidas y guardar en un archivo de texto que el motor carga al iniciar.
+*/
+#[derive(Clone, PartialEq)]
+pub struct EvaluationWeights {
+    // Peón, caballo, alfil, torre y dama
+    pub material: [i32; 5],
+    // Una tabla por pieza: peón, caballo, alfil, torre, dama y rey
+    pub tables: [[[i32; 8]; 8]; 6],
+}
+
+impl Default for EvaluationWeights {
+    fn default() -> Self {
+        EvaluationWeights {
+            material: [
+                piece_value(ChessPieceType::Pawn),
+                piece_value(ChessPieceType::Knight),
+                piece_value(ChessPieceType::Bishop),
+                piece_value(ChessPieceType::Rook),
+                piece_value(ChessPieceType::Queen),
+            ],
+            tables: [
+                PAWN_TABLE,
+                KNIGHT_TABLE,
+                BISHOP_TABLE,
+                ROOK_TABLE,
+                QUEEN_TABLE,
+                KING_TABLE,
+            ],
+        }
+    }
+}
+
+impl EvaluationWeights {
+    /**
+     Lee los pesos de un archivo con la línea "material" seguida de cinco valores y, por cada
+     pieza, su nombre seguido de las 64 casillas de su tabla. Las secciones que falten conservan
+     los valores por defecto y el texto después de "#" se ignora.
+     # Arguments
+     * `path` - La ruta del archivo de pesos.
+     # Returns
+     Los pesos leídos, o un mensaje con el error.
+    */
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<EvaluationWeights, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut weights = EvaluationWeights::default();
+
+        let mut tokens = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace());
+
+        while let Some(section) = tokens.next() {
+            let count = if section == "material" { 5 } else { 64 };
+            let values = tokens
+                .by_ref()
+                .take(count)
+                .map(|token| token.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| format!("Valor inválido en la sección {}", section))?;
+
+            if values.len() < count {
+                return Err(format!("Faltan valores en la sección {}", section));
+            }
+
+            if section == "material" {
+                weights.material.copy_from_slice(&values);
+                continue;
+            }
+
+            let Some(piece) = WEIGHT_NAMES.iter().position(|name| *name == section) else {
+                return Err(format!("Sección desconocida: {}", section));
+            };
+
+            for (square, value) in values.into_iter().enumerate() {
+                weights.tables[piece][square / 8][square % 8] = value;
+            }
+        }
+
+        Ok(weights)
+    }
+
+    // Escribe los pesos con el mismo formato que lee `read`
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut text = String::new();
+        text.push_str("# Material de peón, caballo, alfil, torre y dama\n");
+        text.push_str(&format!("material {}\n", join_values(&self.material)));
+        text.push_str("# Tablas de posición desde las blancas, de la octava fila a la primera\n");
+
+        for (name, table) in WEIGHT_NAMES.iter().zip(self.tables.iter()) {
+            text.push_str(&format!("\n{}\n", name));
+            for row in table.iter() {
+                text.push_str(&join_values(row));
+                text.push('\n');
+            }
+        }
+
+        fs::write(path, text)
+    }
+
+    // Peso de la pieza en la casilla, sin importar el color
+    fn value(&self, piece: ChessPieceType, table_row: usize, column: usize) -> i32 {
+        let index = weight_index(piece);
+        let material = self.material.get(index).copied().unwrap_or(0);
+
+        material + self.tables[index][table_row][column]
+    }
+}
+
+// Posición de la pieza en las tablas de los pesos
+pub fn weight_index(piece: ChessPieceType) -> usize {
+    match piece {
+        ChessPieceType::Pawn => 0,
+        ChessPieceType::Knight => 1,
+        ChessPieceType::Bishop => 2,
+        ChessPieceType::Rook => 3,
+        ChessPieceType::Queen => 4,
+        ChessPieceType::King => 5,
+    }
+}
+
+fn join_values(values: &[i32]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{:4}", value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/**
+ Carga los pesos que usa `evaluate` durante el resto del programa.
+ # Arguments
+ * `path` - La ruta del archivo de pesos.
+ # Returns
+ Un mensaje con el error si el archivo no es válido o si ya se cargaron otros pesos.
+*/
+pub fn load_weights<P: AsRef<Path>>(path: P) -> Result<(), String> {
+    let weights = EvaluationWeights::read(path)?;
+    WEIGHTS
+        .set(weights)
+        .map_err(|_| "Los pesos de evaluación ya estaban cargados".to_string())
+}
+
+// Los pesos en uso
+pub fn weights() -> &'static EvaluationWeights {
+    WEIGHTS.get_or_init(EvaluationWeights::default)
+}
+
+/**
+ Evalúa el tablero con material y tablas de posición.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ # Returns
+ `i32` - La evaluación en centipeones desde el punto de vista del jugador en turno.
+*/
+pub fn evaluate(chessboard: &Chessboard) -> i32 {
+    let weights = weights();
+    let mut score = 0;
+
+    for (i, row) in chessboard.board.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            if let Some(piece) = cell {
+                // Las tablas están escritas para las blancas, para las negras se reflejan
+                let table_row = match piece.color {
+                    ChessPieceColor::White => i,
+                    ChessPieceColor::Black => 7 - i,
+                };
+
+                let value = weights.value(piece.piece, table_row, j);
+
+                match piece.color {
+                    ChessPieceColor::White => score += value,
+                    ChessPieceColor::Black => score -= value,
+                }
+            }
+        }
+    }
+
+    match chessboard.player_turn {
+        ChessPieceColor::White => score,
+        ChessPieceColor::Black => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_survive_a_write_and_read() {
+        let mut weights = EvaluationWeights::default();
+        weights.material[0] = 93;
+        weights.tables[1][2][3] = -17;
+        weights.tables[5][7][6] = 40;
+
+        let path = std::env::temp_dir().join("ajedrez-pesos.txt");
+        weights.write(&path).unwrap();
+        let read = EvaluationWeights::read(&path).ok().unwrap();
+        assert!(read == weights);
+        assert!(read != EvaluationWeights::default());
+    }
+
+    #[test]
+    fn invalid_weight_files_are_rejected() {
+        let path = std::env::temp_dir().join("ajedrez-pesos-invalidos.txt");
+
+        for text in ["material 100 300", "material 1 2 3 4 x", "obispo 1 2 3"] {
+            fs::write(&path, text).unwrap();
+            assert!(EvaluationWeights::read(&path).is_err());
+        }
+
+        // Las secciones que faltan conservan los valores por defecto
+        fs::write(&path, "# solo el material\nmaterial 90 310 320 500 950\n").unwrap();
+        let weights = EvaluationWeights::read(&path).ok().unwrap();
+        assert_eq!(weights.material, [90, 310, 320, 500, 950]);
+        assert!(weights.tables == EvaluationWeights::default().tables);
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+use super::evaluation::piece_value;
+use super::move_generator::{generate_moves, Move};
+use super::san::to_san;
+use super::search::{opposite_color, Engine, SearchLimits, MATE_SCORE, MAX_PLY};
+
+// Tiempo que piensa el motor para dar una pista
+const HINT_TIME: Duration = Duration::from_millis(1500);
+const HINT_MAX_DEPTH: u32 = 32;
+
+// Profundidad para buscar qué amenaza el movimiento sugerido
+const THREAT_DEPTH: u32 = 4;
+
+// Jugadas de la variante principal que se revisan para saber si el movimiento gana material
+const MATERIAL_PLIES: usize = 6;
+
+pub struct Hint {
+    pub san: String,
+    pub explanation: Option<String>,
+    pub from_book: bool,
+}
+
+impl Hint {
+    // Texto para mostrar al jugador, por ejemplo "Pista: Nxe5 (gana un caballo)"
+    pub fn describe(&self) -> String {
+        let mut text = format!("Pista: {}", self.san);
+
+        if self.from_book {
+            text.push_str(" (libro de aperturas)");
+        } else if let Some(explanation) = &self.explanation {
+            text.push_str(&format!(" ({})", explanation));
+        }
+
+        text
+    }
+}
+
+/**
+ Busca durante un momento el mejor movimiento del jugador en turno y explica por qué es bueno.
+ # Arguments
+ * `engine` - El motor de búsqueda.
+ * `chessboard` - El tablero actual.
+ # Returns
+ Retorna la pista, o `None` si el jugador no tiene movimientos legales.
+*/
+pub fn hint(engine: &mut Engine, chessboard: &Chessboard) -> Option<Hint> {
+    let limits = SearchLimits {
+        depth: HINT_MAX_DEPTH,
+        time: Some(HINT_TIME),
+        nodes: None,
+    };
+    let result = engine.search(chessboard, limits);
+    let chess_move = result.best_move?;
+
+    let explanation = if result.from_book {
+        None
+    } else {
+        explain(engine, chessboard, chess_move, result.score, &result.principal_variation)
+    };
+
+    Some(Hint {
+        san: to_san(chessboard, chess_move),
+        explanation,
+        from_book: result.from_book,
+    })
+}
+
+// Explicación en una línea: mate, material ganado, amenazas o jaque
+fn explain(
+    engine: &mut Engine,
+    chessboard: &Chessboard,
+    chess_move: Move,
+    score: i32,
+    variation: &[Move],
+) -> Option<String> {
+    if let Some(moves) = mate_distance(score) {
+        return Some(if moves == 1 {
+            "da jaque mate".to_string()
+        } else {
+            format!("fuerza mate en {}", moves)
+        });
+    }
+
+    let new_chessboard = generate_moves(chessboard, false)
+        .into_iter()
+        .find(|(legal_move, _)| *legal_move == chess_move)
+        .map(|(_, new_chessboard)| new_chessboard)?;
+
+    // Material que gana el jugador siguiendo la variante principal
+    let color = chessboard.player_turn;
+    let mut current = chessboard.clone();
+    for next_move in variation.iter().take(MATERIAL_PLIES) {
+        match generate_moves(&current, false)
+            .into_iter()
+            .find(|(legal_move, _)| legal_move == next_move)
+        {
+            Some((_, next)) => current = next,
+            None => break,
+        }
+    }
+    let gain = material(&current, color) - material(chessboard, color);
+
+    if gain >= piece_value(ChessPieceType::Pawn) {
+        // Nombramos la pieza capturada si la ganancia la cubre
+        let captured = chessboard.board[chess_move.to[0]][chess_move.to[1]]
+            .map(|piece| piece.piece)
+            .filter(|piece| gain >= piece_value(*piece) - piece_value(ChessPieceType::Pawn) / 2);
+
+        return Some(match captured {
+            Some(piece) => format!("gana {}", piece_name(piece)),
+            None => "gana material".to_string(),
+        });
+    }
+
+    // Si el rival no respondiera, ¿qué lograríamos en la siguiente jugada?
+    let mut threat_chessboard = new_chessboard.clone();
+    threat_chessboard.player_turn = color;
+
+    if is_check(&threat_chessboard, opposite_color(color)).is_none() {
+        let threat = engine.search(
+            &threat_chessboard,
+            SearchLimits {
+                depth: THREAT_DEPTH,
+                time: Some(HINT_TIME),
+                nodes: None,
+            },
+        );
+
+        if let Some(moves) = mate_distance(threat.score) {
+            return Some(format!("amenaza mate en {}", moves));
+        }
+    }
+
+    if chess_move.promotion.is_some() {
+        return Some("corona un peón".to_string());
+    }
+
+    if is_check(&new_chessboard, new_chessboard.player_turn).is_some() {
+        return Some("da jaque".to_string());
+    }
+
+    None
+}
+
+// Cantidad de jugadas hasta el mate, si la puntuación es de mate a favor
+fn mate_distance(score: i32) -> Option<i32> {
+    if score >= MATE_SCORE - MAX_PLY as i32 {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else {
+        None
+    }
+}
+
+// Material del jugador menos el del rival, sin contar los reyes
+fn material(chessboard: &Chessboard, color: ChessPieceColor) -> i32 {
+    chessboard
+        .board
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|piece| piece.piece != ChessPieceType::King)
+        .map(|piece| {
+            if piece.color == color {
+                piece_value(piece.piece)
+            } else {
+                -piece_value(piece.piece)
+            }
+        })
+        .sum()
+}
+
+fn piece_name(piece: ChessPieceType) -> &'static str {
+    match piece {
+        ChessPieceType::Pawn => "un peón",
+        ChessPieceType::Knight => "un caballo",
+        ChessPieceType::Bishop => "un alfil",
+        ChessPieceType::Rook => "una torre",
+        ChessPieceType::Queen => "la dama",
+        ChessPieceType::King => "el rey",
+    }
+}
@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+use super::mcts::{MctsConfig, MctsEngine, MctsPolicy};
+use super::move_generator::{generate_moves, Move};
+use super::pgn::GameResult;
+use super::random::Random;
+use super::san::parse_san;
+use super::search::{Engine, SearchConfig, SearchLimits};
+use super::strength::{choose_move, DifficultyLevel, Strength};
+use super::zobrist;
+
+// Profundidad de cada motor si la configuración no indica otra
+const DEFAULT_MATCH_DEPTH: u32 = 4;
+
+// Aperturas cortas y equilibradas para cuando no se indica un archivo
+const DEFAULT_OPENINGS: [&str; 12] = [
+    "e4 e5 Nf3 Nc6 Bb5",
+    "e4 e5 Nf3 Nf6",
+    "e4 c5 Nf3 d6",
+    "e4 e6 d4 d5",
+    "e4 c6 d4 d5",
+    "e4 d5 exd5 Qxd5",
+    "d4 d5 c4 e6",
+    "d4 d5 c4 c6",
+    "d4 Nf6 c4 g6",
+    "d4 Nf6 c4 e6 Nc3 Bb4",
+    "c4 e5",
+    "Nf3 d5 g3",
+];
+
+/**
+ Configuración de uno de los motores del match, leída de un texto con pares clave=valor, por
+ ejemplo "nombre=nuevo prof=5 sin=lmr,null" o "motor=mcts iteraciones=800".
+*/
+#[derive(Clone)]
+pub struct EngineSettings {
+    pub name: String,
+    pub search: SearchConfig,
+    pub limits: SearchLimits,
+    pub threads: usize,
+    // Fuerza limitada con un nivel o un Elo; sin ella se busca con `limits`
+    pub strength: Option<Strength>,
+    // Con configuración de MCTS se usa ese motor en lugar de alfa-beta
+    pub mcts: Option<MctsConfig>,
+}
+
+impl EngineSettings {
+    /**
+     Lee la configuración de un motor.
+     # Arguments
+     * `text` - Los pares clave=valor separados por espacios: nombre, prof, nodos, tiempo (ms),
+       hilos, sin, nivel, elo, motor, politica, iteraciones y exploracion.
+     # Returns
+     La configuración, o un mensaje con la opción que no se pudo leer.
+    */
+    pub fn parse(text: &str) -> Result<EngineSettings, String> {
+        let mut settings = EngineSettings {
+            name: text.to_string(),
+            search: SearchConfig::default(),
+            limits: SearchLimits {
+                depth: DEFAULT_MATCH_DEPTH,
+                time: None,
+                nodes: None,
+            },
+            threads: 1,
+            strength: None,
+            mcts: None,
+        };
+
+        let mut mcts = MctsConfig::default();
+        let mut use_mcts = false;
+
+        for option in text.split_whitespace() {
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(format!("Opción sin valor: {}", option));
+            };
+
+            let invalid = || format!("Valor inválido para {}: {}", key, value);
+
+            match key {
+                "nombre" => settings.name = value.to_string(),
+                "prof" => settings.limits.depth = value.parse().map_err(|_| invalid())?,
+                "nodos" => settings.limits.nodes = Some(value.parse().map_err(|_| invalid())?),
+                "tiempo" => {
+                    let millis = value.parse().map_err(|_| invalid())?;
+                    settings.limits.time = Some(Duration::from_millis(millis));
+                }
+                "hilos" => settings.threads = value.parse().map_err(|_| invalid())?,
+                "sin" => {
+                    for heuristic in value.split(',') {
+                        if !settings.search.disable(heuristic) {
+                            return Err(format!("Heurística desconocida: {}", heuristic));
+                        }
+                    }
+                }
+                "nivel" => {
+                    let level = DifficultyLevel::from_name(value).ok_or_else(invalid)?;
+                    settings.strength = Some(level.strength());
+                }
+                "elo" => {
+                    let elo = value.parse().map_err(|_| invalid())?;
+                    settings.strength = Some(Strength::from_elo(elo));
+                }
+                "motor" => match value {
+                    "alfabeta" => use_mcts = false,
+                    "mcts" => use_mcts = true,
+                    _ => return Err(invalid()),
+                },
+                "politica" => mcts.policy = MctsPolicy::from_name(value).ok_or_else(invalid)?,
+                "iteraciones" => mcts.iterations = value.parse().map_err(|_| invalid())?,
+                "exploracion" => mcts.exploration = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("Opción desconocida: {}", key)),
+            }
+        }
+
+        if use_mcts {
+            settings.mcts = Some(mcts);
+        }
+
+        if settings.name.is_empty() {
+            settings.name = "base".to_string();
+        }
+
+        Ok(settings)
+    }
+}
+
+// Un motor listo para jugar una partida, con su propia tabla de transposición
+struct MatchEngine {
+    settings: EngineSettings,
+    engine: Engine,
+    mcts: Option<MctsEngine>,
+    random: Random,
+}
+
+impl MatchEngine {
+    fn new(settings: &EngineSettings) -> Self {
+        let mut engine = Engine::new(settings.search);
+        engine.set_threads(settings.threads);
+
+        MatchEngine {
+            settings: settings.clone(),
+            engine,
+            mcts: settings.mcts.map(MctsEngine::new),
+            random: Random::from_time(),
+        }
+    }
+
+    fn best_move(&mut self, chessboard: &Chessboard) -> Option<Move> {
+        if let Some(mcts) = &mut self.mcts {
+            return mcts.search(chessboard).best_move;
+        }
+
+        match &self.settings.strength {
+            Some(strength) => {
+                choose_move(&mut self.engine, chessboard, strength, &mut self.random).best_move
+            }
+            None => self.engine.search(chessboard, self.settings.limits).best_move,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Sprt {
+    // Hipótesis nula y alternativa: diferencia de Elo del primer motor sobre el segundo
+    pub elo0: f64,
+    pub elo1: f64,
+    // Probabilidades de error aceptadas
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    // Límites del logaritmo de la razón de verosimilitud: por debajo se acepta H0, por encima H1
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SprtDecision {
+    AcceptH0,
+    AcceptH1,
+}
+
+pub struct MatchOptions {
+    pub games: usize,
+    pub concurrency: usize,
+    // Medias jugadas tras las que la partida se da por tablas
+    pub max_plies: usize,
+    pub openings: Vec<Chessboard>,
+    pub sprt: Option<Sprt>,
+}
+
+// Victorias, tablas y derrotas del primer motor
+#[derive(Clone, Copy, Default)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Puntos por partida del primer motor, entre 0 y 1
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // Varianza del resultado de una partida
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games().max(1) as f64;
+
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+
+    /**
+     Diferencia de Elo del primer motor con el margen de error del 95%.
+     # Returns
+     La diferencia y el margen, o `None` si todavía no se puede calcular porque alguno ganó todo.
+    */
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score();
+        if self.games() == 0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((score - margin).max(1e-6));
+        let high = elo_from_score((score + margin).min(1.0 - 1e-6));
+
+        Some((elo_from_score(score), (high - low) / 2.0))
+    }
+
+    // Logaritmo de la razón de verosimilitud entre las dos hipótesis, con la aproximación normal
+    pub fn llr(&self, sprt: &Sprt) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+
+        let score0 = score_from_elo(sprt.elo0);
+        let score1 = score_from_elo(sprt.elo1);
+
+        self.games() as f64 * (score1 - score0) * (2.0 * self.score() - score0 - score1)
+            / (2.0 * variance)
+    }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// Lo que se informa al terminar cada partida
+pub struct GameReport {
+    pub number: usize,
+    pub opening: usize,
+    // Si el primer motor jugó con blancas
+    pub first_is_white: bool,
+    pub result: GameResult,
+    pub reason: &'static str,
+    pub plies: usize,
+}
+
+pub struct MatchSummary {
+    pub stats: MatchStats,
+    pub decision: Option<SprtDecision>,
+}
+
+/**
+ Juega un match entre dos motores: cada apertura se juega dos veces cambiando los colores y varias
+ partidas se juegan a la vez. Con SPRT el match se detiene en cuanto una hipótesis se acepta.
+ # Arguments
+ * `first` - El motor que se mide, por ejemplo con un cambio nuevo.
+ * `second` - El motor de referencia.
+ * `options` - Las partidas, la concurrencia, las aperturas y los parámetros del SPRT.
+ * `progress` - Se llama al terminar cada partida con su resultado y las estadísticas acumuladas.
+ # Returns
+ Las estadísticas finales y la decisión del SPRT, si se llegó a alguna.
+*/
+pub fn run_match(
+    first: &EngineSettings,
+    second: &EngineSettings,
+    options: &MatchOptions,
+    mut progress: impl FnMut(&GameReport, &MatchStats),
+) -> MatchSummary {
+    let next_game = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut stats = MatchStats::default();
+    let mut decision = None;
+
+    thread::scope(|scope| {
+        for _ in 0..options.concurrency.max(1) {
+            let sender = sender.clone();
+            let (next_game, stop) = (&next_game, &stop);
+
+            scope.spawn(move || loop {
+                let number = next_game.fetch_add(1, Ordering::Relaxed);
+                if number >= options.games || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // Cada apertura se juega dos veces seguidas, con los colores cambiados
+                let opening = (number / 2) % options.openings.len();
+                let first_is_white = number % 2 == 0;
+                let (white, black) = if first_is_white {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+
+                let (result, reason, plies) =
+                    play_game(&options.openings[opening], white, black, options.max_plies);
+
+                let report = GameReport {
+                    number: number + 1,
+                    opening,
+                    first_is_white,
+                    result,
+                    reason,
+                    plies,
+                };
+
+                if sender.send(report).is_err() {
+                    break;
+                }
+            });
+        }
+
+        // Solo los hilos tienen emisores, así el ciclo termina cuando todos terminan
+        drop(sender);
+
+        for report in receiver {
+            match (report.result, report.first_is_white) {
+                (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => stats.wins += 1,
+                (GameResult::WhiteWins, false) | (GameResult::BlackWins, true) => {
+                    stats.losses += 1
+                }
+                _ => stats.draws += 1,
+            }
+
+            progress(&report, &stats);
+
+            if let (Some(sprt), None) = (&options.sprt, decision) {
+                let (lower, upper) = sprt.bounds();
+                let llr = stats.llr(sprt);
+
+                if llr <= lower {
+                    decision = Some(SprtDecision::AcceptH0);
+                } else if llr >= upper {
+                    decision = Some(SprtDecision::AcceptH1);
+                }
+
+                // Las partidas que ya empezaron terminan y se cuentan, pero no empiezan otras
+                if decision.is_some() {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    });
+
+    MatchSummary { stats, decision }
+}
+
+/**
+ Juega una partida desde la apertura hasta el mate, el ahogado, la triple repetición, el material
+ insuficiente o el límite de medias jugadas. El motor que no devuelve un movimiento legal pierde.
+ # Returns
+ El resultado, el motivo del final y las medias jugadas.
+*/
+fn play_game(
+    opening: &Chessboard,
+    white: &EngineSettings,
+    black: &EngineSettings,
+    max_plies: usize,
+) -> (GameResult, &'static str, usize) {
+    let mut engines = [MatchEngine::new(white), MatchEngine::new(black)];
+    let mut chessboard = opening.clone();
+    let mut repetitions: HashMap<u64, u32> = HashMap::new();
+
+    for ply in 0..max_plies {
+        let count = repetitions.entry(zobrist::hash(&chessboard)).or_default();
+        *count += 1;
+        if *count >= 3 {
+            return (GameResult::Draw, "triple repetición", ply);
+        }
+
+        if insufficient_material(&chessboard) {
+            return (GameResult::Draw, "material insuficiente", ply);
+        }
+
+        let turn = match chessboard.player_turn {
+            ChessPieceColor::White => 0,
+            ChessPieceColor::Black => 1,
+        };
+
+        let legal_moves = generate_moves(&chessboard, false);
+        if legal_moves.is_empty() {
+            if is_check(&chessboard, chessboard.player_turn).is_none() {
+                return (GameResult::Draw, "ahogado", ply);
+            }
+            return (opponent_wins(chessboard.player_turn), "mate", ply);
+        }
+
+        // Un motor que no responde o juega un movimiento ilegal pierde la partida
+        let chosen = engines[turn].best_move(&chessboard).and_then(|chess_move| {
+            legal_moves
+                .into_iter()
+                .find(|(legal_move, _)| *legal_move == chess_move)
+        });
+
+        let Some((_, new_chessboard)) = chosen else {
+            return (opponent_wins(chessboard.player_turn), "movimiento ilegal", ply);
+        };
+
+        chessboard = new_chessboard;
+    }
+
+    (GameResult::Draw, "límite de jugadas", max_plies)
+}
+
+// La victoria del rival del color que pierde
+fn opponent_wins(loser: ChessPieceColor) -> GameResult {
+    match loser {
+        ChessPieceColor::White => GameResult::BlackWins,
+        ChessPieceColor::Black => GameResult::WhiteWins,
+    }
+}
+
+// Solo quedan los reyes, o los reyes y una pieza menor
+pub fn insufficient_material(chessboard: &Chessboard) -> bool {
+    let mut minor_pieces = 0;
+
+    for piece in chessboard.board.iter().flatten().flatten() {
+        match piece.piece {
+            ChessPieceType::King => {}
+            ChessPieceType::Knight | ChessPieceType::Bishop => minor_pieces += 1,
+            _ => return false,
+        }
+    }
+
+    minor_pieces <= 1
+}
+
+/**
+ Lee las aperturas de un archivo con una por línea, en FEN o como jugadas SAN desde la posición
+ inicial, por ejemplo "1. e4 e5 2. Nf3". Las líneas vacías y las que empiezan con "#" se ignoran.
+ # Arguments
+ * `path` - La ruta del archivo.
+ # Returns
+ Las posiciones de salida, o un mensaje con la línea que no se pudo leer.
+*/
+pub fn load_openings<P: AsRef<Path>>(path: P) -> Result<Vec<Chessboard>, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let mut openings = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let opening = Chessboard::from_fen(line)
+            .ok()
+            .or_else(|| play_opening(line))
+            .ok_or_else(|| format!("Apertura inválida en la línea {}: {}", number + 1, line))?;
+        openings.push(opening);
+    }
+
+    if openings.is_empty() {
+        return Err("El archivo no tiene aperturas".to_string());
+    }
+
+    Ok(openings)
+}
+
+pub fn default_openings() -> Vec<Chessboard> {
+    DEFAULT_OPENINGS
+        .iter()
+        .filter_map(|moves| play_opening(moves))
+        .collect()
+}
+
+// Juega las jugadas SAN desde la posición inicial, saltando los números de jugada
+fn play_opening(moves: &str) -> Option<Chessboard> {
+    let mut chessboard = Chessboard::new();
+
+    for san in moves.split_whitespace() {
+        let san = san.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if san.is_empty() {
+            continue;
+        }
+
+        chessboard = parse_san(&chessboard, san)?.1;
+    }
+
+    Some(chessboard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Chessboard {
+        Chessboard::from_fen(fen).ok().unwrap()
+    }
+
+    fn settings(text: &str) -> EngineSettings {
+        EngineSettings::parse(text).unwrap()
+    }
+
+    #[test]
+    fn an_engine_without_a_move_forfeits() {
+        let opening = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let silent = settings("motor=mcts iteraciones=0");
+        let engine = settings("prof=1");
+
+        let (result, reason, plies) = play_game(&opening, &silent, &engine, 10);
+        assert!(result == GameResult::BlackWins);
+        assert_eq!(reason, "movimiento ilegal");
+        assert_eq!(plies, 0);
+    }
+
+    #[test]
+    fn mate_and_stalemate_end_the_game() {
+        let engine = settings("prof=1");
+
+        let mated = board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        let (result, reason, _) = play_game(&mated, &engine, &engine, 10);
+        assert!(result == GameResult::BlackWins);
+        assert_eq!(reason, "mate");
+
+        let stalemate = board("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        let (result, reason, _) = play_game(&stalemate, &engine, &engine, 10);
+        assert!(result == GameResult::Draw);
+        assert_eq!(reason, "ahogado");
+    }
+}
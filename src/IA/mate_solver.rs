@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+
+use super::move_generator::{generate_moves, Move};
+use super::san::to_san;
+use super::zobrist;
+
+// Una jugada del atacante con todas las defensas posibles y cómo sigue el mate contra cada una
+pub struct MateLine {
+    pub san: String,
+    pub replies: Vec<MateReply>,
+}
+
+pub struct MateReply {
+    pub san: String,
+    // En el mate inverso la última respuesta es el mate y no tiene continuación
+    pub continuation: Option<MateLine>,
+}
+
+// Un intento del atacante que no da mate y la defensa que lo refuta
+pub struct Refutation {
+    pub san: String,
+    // `None` si el intento deja al rival sin jugadas legales
+    pub defence: Option<String>,
+}
+
+pub enum MateResult {
+    // Jugadas clave que fuerzan el mate, cada una con la cantidad mínima de jugadas
+    Solved(Vec<(u32, MateLine)>),
+    Refuted(Vec<Refutation>),
+    // Todas las soluciones del mate ayudado; vacío si no hay ninguna
+    Helpmates(Vec<String>),
+}
+
+// Géneros de problemas: en todos el bando en turno es el que empieza
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProblemType {
+    // El bando en turno da mate contra cualquier defensa
+    Direct,
+    // Los dos bandos cooperan para que el bando en turno reciba mate
+    Helpmate,
+    // El bando en turno obliga al rival a darle mate
+    Selfmate,
+}
+
+/**
+ Resuelve problemas de mate en N: demuestra que hay solución en N jugadas o menos contra cualquier
+ defensa, o la refuta. Varias claves o soluciones indican que el problema está cocinado.
+*/
+pub struct MateSolver {
+    problem_type: ProblemType,
+    cache: HashMap<(u64, u32), bool>,
+    pub nodes: u64,
+}
+
+impl MateSolver {
+    pub fn new(problem_type: ProblemType) -> Self {
+        MateSolver {
+            problem_type,
+            cache: HashMap::new(),
+            nodes: 0,
+        }
+    }
+
+    /**
+     Busca todas las jugadas clave que fuerzan mate en `moves` jugadas o menos.
+     # Arguments
+     * `chessboard` - La posición del problema, con el atacante en turno.
+     * `moves` - La cantidad máxima de jugadas del atacante.
+     # Returns
+     Las claves con el árbol completo de defensas, ordenadas de la más corta a la más larga, o
+     los intentos con la defensa que refuta cada uno si no hay mate.
+    */
+    pub fn solve(&mut self, chessboard: &Chessboard, moves: u32) -> MateResult {
+        // Sin jugadas no hay nada que demostrar ni intentos que refutar
+        if moves == 0 {
+            return match self.problem_type {
+                ProblemType::Helpmate => MateResult::Helpmates(Vec::new()),
+                _ => MateResult::Refuted(Vec::new()),
+            };
+        }
+
+        if self.problem_type == ProblemType::Helpmate {
+            let mut solutions = Vec::new();
+            self.helpmates(chessboard, moves * 2, &mut Vec::new(), &mut solutions);
+            return MateResult::Helpmates(solutions);
+        }
+
+        let mut keys = Vec::new();
+
+        for (chess_move, new_chessboard) in generate_moves(chessboard, false) {
+            if let Some(length) = (1..=moves).find(|length| self.forces(&new_chessboard, *length))
+            {
+                keys.push((length, self.mate_line(chessboard, chess_move, length)));
+            }
+        }
+
+        if !keys.is_empty() {
+            keys.sort_by_key(|(length, _)| *length);
+            return MateResult::Solved(keys);
+        }
+
+        let refutations = generate_moves(chessboard, false)
+            .into_iter()
+            .map(|(chess_move, new_chessboard)| {
+                let defence = generate_moves(&new_chessboard, false)
+                    .into_iter()
+                    .find(|(_, defended)| {
+                        !self.is_final_reply(defended) && !self.attacker_wins(defended, moves - 1)
+                    })
+                    .map(|(defence, _)| to_san(&new_chessboard, defence));
+
+                Refutation {
+                    san: to_san(chessboard, chess_move),
+                    defence,
+                }
+            })
+            .collect();
+
+        MateResult::Refuted(refutations)
+    }
+
+    // Después de la jugada del atacante, el objetivo se cumple en `moves` jugadas o menos
+    fn forces(&mut self, chessboard: &Chessboard, moves: u32) -> bool {
+        match self.problem_type {
+            ProblemType::Selfmate => self.selfmate_defender_loses(chessboard, moves),
+            _ => self.defender_loses(chessboard, moves - 1),
+        }
+    }
+
+    // En el mate inverso la defensa que da mate termina el problema
+    fn is_final_reply(&self, chessboard: &Chessboard) -> bool {
+        self.problem_type == ProblemType::Selfmate && is_mate(chessboard)
+    }
+
+    // El bando en turno cumple el objetivo en `moves` jugadas o menos
+    fn attacker_wins(&mut self, chessboard: &Chessboard, moves: u32) -> bool {
+        if moves == 0 {
+            return false;
+        }
+
+        let key = (zobrist::hash(chessboard), moves);
+        if let Some(wins) = self.cache.get(&key) {
+            return *wins;
+        }
+
+        let wins = generate_moves(chessboard, false)
+            .iter()
+            .any(|(_, new_chessboard)| {
+                // En la última jugada del mate directo solo sirven los movimientos que dan jaque
+                if moves == 1
+                    && self.problem_type == ProblemType::Direct
+                    && is_check(new_chessboard, new_chessboard.player_turn).is_none()
+                {
+                    return false;
+                }
+
+                self.forces(new_chessboard, moves)
+            });
+
+        self.cache.insert(key, wins);
+        wins
+    }
+
+    // El bando en turno recibe mate en `moves` jugadas del atacante o menos, defienda como defienda
+    fn defender_loses(&mut self, chessboard: &Chessboard, moves: u32) -> bool {
+        self.nodes += 1;
+        let defences = generate_moves(chessboard, false);
+
+        // Sin movimientos legales es mate o ahogado
+        if defences.is_empty() {
+            return is_check(chessboard, chessboard.player_turn).is_some();
+        }
+
+        if moves == 0 {
+            return false;
+        }
+
+        defences
+            .iter()
+            .all(|(_, defended)| self.attacker_wins(defended, moves))
+    }
+
+    // El rival en turno queda obligado a dar mate en `moves` jugadas suyas o menos
+    fn selfmate_defender_loses(&mut self, chessboard: &Chessboard, moves: u32) -> bool {
+        self.nodes += 1;
+
+        let defences = generate_moves(chessboard, false);
+
+        // Si el rival no puede mover, recibió mate o quedó ahogado: no es un mate inverso
+        if defences.is_empty() {
+            return false;
+        }
+
+        defences.iter().all(|(_, defended)| {
+            is_mate(defended) || (moves > 1 && self.attacker_wins(defended, moves - 1))
+        })
+    }
+
+    // Busca todas las secuencias de `plies` medias jugadas que terminan en mate
+    fn helpmates(
+        &mut self,
+        chessboard: &Chessboard,
+        plies: u32,
+        path: &mut Vec<String>,
+        solutions: &mut Vec<String>,
+    ) -> bool {
+        let key = (zobrist::hash(chessboard), plies);
+        if self.cache.contains_key(&key) {
+            return false;
+        }
+
+        self.nodes += 1;
+
+        let mut found = false;
+        for (chess_move, new_chessboard) in generate_moves(chessboard, false) {
+            path.push(to_san(chessboard, chess_move));
+
+            if plies == 1 {
+                if is_mate(&new_chessboard) {
+                    solutions.push(format_helpmate(path));
+                    found = true;
+                }
+            } else if self.helpmates(&new_chessboard, plies - 1, path, solutions) {
+                found = true;
+            }
+
+            path.pop();
+        }
+
+        // Solo se recuerdan las posiciones sin solución, las demás hay que recorrerlas de nuevo
+        if !found {
+            self.cache.insert(key, false);
+        }
+
+        found
+    }
+
+    // Arma el árbol de la clave: cada defensa con la continuación más corta del atacante
+    fn mate_line(&mut self, chessboard: &Chessboard, chess_move: Move, moves: u32) -> MateLine {
+        let new_chessboard = play(chessboard, chess_move);
+        let mut replies = Vec::new();
+
+        for (defence, defended) in generate_moves(&new_chessboard, false) {
+            if self.is_final_reply(&defended) {
+                replies.push(MateReply {
+                    san: to_san(&new_chessboard, defence),
+                    continuation: None,
+                });
+                continue;
+            }
+
+            let continuation = (1..moves).find_map(|length| {
+                generate_moves(&defended, false)
+                    .into_iter()
+                    .find(|(_, attacked)| self.forces(attacked, length))
+                    .map(|(next_move, _)| (next_move, length))
+            });
+
+            if let Some((next_move, length)) = continuation {
+                replies.push(MateReply {
+                    san: to_san(&new_chessboard, defence),
+                    continuation: Some(self.mate_line(&defended, next_move, length)),
+                });
+            }
+        }
+
+        MateLine {
+            san: to_san(chessboard, chess_move),
+            replies,
+        }
+    }
+}
+
+// El bando en turno está en jaque y no tiene movimientos legales
+fn is_mate(chessboard: &Chessboard) -> bool {
+    is_check(chessboard, chessboard.player_turn).is_some()
+        && generate_moves(chessboard, false).is_empty()
+}
+
+// Tablero después de un movimiento legal
+fn play(chessboard: &Chessboard, chess_move: Move) -> Chessboard {
+    generate_moves(chessboard, false)
+        .into_iter()
+        .find(|(legal_move, _)| *legal_move == chess_move)
+        .map(|(_, new_chessboard)| new_chessboard)
+        .unwrap()
+}
+
+/**
+ Escribe el árbol de una solución, una defensa por línea, con la numeración de las jugadas.
+ # Arguments
+ * `line` - La jugada clave con sus defensas.
+ * `lines` - Donde se agregan las líneas de texto.
+*/
+pub fn format_mate_line(line: &MateLine, lines: &mut Vec<String>) {
+    lines.push(format!("1. {}!", line.san));
+
+    for reply in line.replies.iter() {
+        format_reply(reply, 1, "  ", lines);
+    }
+}
+
+fn format_reply(reply: &MateReply, move_number: u32, indent: &str, lines: &mut Vec<String>) {
+    let Some(continuation) = &reply.continuation else {
+        lines.push(format!("{}{}... {}", indent, move_number, reply.san));
+        return;
+    };
+
+    lines.push(format!(
+        "{}{}... {} {}. {}",
+        indent,
+        move_number,
+        reply.san,
+        move_number + 1,
+        continuation.san
+    ));
+
+    let indent = format!("{}  ", indent);
+    for sub_reply in continuation.replies.iter() {
+        format_reply(sub_reply, move_number + 1, &indent, lines);
+    }
+}
+
+// Numera una solución del mate ayudado, por ejemplo "1. Kh8 Qg7#"
+fn format_helpmate(path: &[String]) -> String {
+    path.chunks(2)
+        .enumerate()
+        .map(|(index, pair)| format!("{}. {}", index + 1, pair.join(" ")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(fen: &str, moves: u32, problem_type: ProblemType) -> MateResult {
+        let chessboard = Chessboard::from_fen(fen).ok().unwrap();
+        MateSolver::new(problem_type).solve(&chessboard, moves)
+    }
+
+    // Las claves de la solución con la cantidad de jugadas de cada una
+    fn keys(result: MateResult) -> Vec<(u32, String)> {
+        match result {
+            MateResult::Solved(keys) => keys
+                .into_iter()
+                .map(|(length, line)| (length, line.san))
+                .collect(),
+            _ => panic!("se esperaba una solución"),
+        }
+    }
+
+    #[test]
+    fn finds_a_direct_mate_in_one() {
+        let keys = keys(solve(
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+            1,
+            ProblemType::Direct,
+        ));
+        assert_eq!(keys, vec![(1, "Rd8#".to_string())]);
+    }
+
+    #[test]
+    fn finds_a_direct_mate_in_two() {
+        let fen = "7k/8/8/8/8/8/R7/1R4K1 w - - 0 1";
+        assert!(matches!(
+            solve(fen, 1, ProblemType::Direct),
+            MateResult::Refuted(_)
+        ));
+
+        let keys = keys(solve(fen, 2, ProblemType::Direct));
+        assert!(keys.iter().all(|(length, _)| *length == 2));
+        assert!(keys.contains(&(2, "Rb7".to_string())));
+    }
+
+    #[test]
+    fn refutes_a_try_with_the_escape() {
+        let MateResult::Refuted(refutations) = solve(
+            "6k1/5pp1/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+            1,
+            ProblemType::Direct,
+        ) else {
+            panic!("no hay mate en 1");
+        };
+
+        let try_move = refutations
+            .iter()
+            .find(|refutation| refutation.san == "Rd8+")
+            .unwrap();
+        assert_eq!(try_move.defence.as_deref(), Some("Kh7"));
+    }
+
+    #[test]
+    fn finds_a_helpmate_in_one() {
+        let MateResult::Helpmates(solutions) =
+            solve("7k/8/6K1/8/8/8/8/R7 b - - 0 1", 1, ProblemType::Helpmate)
+        else {
+            panic!("se esperaba un mate ayudado");
+        };
+        assert_eq!(solutions, vec!["1. Kg8 Ra8#".to_string()]);
+    }
+
+    #[test]
+    fn finds_a_selfmate_in_one() {
+        let keys = keys(solve(
+            "8/8/8/R7/8/6pk/8/6BK w - - 0 1",
+            1,
+            ProblemType::Selfmate,
+        ));
+        assert!(keys.contains(&(1, "Ra4".to_string())));
+    }
+
+    #[test]
+    fn zero_moves_has_no_solution() {
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1";
+        assert!(
+            matches!(solve(fen, 0, ProblemType::Direct), MateResult::Refuted(r) if r.is_empty())
+        );
+        assert!(
+            matches!(solve(fen, 0, ProblemType::Selfmate), MateResult::Refuted(r) if r.is_empty())
+        );
+        assert!(
+            matches!(solve(fen, 0, ProblemType::Helpmate), MateResult::Helpmates(s) if s.is_empty())
+        );
+    }
+}
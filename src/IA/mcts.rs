@@ -0,0 +1,353 @@
+use std::time::{Duration, Instant};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+
+use super::evaluation::{evaluate, piece_value};
+use super::move_generator::{generate_moves, Move};
+use super::move_ordering::captured_piece;
+use super::random::Random;
+
+// Escala de la evaluación al convertirla en probabilidad de ganar, en centipeones
+const EVALUATION_SCALE: f64 = 400.0;
+
+// Cómo se estima el valor de una posición nueva del árbol
+#[derive(Clone, Copy, PartialEq)]
+pub enum MctsPolicy {
+    // Partida simulada con movimientos al azar
+    Random,
+    // Partida simulada que prefiere las capturas más valiosas
+    Captures,
+    // Sin simulación, solo la evaluación estática
+    Evaluation,
+}
+
+impl MctsPolicy {
+    pub const ALL: [MctsPolicy; 3] = [
+        MctsPolicy::Random,
+        MctsPolicy::Captures,
+        MctsPolicy::Evaluation,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MctsPolicy::Random => "aleatoria",
+            MctsPolicy::Captures => "capturas",
+            MctsPolicy::Evaluation => "evaluacion",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MctsPolicy> {
+        MctsPolicy::ALL.into_iter().find(|policy| policy.name() == name)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MctsConfig {
+    // Constante de exploración de UCT
+    pub exploration: f64,
+    pub iterations: u64,
+    pub time: Option<Duration>,
+    pub policy: MctsPolicy,
+    // Medias jugadas de cada simulación antes de evaluar la posición
+    pub rollout_depth: u32,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            exploration: 1.4,
+            iterations: 2_000,
+            time: Some(Duration::from_secs(5)),
+            policy: MctsPolicy::Captures,
+            rollout_depth: 8,
+        }
+    }
+}
+
+pub struct MctsResult {
+    pub best_move: Option<Move>,
+    // Visitas y probabilidad estimada de ganar del movimiento elegido
+    pub visits: u64,
+    pub win_rate: f64,
+    pub iterations: u64,
+    pub elapsed: Duration,
+}
+
+struct Node {
+    chess_move: Option<Move>,
+    chessboard: Chessboard,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // Movimientos que todavía no tienen nodo; `None` hasta que se generan
+    untried: Option<Vec<(Move, Chessboard)>>,
+    visits: u64,
+    // Suma de resultados para el jugador que hizo el movimiento que lleva a este nodo
+    value: f64,
+}
+
+/**
+ Motor alternativo basado en búsqueda de árbol Monte Carlo con UCT: en lugar de calcular todas
+ las variantes hasta una profundidad, simula muchas partidas y se concentra en las más prometedoras.
+*/
+pub struct MctsEngine {
+    pub config: MctsConfig,
+    random: Random,
+}
+
+impl MctsEngine {
+    pub fn new(config: MctsConfig) -> Self {
+        MctsEngine {
+            config,
+            random: Random::from_time(),
+        }
+    }
+
+    /**
+     Busca el mejor movimiento hasta completar las iteraciones o agotar el tiempo.
+     # Arguments
+     * `chessboard` - El tablero actual.
+     # Returns
+     El movimiento más visitado de la raíz, o `None` si no hay movimientos legales.
+    */
+    pub fn search(&mut self, chessboard: &Chessboard) -> MctsResult {
+        let start = Instant::now();
+        let mut tree = vec![Node {
+            chess_move: None,
+            chessboard: chessboard.clone(),
+            parent: None,
+            children: Vec::new(),
+            untried: None,
+            visits: 0,
+            value: 0.0,
+        }];
+
+        let mut iterations = 0;
+        while iterations < self.config.iterations {
+            if self.config.time.is_some_and(|time| start.elapsed() >= time) {
+                break;
+            }
+
+            let leaf = self.select_and_expand(&mut tree);
+            let result = self.simulate(&tree[leaf].chessboard);
+            backpropagate(&mut tree, leaf, result);
+            iterations += 1;
+
+            // Con un solo movimiento legal no hay nada que pensar
+            if tree[0].untried.as_ref().is_some_and(|untried| untried.is_empty())
+                && tree[0].children.len() < 2
+            {
+                break;
+            }
+        }
+
+        let best = tree[0]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|child| tree[*child].visits);
+
+        MctsResult {
+            best_move: best.and_then(|child| tree[child].chess_move),
+            visits: best.map_or(0, |child| tree[child].visits),
+            win_rate: best.map_or(0.0, |child| {
+                tree[child].value / tree[child].visits.max(1) as f64
+            }),
+            iterations,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    // Baja por el árbol eligiendo con UCT y agrega un hijo nuevo en el primer nodo sin expandir
+    fn select_and_expand(&mut self, tree: &mut Vec<Node>) -> usize {
+        let mut index = 0;
+
+        loop {
+            if tree[index].untried.is_none() {
+                tree[index].untried = Some(generate_moves(&tree[index].chessboard, false));
+            }
+
+            let untried = tree[index].untried.as_mut().unwrap();
+            if !untried.is_empty() {
+                let position = self.random.range(untried.len() as u64) as usize;
+                let (chess_move, chessboard) = untried.swap_remove(position);
+
+                tree.push(Node {
+                    chess_move: Some(chess_move),
+                    chessboard,
+                    parent: Some(index),
+                    children: Vec::new(),
+                    untried: None,
+                    visits: 0,
+                    value: 0.0,
+                });
+
+                let child = tree.len() - 1;
+                tree[index].children.push(child);
+                return child;
+            }
+
+            // Posición terminal: mate o ahogado
+            if tree[index].children.is_empty() {
+                return index;
+            }
+
+            let parent_visits = (tree[index].visits.max(1) as f64).ln();
+            index = *tree[index]
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    let a = uct(&tree[**a], parent_visits, self.config.exploration);
+                    let b = uct(&tree[**b], parent_visits, self.config.exploration);
+                    a.total_cmp(&b)
+                })
+                .unwrap();
+        }
+    }
+
+    // Resultado estimado entre 0 y 1 para el jugador en turno
+    fn simulate(&mut self, chessboard: &Chessboard) -> f64 {
+        let mut current = chessboard.clone();
+        // Los resultados se invierten con cada media jugada para seguir viéndolos desde la posición inicial
+        let mut same_side = true;
+        let plies = match self.config.policy {
+            MctsPolicy::Evaluation => 0,
+            _ => self.config.rollout_depth,
+        };
+
+        for ply in 0..=plies {
+            let mut moves = generate_moves(&current, false);
+
+            if moves.is_empty() {
+                let result = if is_check(&current, current.player_turn).is_some() {
+                    0.0
+                } else {
+                    0.5
+                };
+                return if same_side { result } else { 1.0 - result };
+            }
+
+            if ply == plies {
+                break;
+            }
+
+            let position = self.rollout_move(&current, &moves);
+            current = moves.swap_remove(position).1;
+            same_side = !same_side;
+        }
+
+        let result = win_probability(evaluate(&current));
+        if same_side {
+            result
+        } else {
+            1.0 - result
+        }
+    }
+
+    // Elige el siguiente movimiento de la simulación según la política
+    fn rollout_move(&mut self, chessboard: &Chessboard, moves: &[(Move, Chessboard)]) -> usize {
+        if self.config.policy == MctsPolicy::Captures {
+            let best_capture = moves
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (chess_move, _))| {
+                    captured_piece(chessboard, chess_move).map(|piece| (index, piece_value(piece)))
+                })
+                .max_by_key(|(_, value)| *value);
+
+            if let Some((index, _)) = best_capture {
+                return index;
+            }
+        }
+
+        self.random.range(moves.len() as u64) as usize
+    }
+}
+
+fn uct(node: &Node, parent_visits: f64, exploration: f64) -> f64 {
+    if node.visits == 0 {
+        return f64::INFINITY;
+    }
+
+    let visits = node.visits as f64;
+    node.value / visits + exploration * (parent_visits / visits).sqrt()
+}
+
+// Suma el resultado a cada nodo del camino, alternando el punto de vista de cada jugador
+fn backpropagate(tree: &mut [Node], leaf: usize, result: f64) {
+    // El resultado es para el jugador en turno en la hoja, el nodo guarda el del que movió
+    let mut value = 1.0 - result;
+    let mut index = Some(leaf);
+
+    while let Some(current) = index {
+        tree[current].visits += 1;
+        tree[current].value += value;
+        value = 1.0 - value;
+        index = tree[current].parent;
+    }
+}
+
+// Convierte la evaluación en centipeones en una probabilidad de ganar
+fn win_probability(score: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-score as f64 / EVALUATION_SCALE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Chessboard {
+        Chessboard::from_fen(fen).ok().unwrap()
+    }
+
+    fn engine(iterations: u64, policy: MctsPolicy) -> MctsEngine {
+        MctsEngine::new(MctsConfig {
+            iterations,
+            time: None,
+            policy,
+            ..MctsConfig::default()
+        })
+    }
+
+    #[test]
+    fn fixed_iterations_return_a_legal_move() {
+        let chessboard = Chessboard::new();
+        let legal_moves = generate_moves(&chessboard, false);
+
+        for policy in MctsPolicy::ALL {
+            let result = engine(300, policy).search(&chessboard);
+            assert_eq!(result.iterations, 300);
+            assert!(result.visits > 0);
+            assert!(legal_moves
+                .iter()
+                .any(|(legal_move, _)| Some(*legal_move) == result.best_move));
+        }
+    }
+
+    #[test]
+    fn no_move_without_legal_moves() {
+        let mated = board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert!(engine(100, MctsPolicy::Captures)
+            .search(&mated)
+            .best_move
+            .is_none());
+
+        let stalemate = board("7k/8/8/8/8/8/5q2/7K w - - 0 1");
+        assert!(engine(100, MctsPolicy::Random)
+            .search(&stalemate)
+            .best_move
+            .is_none());
+    }
+
+    #[test]
+    fn a_forced_move_needs_a_single_iteration() {
+        let chessboard = board("7k/8/8/8/8/8/6q1/7K w - - 0 1");
+        let result = engine(1_000, MctsPolicy::Evaluation).search(&chessboard);
+
+        assert_eq!(result.iterations, 1);
+        assert_eq!(
+            result.best_move.map(Move::to_coordinates).as_deref(),
+            Some("h1g2")
+        );
+    }
+}
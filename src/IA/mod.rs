@@ -0,0 +1,20 @@
+pub mod bench;
+pub mod book_builder;
+pub mod evaluation;
+pub mod hint;
+pub mod match_runner;
+pub mod mate_solver;
+pub mod mcts;
+pub mod move_generator;
+pub mod move_ordering;
+pub mod opening_book;
+pub mod pgn;
+pub mod polyglot;
+pub mod random;
+pub mod san;
+pub mod search;
+pub mod strength;
+pub mod syzygy;
+pub mod transposition_table;
+pub mod tuning;
+pub mod zobrist;
@@ -0,0 +1,348 @@
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::{
+    is_check, validate_move, validate_move_with_promotion,
+};
+use crate::pieces::piece_type::{ChessPiece, ChessPieceColor, ChessPieceType};
+
+// Piezas a las que puede coronar un peón, de la más a la menos valiosa
+const PROMOTION_PIECES: [ChessPieceType; 4] = [
+    ChessPieceType::Queen,
+    ChessPieceType::Rook,
+    ChessPieceType::Bishop,
+    ChessPieceType::Knight,
+];
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (2, 1),
+    (2, -1),
+    (-2, 1),
+    (-2, -1),
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+];
+
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Move {
+    pub from: [usize; 2],
+    pub to: [usize; 2],
+    pub promotion: Option<ChessPieceType>,
+}
+
+impl Move {
+    // Devuelve el movimiento en notación de coordenadas, por ejemplo "e2e4" o "e7e8q"
+    pub fn to_coordinates(self) -> String {
+        let promotion = match self.promotion {
+            Some(ChessPieceType::Queen) => "q",
+            Some(ChessPieceType::Rook) => "r",
+            Some(ChessPieceType::Bishop) => "b",
+            Some(ChessPieceType::Knight) => "n",
+            _ => "",
+        };
+
+        format!(
+            "{}{}{}",
+            square_name(self.from),
+            square_name(self.to),
+            promotion
+        )
+    }
+}
+
+// Devuelve el nombre de la casilla, por ejemplo [6, 4] => "e2"
+pub fn square_name(position: [usize; 2]) -> String {
+    format!("{}{}", (b'a' + position[1] as u8) as char, 8 - position[0])
+}
+
+// Índice de la casilla entre 0 y 63
+pub fn square_index(position: [usize; 2]) -> usize {
+    position[0] * 8 + position[1]
+}
+
+/**
+ Genera los movimientos legales del jugador en turno, validando cada candidato con `validate_move`.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `captures_only` - Si es `true`, solo se generan capturas y coronaciones.
+ # Returns
+ Retorna un vector con cada movimiento legal y el tablero resultante después de realizarlo.
+*/
+pub fn generate_moves(chessboard: &Chessboard, captures_only: bool) -> Vec<(Move, Chessboard)> {
+    let mut moves: Vec<(Move, Chessboard)> = Vec::new();
+
+    for row in chessboard.board.iter() {
+        for piece in row.iter().flatten() {
+            // Solo generamos los movimientos del jugador en turno
+            if piece.color != chessboard.player_turn {
+                continue;
+            }
+
+            for to in candidate_targets(chessboard, piece, captures_only) {
+                let to_position = chessboard.board[to[0]][to[1]];
+
+                // Las coronaciones generan un movimiento por cada pieza posible
+                if piece.piece == ChessPieceType::Pawn && (to[0] == 0 || to[0] == 7) {
+                    for promotion in PROMOTION_PIECES {
+                        if let Ok(new_chessboard) = validate_move_with_promotion(
+                            chessboard,
+                            &Some(*piece),
+                            &to_position,
+                            to,
+                            Some(promotion),
+                        ) {
+                            let chess_move = Move {
+                                from: piece.position,
+                                to,
+                                promotion: Some(promotion),
+                            };
+                            moves.push((chess_move, new_chessboard));
+                        }
+                    }
+                    continue;
+                }
+
+                // El enroque necesita validaciones adicionales
+                let is_castling = piece.piece == ChessPieceType::King
+                    && (piece.position[1] as i32 - to[1] as i32).abs() == 2;
+
+                if is_castling && !castling_is_safe(chessboard, piece, to) {
+                    continue;
+                }
+
+                if let Ok(new_chessboard) =
+                    validate_move(chessboard, &Some(*piece), &to_position, to)
+                {
+                    if is_castling && is_check(&new_chessboard, piece.color).is_some() {
+                        continue;
+                    }
+
+                    let chess_move = Move {
+                        from: piece.position,
+                        to,
+                        promotion: None,
+                    };
+                    moves.push((chess_move, new_chessboard));
+                }
+            }
+        }
+    }
+
+    moves
+}
+
+/**
+ Obtiene las casillas a las que una pieza podría moverse, sin validar si el movimiento es legal.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `piece` - La pieza de la cual se buscan los destinos.
+ * `captures_only` - Si es `true`, solo se devuelven capturas y coronaciones.
+ # Returns
+ Retorna un vector con las posiciones destino candidatas.
+*/
+fn candidate_targets(
+    chessboard: &Chessboard,
+    piece: &ChessPiece,
+    captures_only: bool,
+) -> Vec<[usize; 2]> {
+    let mut targets: Vec<[usize; 2]> = Vec::new();
+    let (x, y) = (piece.position[0] as i32, piece.position[1] as i32);
+
+    match piece.piece {
+        ChessPieceType::Pawn => {
+            let (direction, start_row, last_row) = match piece.color {
+                ChessPieceColor::White => (-1, 6, 0),
+                ChessPieceColor::Black => (1, 1, 7),
+            };
+
+            // Avance de una casilla (en modo capturas solo si corona)
+            if let Some(forward) = offset_position(x + direction, y) {
+                let promotes = forward[0] == last_row;
+                if chessboard.board[forward[0]][forward[1]].is_none()
+                    && (!captures_only || promotes)
+                {
+                    targets.push(forward);
+
+                    // Avance doble desde la fila inicial
+                    if !captures_only && x == start_row {
+                        if let Some(double) = offset_position(x + 2 * direction, y) {
+                            if chessboard.board[double[0]][double[1]].is_none() {
+                                targets.push(double);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Capturas en diagonal
+            for dy in [-1, 1] {
+                if let Some(capture) = offset_position(x + direction, y + dy) {
+                    if is_enemy(chessboard, piece, capture) {
+                        targets.push(capture);
+                    }
+                }
+            }
+        }
+        ChessPieceType::Knight => {
+            for (dx, dy) in KNIGHT_OFFSETS {
+                if let Some(to) = offset_position(x + dx, y + dy) {
+                    push_target(chessboard, piece, to, captures_only, &mut targets);
+                }
+            }
+        }
+        ChessPieceType::King => {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if let Some(to) = offset_position(x + dx, y + dy) {
+                        push_target(chessboard, piece, to, captures_only, &mut targets);
+                    }
+                }
+            }
+
+            // Enroque corto y largo si el rey no se ha movido
+            if !captures_only && piece.before_position.is_none() && y == 4 {
+                targets.push([piece.position[0], 6]);
+                targets.push([piece.position[0], 2]);
+            }
+        }
+        ChessPieceType::Rook => slide_targets(
+            chessboard,
+            piece,
+            &ROOK_DIRECTIONS,
+            captures_only,
+            &mut targets,
+        ),
+        ChessPieceType::Bishop => slide_targets(
+            chessboard,
+            piece,
+            &BISHOP_DIRECTIONS,
+            captures_only,
+            &mut targets,
+        ),
+        ChessPieceType::Queen => {
+            slide_targets(
+                chessboard,
+                piece,
+                &ROOK_DIRECTIONS,
+                captures_only,
+                &mut targets,
+            );
+            slide_targets(
+                chessboard,
+                piece,
+                &BISHOP_DIRECTIONS,
+                captures_only,
+                &mut targets,
+            );
+        }
+    }
+
+    targets
+}
+
+// Recorre cada dirección hasta encontrar el borde del tablero o una pieza
+fn slide_targets(
+    chessboard: &Chessboard,
+    piece: &ChessPiece,
+    directions: &[(i32, i32)],
+    captures_only: bool,
+    targets: &mut Vec<[usize; 2]>,
+) {
+    for (dx, dy) in directions {
+        let (mut x, mut y) = (piece.position[0] as i32 + dx, piece.position[1] as i32 + dy);
+
+        while let Some(to) = offset_position(x, y) {
+            push_target(chessboard, piece, to, captures_only, targets);
+
+            if chessboard.board[to[0]][to[1]].is_some() {
+                break;
+            }
+
+            x += dx;
+            y += dy;
+        }
+    }
+}
+
+// Agrega el destino si está vacío o tiene una pieza enemiga
+fn push_target(
+    chessboard: &Chessboard,
+    piece: &ChessPiece,
+    to: [usize; 2],
+    captures_only: bool,
+    targets: &mut Vec<[usize; 2]>,
+) {
+    match chessboard.board[to[0]][to[1]] {
+        Some(_) if is_enemy(chessboard, piece, to) => targets.push(to),
+        None if !captures_only => targets.push(to),
+        _ => {}
+    }
+}
+
+fn is_enemy(chessboard: &Chessboard, piece: &ChessPiece, position: [usize; 2]) -> bool {
+    match chessboard.board[position[0]][position[1]] {
+        Some(other) => other.color != piece.color,
+        None => false,
+    }
+}
+
+fn offset_position(x: i32, y: i32) -> Option<[usize; 2]> {
+    if (0..8).contains(&x) && (0..8).contains(&y) {
+        Some([x as usize, y as usize])
+    } else {
+        None
+    }
+}
+
+/**
+ Valida las condiciones del enroque que `validate_move` no revisa: el rey no puede estar en jaque,
+ no puede atravesar una casilla atacada y el camino hacia la torre debe estar libre.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `king` - El rey que va a enrocar.
+ * `to` - La posición destino del rey.
+ # Returns
+ `bool` - Retorna `true` si el enroque es seguro.
+*/
+fn castling_is_safe(chessboard: &Chessboard, king: &ChessPiece, to: [usize; 2]) -> bool {
+    if is_check(chessboard, king.color).is_some() {
+        return false;
+    }
+
+    let row = king.position[0];
+    let step: i32 = if to[1] > king.position[1] { 1 } else { -1 };
+    let crossed = [row, (king.position[1] as i32 + step) as usize];
+
+    // En el enroque largo la casilla b también debe estar libre
+    if step < 0 && chessboard.board[row][1].is_some() {
+        return false;
+    }
+
+    if chessboard.board[crossed[0]][crossed[1]].is_some()
+        || chessboard.board[to[0]][to[1]].is_some()
+    {
+        return false;
+    }
+
+    // El rey no puede atravesar una casilla atacada
+    validate_move(chessboard, &Some(*king), &None, crossed).is_ok()
+}
+
+/**
+ Busca entre los movimientos legales el que corresponde a la notación de coordenadas indicada.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `coordinates` - El movimiento en notación de coordenadas, por ejemplo "e2e4".
+ # Returns
+ Retorna el movimiento y el tablero resultante, o `None` si el movimiento no es legal.
+*/
+pub fn find_move(chessboard: &Chessboard, coordinates: &str) -> Option<(Move, Chessboard)> {
+    let coordinates = coordinates.trim().to_lowercase();
+
+    generate_moves(chessboard, false)
+        .into_iter()
+        .find(|(chess_move, _)| chess_move.to_coordinates() == coordinates)
+}
@@ -0,0 +1,150 @@
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::ChessPieceType;
+
+use super::evaluation::piece_value;
+use super::move_generator::{square_index, Move};
+use super::search::{SearchConfig, MAX_PLY};
+
+// Prioridades de cada grupo de movimientos
+const HASH_MOVE_SCORE: i32 = 10_000_000;
+const CAPTURE_SCORE: i32 = 1_000_000;
+const FIRST_KILLER_SCORE: i32 = 900_000;
+const SECOND_KILLER_SCORE: i32 = 800_000;
+const HISTORY_LIMIT: i32 = 700_000;
+
+pub struct MoveOrderer {
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    history: Vec<i32>,
+}
+
+impl MoveOrderer {
+    pub fn new() -> Self {
+        MoveOrderer {
+            killers: [[None; 2]; MAX_PLY],
+            history: vec![0; 64 * 64],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        self.history.iter_mut().for_each(|score| *score = 0);
+    }
+
+    /**
+     Asigna una puntuación a cada movimiento para ordenarlos: primero el movimiento de la tabla de
+     transposición, luego las capturas por MVV-LVA, los movimientos asesinos y por último el historial.
+     # Arguments
+     * `chessboard` - El tablero antes de realizar los movimientos.
+     * `moves` - Los movimientos a puntuar.
+     * `hash_move` - El mejor movimiento guardado en la tabla de transposición, si existe.
+     * `ply` - La distancia a la raíz de la búsqueda.
+     * `config` - Qué heurísticas de ordenamiento están activas.
+     # Returns
+     Un vector con la puntuación de cada movimiento, en el mismo orden.
+    */
+    pub fn score_moves(
+        &self,
+        chessboard: &Chessboard,
+        moves: &[(Move, Chessboard)],
+        hash_move: Option<Move>,
+        ply: usize,
+        config: &SearchConfig,
+    ) -> Vec<i32> {
+        moves
+            .iter()
+            .map(|(chess_move, _)| {
+                if config.hash_move && hash_move == Some(*chess_move) {
+                    return HASH_MOVE_SCORE;
+                }
+
+                if config.mvv_lva {
+                    if let Some(victim) = captured_piece(chessboard, chess_move) {
+                        let attacker = chessboard.board[chess_move.from[0]][chess_move.from[1]]
+                            .map(|piece| piece.piece)
+                            .unwrap_or(ChessPieceType::Pawn);
+                        return CAPTURE_SCORE + mvv_lva(victim, attacker);
+                    }
+
+                    if let Some(promotion) = chess_move.promotion {
+                        return CAPTURE_SCORE + piece_value(promotion);
+                    }
+                }
+
+                if config.killer_moves && ply < MAX_PLY {
+                    if self.killers[ply][0] == Some(*chess_move) {
+                        return FIRST_KILLER_SCORE;
+                    }
+                    if self.killers[ply][1] == Some(*chess_move) {
+                        return SECOND_KILLER_SCORE;
+                    }
+                }
+
+                if config.history_heuristic {
+                    return self.history[history_index(chess_move)];
+                }
+
+                0
+            })
+            .collect()
+    }
+
+    // Guarda un movimiento tranquilo que produjo un corte beta
+    pub fn store_killer(&mut self, chess_move: Move, ply: usize) {
+        if ply >= MAX_PLY || self.killers[ply][0] == Some(chess_move) {
+            return;
+        }
+
+        self.killers[ply][1] = self.killers[ply][0];
+        self.killers[ply][0] = Some(chess_move);
+    }
+
+    pub fn is_killer(&self, chess_move: Move, ply: usize) -> bool {
+        ply < MAX_PLY && self.killers[ply].contains(&Some(chess_move))
+    }
+
+    // Aumenta la puntuación de historial de un movimiento tranquilo según la profundidad
+    pub fn update_history(&mut self, chess_move: Move, depth: i32) {
+        let index = history_index(&chess_move);
+        self.history[index] += depth * depth;
+
+        // Si el historial se desborda lo reducimos a la mitad para no superar a los asesinos
+        if self.history[index] >= HISTORY_LIMIT {
+            self.history.iter_mut().for_each(|score| *score /= 2);
+        }
+    }
+}
+
+// Víctima más valiosa, atacante menos valioso
+pub fn mvv_lva(victim: ChessPieceType, attacker: ChessPieceType) -> i32 {
+    piece_value(victim) * 10 - piece_value(attacker) / 10
+}
+
+// Devuelve el tipo de pieza capturada por el movimiento, si la hay
+pub fn captured_piece(chessboard: &Chessboard, chess_move: &Move) -> Option<ChessPieceType> {
+    chessboard.board[chess_move.to[0]][chess_move.to[1]].map(|piece| piece.piece)
+}
+
+fn history_index(chess_move: &Move) -> usize {
+    square_index(chess_move.from) * 64 + square_index(chess_move.to)
+}
+
+/**
+ Selecciona el movimiento con mayor puntuación entre los que quedan por revisar y lo coloca en la
+ posición `index`, de modo que no es necesario ordenar la lista completa si hay un corte temprano.
+ # Arguments
+ * `moves` - Los movimientos con su tablero resultante.
+ * `scores` - La puntuación de cada movimiento.
+ * `index` - La posición que se va a llenar.
+*/
+pub fn pick_next_move(moves: &mut [(Move, Chessboard)], scores: &mut [i32], index: usize) {
+    let mut best = index;
+
+    for i in (index + 1)..moves.len() {
+        if scores[i] > scores[best] {
+            best = i;
+        }
+    }
+
+    moves.swap(index, best);
+    scores.swap(index, best);
+}
@@ -0,0 +1,135 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::chessboard::chessboard::Chessboard;
+
+use super::move_generator::{generate_moves, Move};
+use super::polyglot::{decode_move, polyglot_key};
+use super::random::Random;
+
+// Cada entrada de un libro Polyglot ocupa 16 bytes
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct BookEntry {
+    pub key: u64,
+    pub encoded_move: u16,
+    pub weight: u16,
+}
+
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    /**
+     Lee un libro de aperturas en formato Polyglot `.bin`.
+     # Arguments
+     * `path` - La ruta local del archivo.
+     # Returns
+     Retorna el libro, o un error si el archivo no se puede leer o no tiene un tamaño válido.
+    */
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        OpeningBook::from_bytes(&fs::read(path)?)
+    }
+
+    /**
+     Lee un libro Polyglot que ya está en memoria.
+     # Arguments
+     * `bytes` - El contenido del archivo `.bin`.
+     # Returns
+     Retorna el libro, o un error si el tamaño no es múltiplo de una entrada.
+    */
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "El libro no tiene el formato Polyglot",
+            ));
+        }
+
+        // Todos los campos se guardan en big endian, el campo de aprendizaje se ignora
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                encoded_move: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+            })
+            .collect();
+
+        // Los libros vienen ordenados por clave, pero no confiamos en ello para buscar
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(OpeningBook { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /**
+     Obtiene los movimientos del libro para la posición, descartando los que no son legales.
+     # Arguments
+     * `chessboard` - Una referencia al tablero de ajedrez actual.
+     # Returns
+     Un vector con cada movimiento y su peso.
+    */
+    pub fn moves(&self, chessboard: &Chessboard) -> Vec<(Move, u16)> {
+        let key = polyglot_key(chessboard);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+
+        let candidates: Vec<(Move, u16)> = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .map(|entry| (decode_move(chessboard, entry.encoded_move), entry.weight))
+            .collect();
+
+        if candidates.is_empty() {
+            return candidates;
+        }
+
+        let legal_moves = generate_moves(chessboard, false);
+
+        candidates
+            .into_iter()
+            .filter(|(chess_move, _)| {
+                legal_moves
+                    .iter()
+                    .any(|(legal_move, _)| legal_move == chess_move)
+            })
+            .collect()
+    }
+
+    /**
+     Elige un movimiento del libro al azar, con probabilidad proporcional a su peso.
+     # Arguments
+     * `chessboard` - Una referencia al tablero de ajedrez actual.
+     * `random` - El generador de números aleatorios.
+     # Returns
+     El movimiento elegido, o `None` si la posición no está en el libro.
+    */
+    pub fn pick_move(&self, chessboard: &Chessboard, random: &mut Random) -> Option<Move> {
+        let moves = self.moves(chessboard);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+
+        // Si todos los pesos son cero elegimos de manera uniforme
+        if total == 0 {
+            if moves.is_empty() {
+                return None;
+            }
+            return Some(moves[random.range(moves.len() as u64) as usize].0);
+        }
+
+        let mut choice = random.range(total);
+        for (chess_move, weight) in moves {
+            if choice < weight as u64 {
+                return Some(chess_move);
+            }
+            choice -= weight as u64;
+        }
+
+        None
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl GameResult {
+    pub fn from_token(token: &str) -> Option<GameResult> {
+        match token {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn to_token(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    fn new() -> Self {
+        PgnGame {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.moves.is_empty()
+    }
+}
+
+/**
+ Lee todas las partidas de un archivo PGN local.
+ # Arguments
+ * `path` - La ruta del archivo PGN.
+ # Returns
+ Retorna las partidas encontradas, o un error si no se pudo leer el archivo.
+*/
+pub fn read_pgn_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<PgnGame>> {
+    let bytes = fs::read(path)?;
+    Ok(parse_pgn(&String::from_utf8_lossy(&bytes)))
+}
+
+/**
+ Separa un texto PGN en partidas, ignorando comentarios, variantes y anotaciones numéricas.
+ # Arguments
+ * `text` - El contenido del archivo PGN.
+ # Returns
+ Un vector con las etiquetas, los movimientos en notación algebraica y el resultado de cada partida.
+*/
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::new();
+    let mut chars = text.chars().peekable();
+    let mut variation_depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Etiquetas como [White "Nombre"]
+            '[' if variation_depth == 0 => {
+                // Una etiqueta después de los movimientos empieza una nueva partida
+                if !game.moves.is_empty() {
+                    games.push(std::mem::replace(&mut game, PgnGame::new()));
+                }
+
+                let mut tag = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    tag.push(c);
+                }
+
+                if let Some((name, value)) = tag.trim().split_once(' ') {
+                    game.tags
+                        .push((name.to_string(), value.trim().trim_matches('"').to_string()));
+                }
+            }
+            // Comentarios entre llaves
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            // Comentarios hasta el final de la línea
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}()[];".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+
+                if variation_depth > 0 {
+                    continue;
+                }
+
+                if let Some(result) = GameResult::from_token(&token) {
+                    game.result = result;
+                    games.push(std::mem::replace(&mut game, PgnGame::new()));
+                    continue;
+                }
+
+                // Quitamos el número de jugada, por ejemplo "12." o "12...Nf6", sin tocar "0-0"
+                let san = match token.find(|c: char| !c.is_ascii_digit()) {
+                    Some(index) if token[index..].starts_with('.') => {
+                        token[index..].trim_start_matches('.')
+                    }
+                    Some(_) => token.as_str(),
+                    None => "",
+                };
+
+                if !san.is_empty() && !san.starts_with('$') {
+                    game.moves.push(san.to_string());
+                }
+            }
+        }
+    }
+
+    if !game.is_empty() {
+        games.push(game);
+    }
+
+    // Si el resultado no aparece al final de los movimientos usamos la etiqueta Result
+    for game in games.iter_mut() {
+        if game.result == GameResult::Unknown {
+            if let Some(result) = game.tag("Result").and_then(GameResult::from_token) {
+                game.result = result;
+            }
+        }
+    }
+
+    games
+}
@@ -0,0 +1,41 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Generador pseudoaleatorio xorshift64*, suficiente para variar las jugadas del motor
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random {
+            // El estado nunca puede ser cero
+            state: seed.max(1),
+        }
+    }
+
+    // Crea un generador con una semilla distinta en cada ejecución
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0x2545_F491_4F6C_DD1D);
+
+        Random::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Número entre 0 y `limit` (sin incluirlo)
+    pub fn range(&mut self, limit: u64) -> u64 {
+        if limit == 0 {
+            return 0;
+        }
+
+        self.next_u64() % limit
+    }
+}
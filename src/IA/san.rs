@@ -0,0 +1,159 @@
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::ChessPieceType;
+
+use super::move_generator::{generate_moves, square_name, Move};
+
+// Letra de cada pieza en la notación algebraica estándar (en inglés, como en PGN)
+fn piece_letter(piece: ChessPieceType) -> &'static str {
+    match piece {
+        ChessPieceType::King => "K",
+        ChessPieceType::Queen => "Q",
+        ChessPieceType::Rook => "R",
+        ChessPieceType::Bishop => "B",
+        ChessPieceType::Knight => "N",
+        ChessPieceType::Pawn => "",
+    }
+}
+
+/**
+ Busca el movimiento legal que corresponde a un texto en notación algebraica estándar.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `san` - El movimiento en notación algebraica, por ejemplo "Nbd7", "O-O" o "e8=Q+".
+ # Returns
+ Retorna el movimiento y el tablero resultante, o `None` si no corresponde a ningún movimiento legal.
+*/
+pub fn parse_san(chessboard: &Chessboard, san: &str) -> Option<(Move, Chessboard)> {
+    let wanted = normalize(san);
+    let legal_moves = generate_moves(chessboard, false);
+
+    let index = legal_moves
+        .iter()
+        .position(|(m, _)| san_without_suffix(chessboard, &legal_moves, *m) == wanted)?;
+
+    legal_moves.into_iter().nth(index)
+}
+
+/**
+ Escribe un movimiento legal en notación algebraica estándar, con "+" si da jaque y "#" si da mate.
+ # Arguments
+ * `chessboard` - El tablero antes del movimiento.
+ * `chess_move` - El movimiento.
+ # Returns
+ El movimiento en notación algebraica, por ejemplo "Nxe5+" o "O-O".
+*/
+pub fn to_san(chessboard: &Chessboard, chess_move: Move) -> String {
+    let legal_moves = generate_moves(chessboard, false);
+    let mut san = san_without_suffix(chessboard, &legal_moves, chess_move);
+
+    let new_chessboard = legal_moves
+        .iter()
+        .find(|(legal_move, _)| *legal_move == chess_move)
+        .map(|(_, new_chessboard)| new_chessboard);
+
+    if let Some(new_chessboard) = new_chessboard {
+        if is_check(new_chessboard, new_chessboard.player_turn).is_some() {
+            if generate_moves(new_chessboard, false).is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+    }
+
+    san
+}
+
+// Quita los jaques, las anotaciones y las variantes de escritura del enroque y la coronación
+fn normalize(san: &str) -> String {
+    let mut san = san
+        .trim()
+        .trim_end_matches(['+', '#', '!', '?'])
+        .trim_end_matches("e.p.")
+        .replace('0', "O");
+
+    // Algunas partidas escriben la coronación sin el signo igual, por ejemplo "e8Q"
+    let chars: Vec<char> = san.chars().collect();
+    if chars.len() >= 3
+        && "QRBN".contains(chars[chars.len() - 1])
+        && chars[chars.len() - 2].is_ascii_digit()
+    {
+        san.insert(san.len() - 1, '=');
+    }
+
+    san
+}
+
+fn san_without_suffix(
+    chessboard: &Chessboard,
+    legal_moves: &[(Move, Chessboard)],
+    chess_move: Move,
+) -> String {
+    let piece = match chessboard.board[chess_move.from[0]][chess_move.from[1]] {
+        Some(piece) => piece,
+        None => return chess_move.to_coordinates(),
+    };
+
+    // Enroque
+    if piece.piece == ChessPieceType::King
+        && (chess_move.from[1] as i32 - chess_move.to[1] as i32).abs() == 2
+    {
+        return if chess_move.to[1] == 6 {
+            "O-O".to_string()
+        } else {
+            "O-O-O".to_string()
+        };
+    }
+
+    let is_capture = chessboard.board[chess_move.to[0]][chess_move.to[1]].is_some();
+    let destination = square_name(chess_move.to);
+    let mut san = String::new();
+
+    if piece.piece == ChessPieceType::Pawn {
+        if is_capture {
+            san.push_str(&square_name(chess_move.from)[0..1]);
+            san.push('x');
+        }
+        san.push_str(&destination);
+
+        if let Some(promotion) = chess_move.promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+
+        return san;
+    }
+
+    san.push_str(piece_letter(piece.piece));
+
+    // Si otra pieza igual puede llegar a la misma casilla hay que desambiguar
+    let rivals: Vec<[usize; 2]> = legal_moves
+        .iter()
+        .map(|(m, _)| *m)
+        .filter(|m| m.to == chess_move.to && m.from != chess_move.from)
+        .filter(|m| {
+            matches!(chessboard.board[m.from[0]][m.from[1]], Some(other) if other.piece == piece.piece)
+        })
+        .map(|m| m.from)
+        .collect();
+
+    if !rivals.is_empty() {
+        let from_name = square_name(chess_move.from);
+
+        if rivals.iter().all(|from| from[1] != chess_move.from[1]) {
+            san.push_str(&from_name[0..1]);
+        } else if rivals.iter().all(|from| from[0] != chess_move.from[0]) {
+            san.push_str(&from_name[1..2]);
+        } else {
+            san.push_str(&from_name);
+        }
+    }
+
+    if is_capture {
+        san.push('x');
+    }
+    san.push_str(&destination);
+
+    san
+}
@@ -0,0 +1,1158 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+use super::evaluation::evaluate;
+use super::move_generator::{generate_moves, Move};
+use super::move_ordering::{captured_piece, pick_next_move, MoveOrderer};
+use super::opening_book::OpeningBook;
+use super::random::Random;
+use super::syzygy::{RootProbe, Tablebase, Wdl};
+use super::transposition_table::{Bound, TranspositionEntry, TranspositionTable};
+use super::zobrist;
+
+pub const MAX_PLY: usize = 64;
+pub const MATE_SCORE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+
+// Las victorias de las tablas de finales valen menos que cualquier mate encontrado en la búsqueda
+pub const TABLEBASE_WIN_SCORE: i32 = MATE_SCORE - 2 * MAX_PLY as i32;
+
+// Tamaño por defecto de la tabla de transposición en megabytes
+pub const DEFAULT_HASH_MB: usize = 16;
+
+// Cada cuántos nodos se revisa si se acabó el tiempo
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+// Parámetros de la búsqueda selectiva
+const NULL_MOVE_MIN_DEPTH: i32 = 3;
+const NULL_MOVE_REDUCTION: i32 = 2;
+const LMR_MIN_DEPTH: i32 = 3;
+const LMR_MIN_MOVE_INDEX: usize = 3;
+const FUTILITY_MARGINS: [i32; 2] = [200, 500];
+const RAZORING_MARGINS: [i32; 2] = [300, 550];
+
+// Heurísticas de ordenamiento y de poda que se pueden activar o desactivar por separado
+#[derive(Clone, Copy)]
+pub struct SearchConfig {
+    pub hash_move: bool,
+    pub mvv_lva: bool,
+    pub killer_moves: bool,
+    pub history_heuristic: bool,
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub futility_pruning: bool,
+    pub razoring: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            hash_move: true,
+            mvv_lva: true,
+            killer_moves: true,
+            history_heuristic: true,
+            null_move: true,
+            late_move_reductions: true,
+            futility_pruning: true,
+            razoring: true,
+        }
+    }
+}
+
+impl SearchConfig {
+    /**
+     Desactiva una heurística a partir de su nombre, para medirla por separado.
+     # Arguments
+     * `name` - El nombre de la heurística: "orden", "null", "lmr", "futilidad" o "razoring".
+     # Returns
+     `bool` - Retorna `false` si el nombre no corresponde a ninguna heurística.
+    */
+    pub fn disable(&mut self, name: &str) -> bool {
+        match name {
+            "orden" => {
+                self.hash_move = false;
+                self.mvv_lva = false;
+                self.killer_moves = false;
+                self.history_heuristic = false;
+            }
+            "null" => self.null_move = false,
+            "lmr" => self.late_move_reductions = false,
+            "futilidad" => self.futility_pruning = false,
+            "razoring" => self.razoring = false,
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SearchLimits {
+    pub depth: u32,
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct SearchStats {
+    pub nodes: u64,
+    pub quiescence_nodes: u64,
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    pub null_move_cutoffs: u64,
+    pub late_move_reductions: u64,
+    pub futility_prunes: u64,
+    pub razoring_cutoffs: u64,
+    pub tablebase_hits: u64,
+}
+
+impl SearchStats {
+    // Suma los contadores de otra búsqueda, por ejemplo de otro hilo
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.quiescence_nodes += other.quiescence_nodes;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.null_move_cutoffs += other.null_move_cutoffs;
+        self.late_move_reductions += other.late_move_reductions;
+        self.futility_prunes += other.futility_prunes;
+        self.razoring_cutoffs += other.razoring_cutoffs;
+        self.tablebase_hits += other.tablebase_hits;
+    }
+
+    // Porcentaje de los cortes beta que se produjeron con el primer movimiento
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.beta_cutoffs == 0 {
+            return 0.0;
+        }
+
+        self.first_move_cutoffs as f64 * 100.0 / self.beta_cutoffs as f64
+    }
+}
+
+// Puntuación y variante principal de un movimiento de la raíz buscado con ventana completa
+#[derive(Clone)]
+pub struct RootMove {
+    pub chess_move: Move,
+    pub score: i32,
+    pub principal_variation: Vec<Move>,
+}
+
+// Qué se busca en la raíz
+#[derive(Clone, Copy, PartialEq)]
+enum RootSearch {
+    BestMove,
+    AllMoves,
+    MultiPv(usize),
+}
+
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub principal_variation: Vec<Move>,
+    pub stats: SearchStats,
+    pub elapsed: Duration,
+    pub from_book: bool,
+    pub tablebase: Option<RootProbe>,
+    pub root_moves: Vec<RootMove>,
+}
+
+pub struct Engine {
+    pub config: SearchConfig,
+    transposition_table: TranspositionTable,
+    orderer: MoveOrderer,
+    threads: usize,
+    stop: Arc<AtomicBool>,
+    book: Option<OpeningBook>,
+    tablebase: Option<Arc<Tablebase>>,
+    random: Random,
+}
+
+// Lo que se informa al completar cada profundidad, por ejemplo a una interfaz gráfica
+pub struct SearchInfo<'a> {
+    pub depth: u32,
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub principal_variation: &'a [Move],
+}
+
+// Función que recibe el progreso de la búsqueda desde el hilo principal
+pub type InfoCallback<'a> = &'a mut (dyn FnMut(&SearchInfo) + Send);
+
+// Estado propio de cada hilo de búsqueda; la tabla de transposición se comparte entre todos
+struct SearchThread<'a> {
+    config: SearchConfig,
+    transposition_table: &'a TranspositionTable,
+    tablebase: Option<&'a Tablebase>,
+    stop: &'a AtomicBool,
+    orderer: MoveOrderer,
+    stats: SearchStats,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    stopped: bool,
+    path: Vec<u64>,
+    root_best_move: Option<Move>,
+    // Movimientos de la raíz que no se buscan, porque ya forman parte de otra variante
+    excluded_root_moves: Vec<Move>,
+    // Solo el hilo principal informa su progreso
+    info: Option<InfoCallback<'a>>,
+    start: Instant,
+}
+
+impl Engine {
+    pub fn new(config: SearchConfig) -> Self {
+        Engine {
+            config,
+            transposition_table: TranspositionTable::new(DEFAULT_HASH_MB),
+            orderer: MoveOrderer::new(),
+            threads: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+            book: None,
+            tablebase: None,
+            random: Random::from_time(),
+        }
+    }
+
+    // Usa el libro de aperturas antes de buscar mientras la posición esté en él
+    pub fn set_book(&mut self, book: OpeningBook) {
+        self.book = Some(book);
+    }
+
+    // Consulta las tablas de finales Syzygy cuando quedan pocas piezas
+    pub fn set_tablebase(&mut self, tablebase: Arc<Tablebase>) {
+        self.tablebase = Some(tablebase);
+    }
+
+    // Cantidad de hilos que buscan a la vez; con un solo hilo la búsqueda es determinista
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /**
+     Permite detener desde otro hilo la búsqueda en curso, por ejemplo la que se hace mientras
+     piensa el rival. Si se activa antes de empezar, la búsqueda termina enseguida con el
+     movimiento de la tabla o el primero legal. La búsqueda la desactiva sola al terminar, así que
+     una parada pedida cuando ya terminó queda pendiente y detiene la búsqueda siguiente.
+    */
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    // Cambia el tamaño de la tabla de transposición, que empieza vacía
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.transposition_table = TranspositionTable::new(size_mb);
+    }
+
+    // Olvida todo lo aprendido en búsquedas anteriores
+    pub fn clear(&mut self) {
+        self.transposition_table.clear();
+        self.orderer.clear();
+    }
+
+    /**
+     Busca el mejor movimiento con profundización iterativa y alfa-beta, o lo toma del libro de
+     aperturas o de las tablas de finales si hay cargados y la posición está en ellos.
+     Con varios hilos se usa Lazy SMP: todos buscan la misma posición compartiendo la tabla de
+     transposición y el resultado es el del hilo principal.
+     # Arguments
+     * `chessboard` - El tablero en el que se va a buscar.
+     * `limits` - La profundidad máxima y el tiempo disponible.
+     # Returns
+     Retorna un `SearchResult` con el mejor movimiento, la evaluación, la variante principal y las
+     estadísticas de la última iteración completa.
+    */
+    pub fn search(&mut self, chessboard: &Chessboard, limits: SearchLimits) -> SearchResult {
+        self.run_search(chessboard, limits, RootSearch::BestMove, None)
+    }
+
+    /**
+     Igual que `search`, pero informa la profundidad, la puntuación, los nodos y la variante
+     principal cada vez que completa una iteración.
+     # Arguments
+     * `chessboard` - El tablero en el que se va a buscar.
+     * `limits` - La profundidad máxima, el tiempo y los nodos disponibles.
+     * `info` - La función que recibe el progreso.
+     # Returns
+     Retorna el mismo `SearchResult` que `search`.
+    */
+    pub fn search_with_info(
+        &mut self,
+        chessboard: &Chessboard,
+        limits: SearchLimits,
+        info: InfoCallback,
+    ) -> SearchResult {
+        self.run_search(chessboard, limits, RootSearch::BestMove, Some(info))
+    }
+
+    /**
+     Igual que `search`, pero busca cada movimiento de la raíz con ventana completa para conocer la
+     puntuación de todos, no solo la del mejor. Es más lento, así que se usa con poca profundidad.
+     # Arguments
+     * `chessboard` - El tablero en el que se va a buscar.
+     * `limits` - La profundidad máxima, el tiempo y los nodos disponibles.
+     # Returns
+     Retorna un `SearchResult` con `root_moves` ordenados del mejor al peor.
+    */
+    pub fn search_all_moves(&mut self, chessboard: &Chessboard, limits: SearchLimits) -> SearchResult {
+        self.run_search(chessboard, limits, RootSearch::AllMoves, None)
+    }
+
+    /**
+     Busca las mejores `lines` variantes de la posición para analizarla. Cada variante se busca
+     excluyendo en la raíz los movimientos de las variantes anteriores. No usa el libro ni la
+     consulta de la raíz en las tablas de finales, para mostrar siempre varias alternativas.
+     # Arguments
+     * `chessboard` - El tablero que se va a analizar.
+     * `limits` - La profundidad máxima, el tiempo y los nodos disponibles.
+     * `lines` - La cantidad de variantes.
+     # Returns
+     Retorna un `SearchResult` con las variantes en `root_moves`, de la mejor a la peor.
+    */
+    pub fn search_multi_pv(
+        &mut self,
+        chessboard: &Chessboard,
+        limits: SearchLimits,
+        lines: usize,
+    ) -> SearchResult {
+        self.run_search(chessboard, limits, RootSearch::MultiPv(lines.max(1)), None)
+    }
+
+    fn run_search(
+        &mut self,
+        chessboard: &Chessboard,
+        limits: SearchLimits,
+        root_search: RootSearch,
+        info: Option<InfoCallback>,
+    ) -> SearchResult {
+        let start = Instant::now();
+        let deadline = limits.time.map(|time| start + time);
+
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            principal_variation: Vec::new(),
+            stats: SearchStats::default(),
+            elapsed: Duration::ZERO,
+            from_book: false,
+            tablebase: None,
+            root_moves: Vec::new(),
+        };
+
+        let is_analysis = matches!(root_search, RootSearch::MultiPv(_));
+
+        // Si la posición está en el libro no es necesario buscar
+        if let Some(book) = self.book.as_ref().filter(|_| !is_analysis) {
+            if let Some(book_move) = book.pick_move(chessboard, &mut self.random) {
+                result.best_move = Some(book_move);
+                result.principal_variation = vec![book_move];
+                result.from_book = true;
+                result.elapsed = start.elapsed();
+                return result;
+            }
+        }
+
+        // Con pocas piezas las tablas de finales ya conocen el mejor movimiento
+        if let Some(probe) = self
+            .tablebase
+            .as_ref()
+            .filter(|_| !is_analysis)
+            .and_then(|tb| tb.probe_root(chessboard))
+        {
+            result.best_move = Some(probe.best_move);
+            result.score = tablebase_score(probe.wdl, 0);
+            result.principal_variation = vec![probe.best_move];
+            result.tablebase = Some(probe);
+            result.elapsed = start.elapsed();
+            return result;
+        }
+
+        let new_thread = |orderer| SearchThread {
+            config: self.config,
+            transposition_table: &self.transposition_table,
+            tablebase: self.tablebase.as_deref(),
+            stop: &self.stop,
+            orderer,
+            stats: SearchStats::default(),
+            deadline,
+            max_nodes: limits.nodes,
+            stopped: false,
+            path: Vec::new(),
+            root_best_move: None,
+            excluded_root_moves: Vec::new(),
+            info: None,
+            start,
+        };
+
+        // El hilo principal conserva los asesinos y el historial entre búsquedas
+        let mut main_thread = new_thread(std::mem::replace(&mut self.orderer, MoveOrderer::new()));
+        main_thread.info = info.map(|info| info as &mut (dyn FnMut(&SearchInfo) + Send));
+
+        let stats = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads)
+                .map(|index| {
+                    let mut helper = new_thread(MoveOrderer::new());
+                    scope.spawn(move || {
+                        helper.iterative_deepening(chessboard, limits.depth, index, None);
+                        helper.stats
+                    })
+                })
+                .collect();
+
+            match root_search {
+                RootSearch::BestMove => {
+                    main_thread.iterative_deepening(chessboard, limits.depth, 0, Some(&mut result))
+                }
+                RootSearch::AllMoves => {
+                    main_thread.score_root_moves(chessboard, limits.depth, &mut result)
+                }
+                RootSearch::MultiPv(lines) => {
+                    main_thread.multi_pv(chessboard, limits.depth, lines, &mut result)
+                }
+            }
+
+            // Cuando termina el hilo principal se detienen los demás
+            self.stop.store(true, Ordering::Relaxed);
+
+            let mut stats = main_thread.stats;
+            for helper in helpers {
+                stats.add(&helper.join().unwrap());
+            }
+
+            // Queda listo para la próxima búsqueda
+            self.stop.store(false, Ordering::Relaxed);
+            stats
+        });
+
+        self.orderer = main_thread.orderer;
+
+        result.stats = stats;
+        result.elapsed = start.elapsed();
+        result
+    }
+}
+
+impl SearchThread<'_> {
+    /**
+     Profundización iterativa de un hilo. Los hilos auxiliares empiezan en distintas profundidades
+     para que no busquen exactamente lo mismo que el principal.
+     # Arguments
+     * `chessboard` - El tablero en el que se va a buscar.
+     * `max_depth` - La profundidad máxima.
+     * `index` - El número del hilo, 0 para el principal.
+     * `result` - Donde el hilo principal guarda cada iteración completa.
+    */
+    fn iterative_deepening(
+        &mut self,
+        chessboard: &Chessboard,
+        max_depth: u32,
+        index: usize,
+        mut result: Option<&mut SearchResult>,
+    ) {
+        let max_depth = max_depth.max(1);
+        let first_depth = (1 + (index % 2) as u32).min(max_depth);
+        let mut completed = false;
+
+        for depth in first_depth..=max_depth {
+            self.root_best_move = None;
+            let score = self.negamax(chessboard, depth as i32, 0, -INFINITY, INFINITY, false);
+
+            // Si se acabó el tiempo descartamos la iteración incompleta
+            if self.stopped && completed {
+                break;
+            }
+            // Sin ninguna iteración completa el puntaje no sirve, pero hay que jugar algo
+            if self.stopped {
+                if let Some(result) = result.as_deref_mut() {
+                    result.best_move = self.fallback_move(chessboard);
+                    result.principal_variation = result.best_move.into_iter().collect();
+                }
+                break;
+            }
+            completed = true;
+
+            if let Some(result) = result.as_deref_mut() {
+                result.best_move = self.root_best_move;
+                result.score = score;
+                result.depth = depth;
+                result.principal_variation = self.principal_variation(chessboard, depth as usize);
+
+                if let Some(info) = self.info.as_mut() {
+                    info(&SearchInfo {
+                        depth,
+                        score,
+                        nodes: self.stats.nodes,
+                        elapsed: self.start.elapsed(),
+                        principal_variation: &result.principal_variation,
+                    });
+                }
+            }
+
+            if self.stopped || score.abs() >= MATE_SCORE - MAX_PLY as i32 {
+                break;
+            }
+        }
+    }
+
+    // El movimiento de la tabla de transposición si sigue siendo legal, o el primero legal
+    fn fallback_move(&self, chessboard: &Chessboard) -> Option<Move> {
+        let moves = generate_moves(chessboard, false);
+        let hash_move = self
+            .transposition_table
+            .probe(zobrist::hash(chessboard))
+            .and_then(|entry| entry.best_move)
+            .filter(|hash_move| moves.iter().any(|(chess_move, _)| chess_move == hash_move));
+
+        hash_move.or_else(|| moves.first().map(|(chess_move, _)| *chess_move))
+    }
+
+    // Profundización iterativa puntuando cada movimiento de la raíz con ventana completa
+    fn score_root_moves(&mut self, chessboard: &Chessboard, max_depth: u32, result: &mut SearchResult) {
+        let moves = generate_moves(chessboard, false);
+        let key = zobrist::hash(chessboard);
+
+        for depth in 1..=max_depth.max(1) {
+            let mut root_moves = Vec::with_capacity(moves.len());
+
+            self.path.push(key);
+            for (chess_move, new_chessboard) in moves.iter() {
+                let score = -self.negamax(new_chessboard, depth as i32 - 1, 1, -INFINITY, INFINITY, true);
+                if self.stopped {
+                    break;
+                }
+                root_moves.push(RootMove {
+                    chess_move: *chess_move,
+                    score,
+                    principal_variation: self.variation_after(*chess_move, new_chessboard, depth),
+                });
+            }
+            self.path.pop();
+
+            // Una iteración incompleta solo sirve si no hay ninguna anterior
+            if self.stopped && !result.root_moves.is_empty() {
+                break;
+            }
+
+            root_moves.sort_by_key(|root_move| -root_move.score);
+
+            if let Some(best) = root_moves.first() {
+                result.best_move = Some(best.chess_move);
+                result.score = best.score;
+                result.depth = depth;
+                result.principal_variation = best.principal_variation.clone();
+            }
+            result.root_moves = root_moves;
+
+            if self.stopped {
+                break;
+            }
+        }
+
+        // Detenida antes de puntuar un solo movimiento, igual hay que jugar algo
+        if result.best_move.is_none() {
+            result.best_move = self.fallback_move(chessboard);
+            result.principal_variation = result.best_move.into_iter().collect();
+        }
+    }
+
+    // Profundización iterativa buscando una variante tras otra, sin repetir el primer movimiento
+    fn multi_pv(
+        &mut self,
+        chessboard: &Chessboard,
+        max_depth: u32,
+        lines: usize,
+        result: &mut SearchResult,
+    ) {
+        let moves = generate_moves(chessboard, false);
+        let lines = lines.min(moves.len());
+
+        for depth in 1..=max_depth.max(1) {
+            let mut root_moves: Vec<RootMove> = Vec::with_capacity(lines);
+            self.excluded_root_moves.clear();
+
+            while root_moves.len() < lines {
+                self.root_best_move = None;
+                let score = self.negamax(chessboard, depth as i32, 0, -INFINITY, INFINITY, false);
+
+                let best_move = match self.root_best_move {
+                    Some(best_move) if !self.stopped => best_move,
+                    _ => break,
+                };
+
+                let new_chessboard = moves
+                    .iter()
+                    .find(|(chess_move, _)| *chess_move == best_move)
+                    .map(|(_, new_chessboard)| new_chessboard)
+                    .unwrap();
+
+                root_moves.push(RootMove {
+                    chess_move: best_move,
+                    score,
+                    principal_variation: self.variation_after(best_move, new_chessboard, depth),
+                });
+                self.excluded_root_moves.push(best_move);
+            }
+
+            self.excluded_root_moves.clear();
+
+            // Cada variante se buscó con distinta información en la tabla, así que las reordenamos
+            root_moves.sort_by_key(|root_move| -root_move.score);
+
+            // Las variantes de una iteración incompleta se descartan si hay una anterior
+            if self.stopped && !result.root_moves.is_empty() {
+                break;
+            }
+
+            if let Some(best) = root_moves.first() {
+                result.best_move = Some(best.chess_move);
+                result.score = best.score;
+                result.depth = depth;
+                result.principal_variation = best.principal_variation.clone();
+            }
+            result.root_moves = root_moves;
+
+            if self.stopped {
+                break;
+            }
+        }
+
+        // Detenida antes de puntuar un solo movimiento, igual hay que jugar algo
+        if result.best_move.is_none() {
+            result.best_move = self.fallback_move(chessboard);
+            result.principal_variation = result.best_move.into_iter().collect();
+        }
+    }
+
+    // Variante principal que empieza con un movimiento de la raíz
+    fn variation_after(&self, chess_move: Move, new_chessboard: &Chessboard, depth: u32) -> Vec<Move> {
+        let mut variation = vec![chess_move];
+        variation.extend(self.principal_variation(new_chessboard, depth.saturating_sub(1) as usize));
+        variation
+    }
+
+    fn negamax(
+        &mut self,
+        chessboard: &Chessboard,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        allow_null_move: bool,
+    ) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        let key = zobrist::hash(chessboard);
+
+        // Una posición repetida en la línea actual se considera tablas
+        if ply > 0 && self.path.contains(&key) {
+            return 0;
+        }
+
+        // Las tablas de finales dan el resultado exacto sin necesidad de seguir buscando
+        if ply > 0 {
+            if let Some(wdl) = self.tablebase.as_ref().and_then(|tb| tb.probe_wdl(chessboard)) {
+                self.stats.tablebase_hits += 1;
+                return tablebase_score(wdl, ply);
+            }
+        }
+
+        if depth <= 0 || ply >= MAX_PLY {
+            return self.quiescence(chessboard, ply, alpha, beta);
+        }
+
+        self.stats.nodes += 1;
+
+        // Consultamos la tabla de transposición
+        let mut hash_move = None;
+        if let Some(entry) = self.transposition_table.probe(key) {
+            hash_move = entry.best_move;
+
+            if ply > 0 && entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let in_check = is_check(chessboard, chessboard.player_turn).is_some();
+        let static_eval = evaluate(chessboard);
+        let is_mate_window = beta.abs() >= MATE_SCORE - MAX_PLY as i32;
+
+        // Razoring: si la evaluación está muy por debajo de alfa, lo confirmamos con capturas
+        if self.config.razoring
+            && ply > 0
+            && !in_check
+            && !is_mate_window
+            && depth <= RAZORING_MARGINS.len() as i32
+            && static_eval + RAZORING_MARGINS[depth as usize - 1] < alpha
+        {
+            let score = self.quiescence(chessboard, ply, alpha - 1, alpha);
+            if score < alpha {
+                self.stats.razoring_cutoffs += 1;
+                return score;
+            }
+        }
+
+        // Movimiento nulo: si pasar el turno sigue superando beta, la posición es demasiado buena
+        if self.config.null_move
+            && allow_null_move
+            && ply > 0
+            && !in_check
+            && !is_mate_window
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && static_eval >= beta
+            && has_non_pawn_material(chessboard, chessboard.player_turn)
+        {
+            let mut null_chessboard = chessboard.clone();
+            null_chessboard.player_turn = opposite_color(chessboard.player_turn);
+
+            let reduction = NULL_MOVE_REDUCTION + depth / 6;
+
+            self.path.push(key);
+            let score = -self.negamax(
+                &null_chessboard,
+                depth - 1 - reduction,
+                ply + 1,
+                -beta,
+                -beta + 1,
+                false,
+            );
+            self.path.pop();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                self.stats.null_move_cutoffs += 1;
+                return beta;
+            }
+        }
+
+        let mut moves = generate_moves(chessboard, false);
+
+        // Sin movimientos legales es jaque mate o rey ahogado
+        if moves.is_empty() {
+            if in_check {
+                return -MATE_SCORE + ply as i32;
+            }
+            return 0;
+        }
+
+        let mut scores = self
+            .orderer
+            .score_moves(chessboard, &moves, hash_move, ply, &self.config);
+
+        // Poda de futilidad: cerca de las hojas los movimientos tranquilos no alcanzan a alfa
+        let futile = self.config.futility_pruning
+            && ply > 0
+            && !in_check
+            && !is_mate_window
+            && depth <= FUTILITY_MARGINS.len() as i32
+            && static_eval + FUTILITY_MARGINS[depth as usize - 1] <= alpha;
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+
+        self.path.push(key);
+
+        for index in 0..moves.len() {
+            pick_next_move(&mut moves, &mut scores, index);
+            let (chess_move, new_chessboard) = &moves[index];
+
+            if ply == 0 && self.excluded_root_moves.contains(chess_move) {
+                continue;
+            }
+
+            let is_quiet =
+                chess_move.promotion.is_none() && captured_piece(chessboard, chess_move).is_none();
+            let gives_check = is_check(new_chessboard, new_chessboard.player_turn).is_some();
+
+            if futile && index > 0 && is_quiet && !gives_check {
+                self.stats.futility_prunes += 1;
+                continue;
+            }
+
+            // Reducción de movimientos tardíos: los movimientos tranquilos al final de la lista se
+            // buscan con menos profundidad y solo se repiten completos si superan a alfa
+            let reduction = if self.config.late_move_reductions
+                && depth >= LMR_MIN_DEPTH
+                && index >= LMR_MIN_MOVE_INDEX
+                && is_quiet
+                && !in_check
+                && !gives_check
+                && !self.orderer.is_killer(*chess_move, ply)
+            {
+                if index >= 2 * LMR_MIN_MOVE_INDEX && depth >= 2 * LMR_MIN_DEPTH {
+                    2
+                } else {
+                    1
+                }
+            } else {
+                0
+            };
+
+            let mut score;
+            if reduction > 0 {
+                self.stats.late_move_reductions += 1;
+                score = -self.negamax(
+                    new_chessboard,
+                    depth - 1 - reduction,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    true,
+                );
+
+                if score > alpha && !self.stopped {
+                    score = -self.negamax(new_chessboard, depth - 1, ply + 1, -beta, -alpha, true);
+                }
+            } else {
+                score = -self.negamax(new_chessboard, depth - 1, ply + 1, -beta, -alpha, true);
+            }
+
+            if self.stopped {
+                self.path.pop();
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(*chess_move);
+
+                if ply == 0 {
+                    self.root_best_move = best_move;
+                }
+            }
+
+            if score > alpha {
+                alpha = score;
+            }
+
+            if alpha >= beta {
+                self.stats.beta_cutoffs += 1;
+                if index == 0 {
+                    self.stats.first_move_cutoffs += 1;
+                }
+
+                // Los movimientos tranquilos que cortan alimentan los asesinos y el historial
+                if is_quiet {
+                    if self.config.killer_moves {
+                        self.orderer.store_killer(*chess_move, ply);
+                    }
+                    if self.config.history_heuristic {
+                        self.orderer.update_history(*chess_move, depth);
+                    }
+                }
+                break;
+            }
+        }
+
+        self.path.pop();
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        // Con movimientos excluidos el resultado de la raíz no vale para la posición completa
+        if ply > 0 || self.excluded_root_moves.is_empty() {
+            self.transposition_table.store(TranspositionEntry {
+                key,
+                depth,
+                score: score_to_table(best_score, ply),
+                bound,
+                best_move,
+            });
+        }
+
+        best_score
+    }
+
+    // Búsqueda de capturas para evitar el efecto horizonte
+    fn quiescence(
+        &mut self,
+        chessboard: &Chessboard,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.stats.nodes += 1;
+        self.stats.quiescence_nodes += 1;
+
+        let stand_pat = evaluate(chessboard);
+
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut moves = generate_moves(chessboard, true);
+        let mut scores = self
+            .orderer
+            .score_moves(chessboard, &moves, None, ply, &self.config);
+
+        for index in 0..moves.len() {
+            pick_next_move(&mut moves, &mut scores, index);
+            let score = -self.quiescence(&moves[index].1, ply + 1, -beta, -alpha);
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                self.stats.beta_cutoffs += 1;
+                if index == 0 {
+                    self.stats.first_move_cutoffs += 1;
+                }
+                return score;
+            }
+
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        alpha
+    }
+
+    // Revisa el límite de nodos, y cada cierto número de nodos el tiempo y si otro hilo pidió
+    // detener la búsqueda
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if self.stats.nodes.is_multiple_of(TIME_CHECK_INTERVAL) {
+            let timed_out = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if timed_out || self.stop.load(Ordering::Relaxed) {
+                self.stopped = true;
+            }
+        }
+
+        if self.max_nodes.is_some_and(|max_nodes| self.stats.nodes >= max_nodes) {
+            self.stopped = true;
+        }
+
+        self.stopped
+    }
+
+    // Reconstruye la variante principal siguiendo la tabla de transposición
+    fn principal_variation(&self, chessboard: &Chessboard, max_length: usize) -> Vec<Move> {
+        let mut variation = Vec::new();
+        let mut current = chessboard.clone();
+        let mut seen = Vec::new();
+
+        while variation.len() < max_length {
+            let key = zobrist::hash(&current);
+            if seen.contains(&key) {
+                break;
+            }
+            seen.push(key);
+
+            let best_move = match self.transposition_table.probe(key) {
+                Some(TranspositionEntry {
+                    best_move: Some(best_move),
+                    ..
+                }) => best_move,
+                _ => break,
+            };
+
+            // Validamos que el movimiento siga siendo legal en la posición
+            let next = generate_moves(&current, false)
+                .into_iter()
+                .find(|(chess_move, _)| *chess_move == best_move);
+
+            match next {
+                Some((chess_move, new_chessboard)) => {
+                    variation.push(chess_move);
+                    current = new_chessboard;
+                }
+                None => break,
+            }
+        }
+
+        variation
+    }
+}
+
+// El tablero no aplica la regla de los 50 movimientos, así que las victorias "malditas" también
+// se pueden ganar, aunque se prefieren las que no dependen de ello
+fn tablebase_score(wdl: Wdl, ply: usize) -> i32 {
+    match wdl {
+        Wdl::Win => TABLEBASE_WIN_SCORE - ply as i32,
+        Wdl::CursedWin => TABLEBASE_WIN_SCORE / 2 - ply as i32,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -TABLEBASE_WIN_SCORE / 2 + ply as i32,
+        Wdl::Loss => -TABLEBASE_WIN_SCORE + ply as i32,
+    }
+}
+
+// Las puntuaciones de mate se guardan relativas al nodo y no a la raíz
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_SCORE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE_SCORE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_SCORE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE_SCORE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+pub fn opposite_color(color: ChessPieceColor) -> ChessPieceColor {
+    match color {
+        ChessPieceColor::White => ChessPieceColor::Black,
+        ChessPieceColor::Black => ChessPieceColor::White,
+    }
+}
+
+// En finales de solo peones el movimiento nulo falla por el zugzwang
+fn has_non_pawn_material(chessboard: &Chessboard, color: ChessPieceColor) -> bool {
+    chessboard.board.iter().flatten().flatten().any(|piece| {
+        piece.color == color
+            && piece.piece != ChessPieceType::Pawn
+            && piece.piece != ChessPieceType::King
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(nodes: Option<u64>) -> SearchLimits {
+        SearchLimits {
+            depth: 20,
+            time: None,
+            nodes,
+        }
+    }
+
+    fn is_legal(chessboard: &Chessboard, chess_move: Option<Move>) -> bool {
+        generate_moves(chessboard, false)
+            .iter()
+            .any(|(legal, _)| Some(*legal) == chess_move)
+    }
+
+    #[test]
+    fn stop_before_the_first_iteration_still_returns_a_move() {
+        let chessboard = Chessboard::new();
+        let mut engine = Engine::new(SearchConfig::default());
+        let stop = engine.stop_handle();
+
+        stop.store(true, Ordering::Relaxed);
+        let result = engine.search(&chessboard, limits(None));
+        assert!(is_legal(&chessboard, result.best_move));
+        // La búsqueda deja la señal desactivada para la siguiente
+        assert!(!stop.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn stopped_root_move_searches_still_return_a_move() {
+        let chessboard = Chessboard::new();
+        let mut engine = Engine::new(SearchConfig::default());
+        let stop = engine.stop_handle();
+
+        stop.store(true, Ordering::Relaxed);
+        let result = engine.search_all_moves(&chessboard, limits(None));
+        assert!(is_legal(&chessboard, result.best_move));
+
+        stop.store(true, Ordering::Relaxed);
+        let result = engine.search_multi_pv(&chessboard, limits(None), 3);
+        assert!(is_legal(&chessboard, result.best_move));
+    }
+
+    #[test]
+    fn node_limit_inside_the_first_iteration_still_returns_a_move() {
+        let chessboard = Chessboard::new();
+        let mut engine = Engine::new(SearchConfig::default());
+
+        let result = engine.search(&chessboard, limits(Some(1)));
+        assert!(is_legal(&chessboard, result.best_move));
+    }
+
+    #[test]
+    fn mate_in_two_survives_each_pruning_switch() {
+        let chessboard = Chessboard::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1")
+            .ok()
+            .unwrap();
+
+        let mate_limits = SearchLimits {
+            depth: 4,
+            ..limits(None)
+        };
+        let all = ["null", "lmr", "futilidad", "razoring", "orden"];
+
+        // Cada heurística apagada por separado, y luego todas juntas
+        for switches in all.iter().map(std::slice::from_ref).chain([&all[..]]) {
+            let mut config = SearchConfig::default();
+            for switch in switches {
+                assert!(config.disable(switch));
+            }
+
+            let mut engine = Engine::new(config);
+            let result = engine.search(&chessboard, mate_limits);
+            assert!(is_legal(&chessboard, result.best_move));
+            assert_eq!(result.score, MATE_SCORE - 3);
+        }
+
+        assert!(!SearchConfig::default().disable("desconocida"));
+    }
+
+    #[test]
+    fn helper_threads_still_return_a_legal_move() {
+        let chessboard = Chessboard::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .ok()
+        .unwrap();
+        let search_limits = SearchLimits {
+            depth: 4,
+            ..limits(None)
+        };
+
+        for threads in [1, 4] {
+            let mut engine = Engine::new(SearchConfig::default());
+            engine.set_threads(threads);
+
+            let result = engine.search(&chessboard, search_limits);
+            assert!(is_legal(&chessboard, result.best_move));
+            assert_eq!(result.depth, 4);
+            assert!(result.stats.nodes > 0);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::chessboard::chessboard::Chessboard;
+
+use super::random::Random;
+use super::search::{Engine, SearchLimits, SearchResult, MATE_SCORE, MAX_PLY};
+
+// Un error grave pierde al menos esta cantidad de centipeones respecto del mejor movimiento
+const BLUNDER_MIN_LOSS: i32 = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DifficultyLevel {
+    Beginner,
+    Novice,
+    Intermediate,
+    Advanced,
+    Expert,
+    Master,
+}
+
+impl DifficultyLevel {
+    pub const ALL: [DifficultyLevel; 6] = [
+        DifficultyLevel::Beginner,
+        DifficultyLevel::Novice,
+        DifficultyLevel::Intermediate,
+        DifficultyLevel::Advanced,
+        DifficultyLevel::Expert,
+        DifficultyLevel::Master,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DifficultyLevel::Beginner => "principiante",
+            DifficultyLevel::Novice => "novato",
+            DifficultyLevel::Intermediate => "intermedio",
+            DifficultyLevel::Advanced => "avanzado",
+            DifficultyLevel::Expert => "experto",
+            DifficultyLevel::Master => "maestro",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DifficultyLevel> {
+        DifficultyLevel::ALL
+            .into_iter()
+            .find(|level| level.name() == name)
+    }
+
+    // Fuerza aproximada de cada nivel
+    pub fn elo(self) -> u32 {
+        match self {
+            DifficultyLevel::Beginner => 800,
+            DifficultyLevel::Novice => 1100,
+            DifficultyLevel::Intermediate => 1400,
+            DifficultyLevel::Advanced => 1700,
+            DifficultyLevel::Expert => 2000,
+            DifficultyLevel::Master => 2300,
+        }
+    }
+
+    pub fn strength(self) -> Strength {
+        match self {
+            DifficultyLevel::Beginner => Strength {
+                depth: 1,
+                nodes: Some(1_000),
+                randomness: 200,
+                blunder_rate: 20,
+            },
+            DifficultyLevel::Novice => Strength {
+                depth: 2,
+                nodes: Some(4_000),
+                randomness: 120,
+                blunder_rate: 12,
+            },
+            DifficultyLevel::Intermediate => Strength {
+                depth: 3,
+                nodes: Some(15_000),
+                randomness: 60,
+                blunder_rate: 6,
+            },
+            DifficultyLevel::Advanced => Strength {
+                depth: 4,
+                nodes: Some(50_000),
+                randomness: 30,
+                blunder_rate: 3,
+            },
+            DifficultyLevel::Expert => Strength {
+                depth: 5,
+                nodes: Some(200_000),
+                randomness: 10,
+                blunder_rate: 1,
+            },
+            DifficultyLevel::Master => Strength::full(6),
+        }
+    }
+}
+
+/**
+ Cómo se limita la fuerza de la computadora: menos profundidad o nodos, una variación aleatoria
+ de la puntuación de cada movimiento y un porcentaje de errores graves a propósito.
+*/
+#[derive(Clone, Copy)]
+pub struct Strength {
+    pub depth: u32,
+    pub nodes: Option<u64>,
+    // Máxima cantidad de centipeones que se suma al azar a cada movimiento
+    pub randomness: i32,
+    // Porcentaje de jugadas en las que se elige un movimiento malo a propósito
+    pub blunder_rate: u32,
+}
+
+impl Strength {
+    // Sin limitaciones, solo con la profundidad indicada
+    pub fn full(depth: u32) -> Self {
+        Strength {
+            depth,
+            nodes: None,
+            randomness: 0,
+            blunder_rate: 0,
+        }
+    }
+
+    /**
+     Calcula la fuerza para un Elo objetivo interpolando entre los niveles de dificultad.
+     # Arguments
+     * `elo` - El Elo objetivo; se limita al rango de los niveles.
+     # Returns
+     La fuerza correspondiente.
+    */
+    pub fn from_elo(elo: u32) -> Self {
+        let levels = DifficultyLevel::ALL;
+
+        if elo <= levels[0].elo() {
+            return levels[0].strength();
+        }
+
+        for pair in levels.windows(2) {
+            let (lower, upper) = (pair[0], pair[1]);
+            if elo > upper.elo() {
+                continue;
+            }
+
+            let t = (elo - lower.elo()) as f64 / (upper.elo() - lower.elo()) as f64;
+            let (weak, strong) = (lower.strength(), upper.strength());
+            let mix = |a: f64, b: f64| a + (b - a) * t;
+
+            // El nivel más fuerte no tiene límite de nodos, así que interpolamos hasta ocho veces más
+            let nodes = match (weak.nodes, strong.nodes) {
+                (Some(a), Some(b)) => Some(mix(a as f64, b as f64).round() as u64),
+                (Some(a), None) => Some(mix(a as f64, 8.0 * a as f64).round() as u64),
+                _ => None,
+            };
+
+            return Strength {
+                depth: mix(weak.depth as f64, strong.depth as f64).round() as u32,
+                nodes,
+                randomness: mix(weak.randomness as f64, strong.randomness as f64).round() as i32,
+                blunder_rate: mix(weak.blunder_rate as f64, strong.blunder_rate as f64).round()
+                    as u32,
+            };
+        }
+
+        levels[levels.len() - 1].strength()
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.nodes.is_some() || self.randomness > 0 || self.blunder_rate > 0
+    }
+
+    pub fn limits(&self) -> SearchLimits {
+        SearchLimits {
+            depth: self.depth,
+            time: None,
+            nodes: self.nodes,
+        }
+    }
+}
+
+/**
+ Elige el movimiento de la computadora según la fuerza configurada.
+ # Arguments
+ * `engine` - El motor de búsqueda.
+ * `chessboard` - El tablero actual.
+ * `strength` - Los límites de profundidad, nodos, variación aleatoria y errores.
+ * `random` - El generador de números aleatorios.
+ # Returns
+ El resultado de la búsqueda con el movimiento elegido, que puede no ser el mejor.
+*/
+pub fn choose_move(
+    engine: &mut Engine,
+    chessboard: &Chessboard,
+    strength: &Strength,
+    random: &mut Random,
+) -> SearchResult {
+    if !strength.is_limited() {
+        return engine.search(chessboard, strength.limits());
+    }
+
+    let mut result = engine.search_all_moves(chessboard, strength.limits());
+
+    if result.root_moves.len() < 2 {
+        return result;
+    }
+
+    let best_score = result.root_moves[0].score;
+
+    // Los errores graves nunca eligen un movimiento que recibe mate
+    let blunders: Vec<_> = result
+        .root_moves
+        .iter()
+        .filter(|root_move| {
+            root_move.score <= best_score - BLUNDER_MIN_LOSS
+                && root_move.score > -MATE_SCORE + MAX_PLY as i32
+        })
+        .cloned()
+        .collect();
+
+    let chosen = if !blunders.is_empty() && random.range(100) < strength.blunder_rate as u64 {
+        blunders[random.range(blunders.len() as u64) as usize].clone()
+    } else {
+        // Sumamos un valor al azar a cada puntuación, así los movimientos parecidos se alternan
+        result
+            .root_moves
+            .iter()
+            .max_by_key(|root_move| {
+                root_move.score + random.range(strength.randomness as u64 + 1) as i32
+            })
+            .unwrap()
+            .clone()
+    };
+
+    result.best_move = Some(chosen.chess_move);
+    result.score = chosen.score;
+    result.principal_variation = chosen.principal_variation;
+    result
+}
@@ -0,0 +1,1550 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+use super::move_generator::{generate_moves, Move};
+use super::zobrist::castling_rights;
+
+// Encabezados de los archivos de Syzygy
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// Bandera del primer byte de la tabla
+const HAS_PAWNS_FLAG: u8 = 2;
+
+// Banderas de cada bloque comprimido
+const STM_FLAG: u8 = 1;
+const MAPPED_FLAG: u8 = 2;
+const WIN_PLIES_FLAG: u8 = 4;
+const LOSS_PLIES_FLAG: u8 = 8;
+const WIDE_FLAG: u8 = 16;
+const SINGLE_VALUE_FLAG: u8 = 128;
+
+const MAX_PIECES: usize = 7;
+
+// Orden de las piezas en los nombres de archivo
+const PIECE_ORDER: [(ChessPieceType, char); 6] = [
+    (ChessPieceType::King, 'K'),
+    (ChessPieceType::Queen, 'Q'),
+    (ChessPieceType::Rook, 'R'),
+    (ChessPieceType::Bishop, 'B'),
+    (ChessPieceType::Knight, 'N'),
+    (ChessPieceType::Pawn, 'P'),
+];
+
+// Resultado de una posición según la tabla: victoria, tablas o derrota
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Wdl {
+    Loss,
+    // Derrota que se salva por la regla de los 50 movimientos
+    BlessedLoss,
+    Draw,
+    // Victoria que no alcanza a concretarse antes de la regla de los 50 movimientos
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            1 => Wdl::CursedWin,
+            2 => Wdl::Win,
+            _ => Wdl::Draw,
+        }
+    }
+
+    fn value(self) -> i32 {
+        match self {
+            Wdl::Loss => -2,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 1,
+            Wdl::Win => 2,
+        }
+    }
+
+    fn negate(self) -> Wdl {
+        Wdl::from_value(-self.value())
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Wdl::Win => "victoria",
+            Wdl::CursedWin => "victoria (tablas por la regla de los 50 movimientos)",
+            Wdl::Draw => "tablas",
+            Wdl::BlessedLoss => "derrota (tablas por la regla de los 50 movimientos)",
+            Wdl::Loss => "derrota",
+        }
+    }
+}
+
+// Resultado de consultar las tablas en la raíz
+#[derive(Clone, Copy)]
+pub struct RootProbe {
+    pub best_move: Move,
+    pub wdl: Wdl,
+    pub dtz: i32,
+}
+
+#[derive(PartialEq)]
+enum ProbeState {
+    Ok,
+    // La tabla DTZ guarda el otro turno
+    ChangeStm,
+    // El mejor movimiento captura o mueve un peón
+    ZeroingBestMove,
+}
+
+/**
+ Tablas de finales Syzygy leídas desde un directorio local. Los archivos se cargan en memoria la
+ primera vez que se necesitan.
+*/
+pub struct Tablebase {
+    directory: PathBuf,
+    max_pieces: usize,
+    wdl_tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+    dtz_tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+}
+
+impl Tablebase {
+    /**
+     Prepara las tablas de un directorio, revisando cuántas piezas tienen los archivos disponibles.
+     # Arguments
+     * `directory` - El directorio con los archivos `.rtbw` y `.rtbz`.
+     # Returns
+     Retorna las tablas, o un error si el directorio no se puede leer o no tiene tablas.
+    */
+    pub fn open(directory: &str) -> Result<Tablebase, String> {
+        let entries = fs::read_dir(directory).map_err(|error| error.to_string())?;
+        let mut max_pieces = 0;
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(table) = name.strip_suffix(".rtbw") {
+                max_pieces = max_pieces.max(table.chars().filter(|c| *c != 'v').count());
+            }
+        }
+
+        if max_pieces == 0 {
+            return Err("El directorio no tiene tablas .rtbw".to_string());
+        }
+
+        Ok(Tablebase {
+            directory: PathBuf::from(directory),
+            max_pieces: max_pieces.min(MAX_PIECES),
+            wdl_tables: Mutex::new(HashMap::new()),
+            dtz_tables: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // Indica si la posición se puede consultar: pocas piezas y sin derechos de enroque
+    pub fn can_probe(&self, chessboard: &Chessboard) -> bool {
+        let pieces = chessboard.board.iter().flatten().flatten().count();
+        pieces <= self.max_pieces && !castling_rights(chessboard).contains(&true)
+    }
+
+    /**
+     Consulta el resultado teórico de la posición (victoria, tablas o derrota).
+     # Arguments
+     * `chessboard` - La posición a consultar, desde el punto de vista del jugador en turno.
+     # Returns
+     Retorna el resultado, o `None` si no están las tablas necesarias.
+    */
+    pub fn probe_wdl(&self, chessboard: &Chessboard) -> Option<Wdl> {
+        if !self.can_probe(chessboard) {
+            return None;
+        }
+
+        self.search(chessboard, false).map(|(wdl, _)| wdl)
+    }
+
+    /**
+     Consulta la distancia hasta el próximo movimiento que reinicia la regla de los 50 movimientos
+     (captura o movimiento de peón) jugando de manera óptima.
+     # Arguments
+     * `chessboard` - La posición a consultar.
+     # Returns
+     Retorna la distancia en medias jugadas, positiva si se gana y negativa si se pierde, o `None`
+     si no están las tablas necesarias.
+    */
+    pub fn probe_dtz(&self, chessboard: &Chessboard) -> Option<i32> {
+        if !self.can_probe(chessboard) {
+            return None;
+        }
+
+        self.dtz(chessboard)
+    }
+
+    /**
+     Elige el mejor movimiento en la raíz: el que mantiene el mejor resultado y, entre ellos, el que
+     avanza más rápido (o resiste más si se pierde).
+     # Arguments
+     * `chessboard` - La posición en la raíz.
+     # Returns
+     Retorna el movimiento con su resultado y distancia, o `None` si no se puede consultar.
+    */
+    pub fn probe_root(&self, chessboard: &Chessboard) -> Option<RootProbe> {
+        if !self.can_probe(chessboard) {
+            return None;
+        }
+
+        let mut best: Option<(RootProbe, i32)> = None;
+
+        for (chess_move, new_chessboard) in generate_moves(chessboard, false) {
+            let zeroing = is_zeroing(chessboard, &chess_move);
+
+            let dtz = if zeroing {
+                dtz_before_zeroing(self.search(&new_chessboard, false)?.0.negate())
+            } else {
+                let dtz = -self.dtz(&new_chessboard)?;
+                dtz + dtz.signum()
+            };
+
+            // Un movimiento que da mate tiene distancia 1
+            let dtz = if dtz == 2
+                && is_check(&new_chessboard, new_chessboard.player_turn).is_some()
+                && generate_moves(&new_chessboard, false).is_empty()
+            {
+                1
+            } else {
+                dtz
+            };
+
+            let wdl = wdl_from_dtz(dtz);
+
+            // Ganando preferimos la distancia más corta y perdiendo la más larga
+            let rank = match wdl {
+                Wdl::Win | Wdl::CursedWin => 2000 * wdl.value() - dtz,
+                Wdl::Draw => 0,
+                Wdl::Loss | Wdl::BlessedLoss => 2000 * wdl.value() - dtz,
+            };
+
+            if best.as_ref().is_none_or(|(_, best_rank)| rank > *best_rank) {
+                best = Some((
+                    RootProbe {
+                        best_move: chess_move,
+                        wdl,
+                        dtz,
+                    },
+                    rank,
+                ));
+            }
+        }
+
+        best.map(|(probe, _)| probe)
+    }
+
+    // Búsqueda de capturas antes de consultar la tabla, porque las tablas no guardan bien
+    // las posiciones en las que lo mejor es capturar
+    fn search(&self, chessboard: &Chessboard, check_zeroing: bool) -> Option<(Wdl, ProbeState)> {
+        let moves = generate_moves(chessboard, false);
+        let mut best = Wdl::Loss;
+        let mut move_count = 0;
+
+        for (chess_move, new_chessboard) in moves.iter() {
+            let is_capture = chessboard.board[chess_move.to[0]][chess_move.to[1]].is_some();
+            let is_pawn = is_pawn_move(chessboard, chess_move);
+
+            if !is_capture && (!check_zeroing || !is_pawn) {
+                continue;
+            }
+
+            move_count += 1;
+            let value = self.search(new_chessboard, false)?.0.negate();
+
+            if value > best {
+                best = value;
+
+                if value == Wdl::Win {
+                    return Some((value, ProbeState::ZeroingBestMove));
+                }
+            }
+        }
+
+        let no_more_moves = move_count > 0 && move_count == moves.len();
+
+        let value = if no_more_moves {
+            best
+        } else {
+            self.probe_wdl_table(chessboard)?
+        };
+
+        // Si capturar es al menos tan bueno como lo que dice la tabla, nos quedamos con la captura
+        if best >= value {
+            let state = if best > Wdl::Draw || no_more_moves {
+                ProbeState::ZeroingBestMove
+            } else {
+                ProbeState::Ok
+            };
+            return Some((best, state));
+        }
+
+        Some((value, ProbeState::Ok))
+    }
+
+    fn dtz(&self, chessboard: &Chessboard) -> Option<i32> {
+        let (wdl, state) = self.search(chessboard, true)?;
+
+        // Las tablas DTZ no guardan las tablas
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+
+        if state == ProbeState::ZeroingBestMove {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        let (dtz, state) = self.probe_dtz_table(chessboard, wdl)?;
+
+        if state != ProbeState::ChangeStm {
+            let cursed = matches!(wdl, Wdl::BlessedLoss | Wdl::CursedWin);
+            return Some((dtz + if cursed { 100 } else { 0 }) * wdl.value().signum());
+        }
+
+        // La tabla guarda el otro turno, así que buscamos una jugada y tomamos la mejor distancia
+        let mut min_dtz = i32::MAX;
+
+        for (chess_move, new_chessboard) in generate_moves(chessboard, false) {
+            let zeroing = is_zeroing(chessboard, &chess_move);
+
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search(&new_chessboard, false)?.0)
+            } else {
+                -self.dtz(&new_chessboard)?
+            };
+
+            if dtz == 1
+                && is_check(&new_chessboard, new_chessboard.player_turn).is_some()
+                && generate_moves(&new_chessboard, false).is_empty()
+            {
+                min_dtz = 1;
+            }
+
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == wdl.value().signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        // Sin movimientos legales es jaque mate
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    fn probe_wdl_table(&self, chessboard: &Chessboard) -> Option<Wdl> {
+        let position = TablePosition::new(chessboard);
+
+        // Rey contra rey siempre es tablas
+        if position.squares.len() == 2 {
+            return Some(Wdl::Draw);
+        }
+
+        let table = self.table(&position.material, false)?;
+        let (value, _) = table.probe(&position, Wdl::Draw)?;
+
+        Some(Wdl::from_value(value))
+    }
+
+    fn probe_dtz_table(&self, chessboard: &Chessboard, wdl: Wdl) -> Option<(i32, ProbeState)> {
+        let position = TablePosition::new(chessboard);
+        let table = self.table(&position.material, true)?;
+
+        table.probe(&position, wdl)
+    }
+
+    // Busca la tabla por su material, cargándola del disco si aún no está en memoria
+    fn table(&self, material: &str, dtz: bool) -> Option<Arc<Table>> {
+        let tables = if dtz {
+            &self.dtz_tables
+        } else {
+            &self.wdl_tables
+        };
+
+        let mut tables = tables.lock().unwrap();
+
+        if let Some(table) = tables.get(material) {
+            return table.clone();
+        }
+
+        // El archivo siempre tiene primero al bando con más material
+        let (white, black) = material.split_once('v')?;
+        let mirrored = format!("{}v{}", black, white);
+        let extension = if dtz { "rtbz" } else { "rtbw" };
+
+        let table = [material, mirrored.as_str()].iter().find_map(|name| {
+            let path = self.directory.join(format!("{}.{}", name, extension));
+            let bytes = fs::read(path).ok()?;
+            Table::new(name, bytes, dtz).map(Arc::new)
+        });
+
+        tables.insert(material.to_string(), table.clone());
+        tables.insert(mirrored, table.clone());
+        table
+    }
+}
+
+fn is_pawn_move(chessboard: &Chessboard, chess_move: &Move) -> bool {
+    matches!(
+        chessboard.board[chess_move.from[0]][chess_move.from[1]],
+        Some(piece) if piece.piece == ChessPieceType::Pawn
+    )
+}
+
+// Movimientos que reinician la regla de los 50 movimientos
+fn is_zeroing(chessboard: &Chessboard, chess_move: &Move) -> bool {
+    chessboard.board[chess_move.to[0]][chess_move.to[1]].is_some()
+        || is_pawn_move(chessboard, chess_move)
+}
+
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+fn wdl_from_dtz(dtz: i32) -> Wdl {
+    match dtz {
+        d if d < -100 => Wdl::BlessedLoss,
+        d if d < 0 => Wdl::Loss,
+        0 => Wdl::Draw,
+        d if d <= 100 => Wdl::Win,
+        _ => Wdl::CursedWin,
+    }
+}
+
+// Posición convertida a la numeración de Syzygy: a1 = 0, h8 = 63, peones 1 a reyes 6, negras + 8
+struct TablePosition {
+    squares: Vec<(usize, u8)>,
+    white_to_move: bool,
+    material: String,
+}
+
+impl TablePosition {
+    fn new(chessboard: &Chessboard) -> Self {
+        let mut squares = Vec::new();
+
+        // Recorremos de a1 a h8 para que las casillas queden en orden ascendente
+        for row in (0..8).rev() {
+            for col in 0..8 {
+                if let Some(piece) = chessboard.board[row][col] {
+                    let code = piece_code(piece.piece)
+                        + match piece.color {
+                            ChessPieceColor::White => 0,
+                            ChessPieceColor::Black => 8,
+                        };
+                    squares.push(((7 - row) * 8 + col, code));
+                }
+            }
+        }
+
+        let material = format!(
+            "{}v{}",
+            material_side(chessboard, ChessPieceColor::White),
+            material_side(chessboard, ChessPieceColor::Black)
+        );
+
+        TablePosition {
+            squares,
+            white_to_move: chessboard.player_turn == ChessPieceColor::White,
+            material,
+        }
+    }
+}
+
+fn piece_code(piece: ChessPieceType) -> u8 {
+    match piece {
+        ChessPieceType::Pawn => 1,
+        ChessPieceType::Knight => 2,
+        ChessPieceType::Bishop => 3,
+        ChessPieceType::Rook => 4,
+        ChessPieceType::Queen => 5,
+        ChessPieceType::King => 6,
+    }
+}
+
+// Por ejemplo "KRP" para un rey, una torre y un peón
+fn material_side(chessboard: &Chessboard, color: ChessPieceColor) -> String {
+    let mut text = String::new();
+
+    for (piece_type, letter) in PIECE_ORDER {
+        let count = chessboard
+            .board
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|piece| piece.piece == piece_type && piece.color == color)
+            .count();
+
+        for _ in 0..count {
+            text.push(letter);
+        }
+    }
+
+    text
+}
+
+// Tablas de índices que se calculan una sola vez
+struct Indexes {
+    map_pawns: [usize; 64],
+    map_b1h1h7: [usize; 64],
+    map_a1d1d4: [usize; 64],
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; 6],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+static INDEXES: OnceLock<Indexes> = OnceLock::new();
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+// Distancia con signo a la diagonal a1-h8 (negativa debajo de ella)
+fn off_a1h8(square: usize) -> i32 {
+    rank_of(square) as i32 - file_of(square) as i32
+}
+
+// Coeficiente binomial C(n, k) de la tabla, o `None` si está fuera de ella
+fn binomial(k: usize, n: usize) -> Option<u64> {
+    indexes().binomial.get(k)?.get(n).copied()
+}
+
+fn indexes() -> &'static Indexes {
+    INDEXES.get_or_init(|| {
+        let mut indexes = Indexes {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        // Casillas debajo de la diagonal a1-h8 numeradas de 0 a 27
+        let mut code = 0;
+        for square in 0..64 {
+            if off_a1h8(square) < 0 {
+                indexes.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        // Triángulo a1-d1-d4 numerado de 0 a 9, con la diagonal al final
+        let mut diagonal = Vec::new();
+        code = 0;
+        for square in 0..28 {
+            if off_a1h8(square) < 0 && file_of(square) <= 3 {
+                indexes.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_a1h8(square) == 0 && file_of(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            indexes.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // Las 462 posiciones legales de los dos reyes con el primero en el triángulo
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..28 {
+                if indexes.map_a1d1d4[s1] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+
+                for s2 in 0..64 {
+                    let adjacent = (rank_of(s1) as i32 - rank_of(s2) as i32).abs() <= 1
+                        && (file_of(s1) as i32 - file_of(s2) as i32).abs() <= 1;
+
+                    if adjacent || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                        continue;
+                    }
+
+                    if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        indexes.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            indexes.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        // Coeficientes binomiales con el triángulo de Pascal
+        indexes.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                indexes.binomial[k][n] = if k > 0 {
+                    indexes.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { indexes.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Índices de los peones líderes según su columna y fila
+        let mut available_squares = 47;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+
+                    if lead_pawns == 1 {
+                        indexes.map_pawns[square] = available_squares;
+                        available_squares = available_squares.saturating_sub(1);
+                        indexes.map_pawns[square ^ 7] = available_squares;
+                        available_squares = available_squares.saturating_sub(1);
+                    }
+
+                    indexes.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += indexes.binomial[lead_pawns - 1][indexes.map_pawns[square]];
+                }
+
+                indexes.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        indexes
+    })
+}
+
+// Datos de compresión de cada subtabla
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    min_sym_len: u8,
+    block_size: u64,
+    span: u64,
+    num_blocks: u64,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: u64,
+    sparse_index: usize,
+    sparse_index_size: u64,
+    data: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    map_idx: [usize; 4],
+}
+
+struct Table {
+    bytes: Vec<u8>,
+    dtz: bool,
+    key: String,
+    key2: String,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    pawn_count: [usize; 2],
+    // Índice [lado][columna]
+    items: Vec<Vec<PairsData>>,
+    map: usize,
+}
+
+// Las lecturas devuelven `None` si el archivo se termina antes, por ejemplo si está truncado
+fn read_u8(bytes: &[u8], offset: usize) -> Option<u8> {
+    bytes.get(offset).copied()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let end = offset.checked_add(2)?;
+    Some(u16::from_le_bytes(bytes.get(offset..end)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let end = offset.checked_add(4)?;
+    Some(u32::from_le_bytes(bytes.get(offset..end)?.try_into().ok()?))
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    let end = offset.checked_add(4)?;
+    Some(u32::from_be_bytes(bytes.get(offset..end)?.try_into().ok()?))
+}
+
+impl Table {
+    /**
+     Interpreta un archivo de Syzygy.
+     # Arguments
+     * `name` - El material de la tabla, por ejemplo "KRPvKR".
+     * `bytes` - El contenido del archivo.
+     * `dtz` - Si es una tabla DTZ (`.rtbz`) en lugar de WDL (`.rtbw`).
+     # Returns
+     Retorna la tabla, o `None` si el archivo no es válido.
+    */
+    fn new(name: &str, bytes: Vec<u8>, dtz: bool) -> Option<Table> {
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if bytes.len() < 5 || bytes[0..4] != magic {
+            return None;
+        }
+
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, letter: char| side.chars().filter(|c| *c == letter).count();
+
+        let piece_count = white.len() + black.len();
+        if piece_count > MAX_PIECES {
+            return None;
+        }
+        let has_pawns = name.contains('P');
+
+        let has_unique_pieces = [white, black].iter().any(|side| {
+            ['Q', 'R', 'B', 'N', 'P']
+                .iter()
+                .any(|letter| count(side, *letter) == 1)
+        });
+
+        // El bando líder es el que tiene menos peones (si ambos tienen)
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads {
+            [white_pawns, black_pawns]
+        } else {
+            [black_pawns, white_pawns]
+        };
+
+        let mut table = Table {
+            bytes,
+            dtz,
+            key: name.to_string(),
+            key2: format!("{}v{}", black, white),
+            piece_count,
+            has_pawns,
+            has_unique_pieces,
+            pawn_count,
+            items: Vec::new(),
+            map: 0,
+        };
+
+        table.setup()?;
+        Some(table)
+    }
+
+    // Lee los encabezados de todas las subtablas
+    fn setup(&mut self) -> Option<()> {
+        let bytes = &self.bytes;
+        let mut data = 4;
+
+        let flags = *bytes.get(data)?;
+        if (flags & HAS_PAWNS_FLAG != 0) != self.has_pawns {
+            return None;
+        }
+        data += 1;
+
+        let sides = if !self.dtz && self.key != self.key2 {
+            2
+        } else {
+            1
+        };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+
+        let mut items = vec![vec![PairsData::default(); files]; sides];
+
+        for file in 0..files {
+            let first = read_u8(bytes, data)?;
+            let second = if both_pawns {
+                read_u8(bytes, data + 1)?
+            } else {
+                0xFF
+            };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            data += 1 + both_pawns as usize;
+
+            for k in 0..self.piece_count {
+                let pieces = read_u8(bytes, data)?;
+                for (side, item) in items.iter_mut().enumerate() {
+                    item[file].pieces[k] = if side > 0 { pieces >> 4 } else { pieces & 0xF };
+                }
+                data += 1;
+            }
+
+            for (side, item) in items.iter_mut().enumerate() {
+                self.set_groups(&mut item[file], order[side], file)?;
+            }
+        }
+
+        data += data & 1;
+
+        for file in 0..files {
+            for item in items.iter_mut() {
+                data = set_sizes(bytes, &mut item[file], data)?;
+            }
+        }
+
+        // Las tablas DTZ traducen los valores guardados con un mapa
+        if self.dtz {
+            self.map = data;
+
+            for pairs in items[0].iter_mut() {
+                if pairs.flags & MAPPED_FLAG == 0 {
+                    continue;
+                }
+
+                if pairs.flags & WIDE_FLAG != 0 {
+                    data += data & 1;
+                    for i in 0..4 {
+                        pairs.map_idx[i] = (data - self.map) / 2 + 1;
+                        data += 2 + 2 * read_u16(bytes, data)? as usize;
+                    }
+                } else {
+                    for i in 0..4 {
+                        pairs.map_idx[i] = data - self.map + 1;
+                        data += 1 + read_u8(bytes, data)? as usize;
+                    }
+                }
+            }
+
+            data += data & 1;
+        }
+
+        for file in 0..files {
+            for item in items.iter_mut() {
+                item[file].sparse_index = data;
+                data = data.checked_add(
+                    usize::try_from(item[file].sparse_index_size)
+                        .ok()?
+                        .checked_mul(6)?,
+                )?;
+            }
+        }
+
+        for file in 0..files {
+            for item in items.iter_mut() {
+                item[file].block_length = data;
+                data = data.checked_add(
+                    usize::try_from(item[file].block_length_size)
+                        .ok()?
+                        .checked_mul(2)?,
+                )?;
+            }
+        }
+
+        for file in 0..files {
+            for item in items.iter_mut() {
+                // Los datos comprimidos están alineados a 64 bytes
+                data = data.checked_add(0x3F)? & !0x3F;
+                item[file].data = data;
+                let size = item[file].num_blocks.checked_mul(item[file].block_size)?;
+                data = data.checked_add(usize::try_from(size).ok()?)?;
+            }
+        }
+
+        if data > bytes.len() {
+            return None;
+        }
+
+        self.items = items;
+        Some(())
+    }
+
+    // Calcula cómo se agrupan las piezas para codificar la posición; `None` si el orden de las
+    // piezas del archivo no es válido
+    fn set_groups(&self, pairs: &mut PairsData, order: [u8; 2], file: usize) -> Option<()> {
+        let indexes = indexes();
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+
+        pairs.group_len[n] = 1;
+
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_len[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_len[n] = 1;
+            }
+        }
+
+        n += 1;
+        pairs.group_len[n] = 0;
+
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares =
+            64 - pairs.group_len[0] - if both_pawns { pairs.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            // Un orden que no nombra a todos los grupos dejaría el ciclo sin terminar
+            if k >= MAX_PIECES {
+                return None;
+            }
+
+            let size = if k == order[0] as usize {
+                // Peones o piezas líderes
+                pairs.group_idx[0] = idx;
+                if self.has_pawns {
+                    *indexes.lead_pawns_size.get(pairs.group_len[0])?.get(file)?
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                }
+            } else if k == order[1] as usize {
+                // Resto de los peones
+                pairs.group_idx[1] = idx;
+                binomial(
+                    pairs.group_len[1],
+                    48_usize.checked_sub(pairs.group_len[0])?,
+                )?
+            } else {
+                // Resto de las piezas
+                pairs.group_idx[next] = idx;
+                let size = binomial(pairs.group_len[next], free_squares)?;
+                free_squares = free_squares.checked_sub(pairs.group_len[next])?;
+                next += 1;
+                size
+            };
+            idx = idx.checked_mul(size)?;
+            k += 1;
+        }
+
+        pairs.group_idx[n] = idx;
+        Some(())
+    }
+
+    fn get(&self, stm: usize, file: usize) -> &PairsData {
+        let side = stm % self.items.len();
+        let file = if self.has_pawns { file } else { 0 };
+        &self.items[side][file]
+    }
+
+    /**
+     Calcula el índice de la posición dentro de la tabla y obtiene el valor guardado.
+     # Arguments
+     * `position` - La posición convertida a la numeración de Syzygy.
+     * `wdl` - El resultado de la posición, necesario para interpretar las tablas DTZ.
+     # Returns
+     El valor (resultado WDL o distancia DTZ) y el estado de la consulta.
+    */
+    fn probe(&self, position: &TablePosition, wdl: Wdl) -> Option<(i32, ProbeState)> {
+        let indexes = indexes();
+
+        // Si ambos bandos tienen el mismo material la tabla solo guarda el turno de las blancas
+        let symmetric_black_to_move = self.key == self.key2 && !position.white_to_move;
+        let black_stronger = position.material != self.key;
+        let flip = symmetric_black_to_move || black_stronger;
+
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip == position.white_to_move) as usize;
+
+        let mut squares: Vec<usize> = Vec::with_capacity(MAX_PIECES);
+        let mut pieces: Vec<u8> = Vec::with_capacity(MAX_PIECES);
+        let mut lead_pawns_count = 0;
+        let mut table_file = 0;
+
+        if self.has_pawns {
+            // Los peones líderes son del color del primer peón de la tabla
+            let lead_piece = self.get(0, 0).pieces[0] ^ flip_color;
+
+            for (square, code) in position.squares.iter() {
+                if *code == lead_piece {
+                    squares.push(square ^ flip_squares);
+                    pieces.push(*code ^ flip_color);
+                }
+            }
+
+            lead_pawns_count = squares.len();
+
+            // El peón líder es el más cercano al borde y, en la misma columna, el de menor fila
+            let lead = (0..lead_pawns_count)
+                .max_by_key(|i| (indexes.map_pawns[squares[*i]], usize::MAX - i))?;
+            squares.swap(0, lead);
+
+            let file = file_of(squares[0]);
+            table_file = file.min(7 - file);
+        }
+
+        // Las tablas DTZ solo guardan un turno
+        if self.dtz {
+            let flags = self.get(stm, table_file).flags;
+            let symmetric = self.key == self.key2 && !self.has_pawns;
+            if (flags & STM_FLAG) as usize != stm && !symmetric {
+                return Some((0, ProbeState::ChangeStm));
+            }
+        }
+
+        let lead_piece = if self.has_pawns {
+            Some(self.get(0, 0).pieces[0] ^ flip_color)
+        } else {
+            None
+        };
+
+        for (square, code) in position.squares.iter() {
+            if Some(*code) == lead_piece {
+                continue;
+            }
+            squares.push(square ^ flip_squares);
+            pieces.push(*code ^ flip_color);
+        }
+
+        let size = squares.len();
+        let pairs = self.get(stm, table_file);
+
+        // Ordenamos las piezas en la misma secuencia que la tabla
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            for j in (i + 1)..size {
+                if pairs.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // La primera pieza debe quedar en las columnas a-d
+        if file_of(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx: u64;
+
+        if self.has_pawns {
+            idx = *indexes
+                .lead_pawn_idx
+                .get(lead_pawns_count)?
+                .get(squares[0])?;
+
+            squares[1..lead_pawns_count].sort_by_key(|square| indexes.map_pawns[*square]);
+
+            for (i, square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                idx += binomial(i, indexes.map_pawns[*square])?;
+            }
+        } else {
+            // Sin peones la primera pieza también queda en las filas 1-4
+            if rank_of(squares[0]) > 3 {
+                for square in squares.iter_mut() {
+                    *square ^= 56;
+                }
+            }
+
+            // La primera pieza fuera de la diagonal a1-h8 debe quedar debajo de ella
+            for i in 0..pairs.group_len[0].min(size) {
+                if off_a1h8(squares[i]) == 0 {
+                    continue;
+                }
+
+                if off_a1h8(squares[i]) > 0 {
+                    for square in squares.iter_mut().skip(i) {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let adjust1 = (squares[1] > squares[0]) as usize;
+                let adjust2 =
+                    (squares[2] > squares[0]) as usize + (squares[2] > squares[1]) as usize;
+
+                idx = if off_a1h8(squares[0]) != 0 {
+                    ((indexes.map_a1d1d4[squares[0]] * 63 + (squares[1] - adjust1)) * 62
+                        + squares[2]
+                        - adjust2) as u64
+                } else if off_a1h8(squares[1]) != 0 {
+                    ((6 * 63 + rank_of(squares[0]) * 28 + indexes.map_b1h1h7[squares[1]]) * 62
+                        + squares[2]
+                        - adjust2) as u64
+                } else if off_a1h8(squares[2]) != 0 {
+                    (6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank_of(squares[0]) * 7 * 28
+                        + (rank_of(squares[1]) - adjust1) * 28
+                        + indexes.map_b1h1h7[squares[2]]) as u64
+                } else {
+                    (6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank_of(squares[0]) * 7 * 6
+                        + (rank_of(squares[1]) - adjust1) * 6
+                        + (rank_of(squares[2]) - adjust2)) as u64
+                };
+            } else {
+                idx = indexes.map_kk[indexes.map_a1d1d4[squares[0]]][squares[1]] as u64;
+            }
+        }
+
+        // Codificamos el resto de los grupos en orden ascendente de casilla
+        idx = idx.checked_mul(pairs.group_idx[0])?;
+        let mut group_start = pairs.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+
+        while next < pairs.group_len.len() && pairs.group_len[next] > 0 {
+            let group_end = group_start + pairs.group_len[next];
+            squares.get_mut(group_start..group_end)?.sort();
+
+            let mut n: u64 = 0;
+            for i in 0..pairs.group_len[next] {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start]
+                    .iter()
+                    .filter(|s| square > **s)
+                    .count();
+                let pawn_offset = if remaining_pawns { 8 } else { 0 };
+                let free = square.checked_sub(adjust + pawn_offset)?;
+                n += binomial(i + 1, free)?;
+            }
+
+            remaining_pawns = false;
+            idx = idx.checked_add(n.checked_mul(pairs.group_idx[next])?)?;
+            group_start = group_end;
+            next += 1;
+        }
+
+        let value = decompress_pairs(&self.bytes, pairs, idx)? as i32;
+        Some((self.map_score(table_file, value, wdl)?, ProbeState::Ok))
+    }
+
+    // Convierte el valor guardado en el resultado WDL o en la distancia DTZ en medias jugadas
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> Option<i32> {
+        if !self.dtz {
+            return Some(value - 2);
+        }
+
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+        let pairs = self.get(0, file);
+        let mut value = value;
+
+        if pairs.flags & MAPPED_FLAG != 0 {
+            let index = pairs.map_idx[WDL_MAP[(wdl.value() + 2) as usize]] + value as usize;
+            value = if pairs.flags & WIDE_FLAG != 0 {
+                read_u16(&self.bytes, self.map + 2 * index)? as i32
+            } else {
+                read_u8(&self.bytes, self.map + index)? as i32
+            };
+        }
+
+        if (wdl == Wdl::Win && pairs.flags & WIN_PLIES_FLAG == 0)
+            || (wdl == Wdl::Loss && pairs.flags & LOSS_PLIES_FLAG == 0)
+            || wdl == Wdl::CursedWin
+            || wdl == Wdl::BlessedLoss
+        {
+            value *= 2;
+        }
+
+        Some(value + 1)
+    }
+}
+
+// Lee los tamaños de la subtabla y construye la información para decodificar los símbolos
+fn set_sizes(bytes: &[u8], pairs: &mut PairsData, mut data: usize) -> Option<usize> {
+    pairs.flags = read_u8(bytes, data)?;
+    data += 1;
+
+    // Todas las posiciones guardan el mismo valor
+    if pairs.flags & SINGLE_VALUE_FLAG != 0 {
+        pairs.min_sym_len = read_u8(bytes, data)?;
+        return Some(data + 1);
+    }
+
+    let group_end = pairs.group_len.iter().position(|len| *len == 0)?;
+    let table_size = pairs.group_idx[group_end];
+
+    pairs.block_size = 1u64.checked_shl(read_u8(bytes, data)? as u32)?;
+    pairs.span = 1u64.checked_shl(read_u8(bytes, data + 1)? as u32)?;
+    pairs.sparse_index_size = table_size.div_ceil(pairs.span);
+    let padding = read_u8(bytes, data + 2)? as u64;
+    pairs.num_blocks = read_u32(bytes, data + 3)? as u64;
+    pairs.block_length_size = pairs.num_blocks + padding;
+    let max_sym_len = read_u8(bytes, data + 7)?;
+    pairs.min_sym_len = read_u8(bytes, data + 8)?;
+    data += 9;
+
+    pairs.lowest_sym = data;
+    let lengths = max_sym_len.checked_sub(pairs.min_sym_len)? as usize + 1;
+    pairs.base64 = vec![0; lengths];
+
+    // Código de Huffman canónico: los símbolos más largos tienen valores menores
+    for i in (0..lengths.saturating_sub(1)).rev() {
+        let lowest = read_u16(bytes, pairs.lowest_sym + 2 * i)? as u64;
+        let next_lowest = read_u16(bytes, pairs.lowest_sym + 2 * (i + 1))? as u64;
+        pairs.base64[i] = pairs.base64[i + 1]
+            .wrapping_add(lowest)
+            .wrapping_sub(next_lowest)
+            / 2;
+    }
+
+    for (i, base) in pairs.base64.iter_mut().enumerate() {
+        let shift = 64_u32.checked_sub(i as u32 + pairs.min_sym_len as u32)?;
+        *base = if shift >= 64 { 0 } else { *base << shift };
+    }
+
+    data += lengths * 2;
+    let symbols = read_u16(bytes, data)? as usize;
+    data += 2;
+    pairs.btree = data;
+
+    // El árbol de símbolos tiene que estar completo en el archivo
+    let end = data + symbols * 3 + (symbols & 1);
+    if end > bytes.len() {
+        return None;
+    }
+
+    // Cada símbolo se expande recursivamente en un par de símbolos
+    pairs.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            let length = set_symlen(bytes, pairs, symbol, &mut visited)?;
+            pairs.symlen[symbol] = length;
+        }
+    }
+
+    Some(end)
+}
+
+fn btree_left(bytes: &[u8], pairs: &PairsData, symbol: usize) -> Option<usize> {
+    let offset = pairs.btree + 3 * symbol;
+    Some((((read_u8(bytes, offset + 1)? & 0xF) as usize) << 8) | read_u8(bytes, offset)? as usize)
+}
+
+fn btree_right(bytes: &[u8], pairs: &PairsData, symbol: usize) -> Option<usize> {
+    let offset = pairs.btree + 3 * symbol;
+    Some(
+        ((read_u8(bytes, offset + 2)? as usize) << 4) | (read_u8(bytes, offset + 1)? >> 4) as usize,
+    )
+}
+
+fn set_symlen(
+    bytes: &[u8],
+    pairs: &mut PairsData,
+    symbol: usize,
+    visited: &mut [bool],
+) -> Option<u8> {
+    visited[symbol] = true;
+    let right = btree_right(bytes, pairs, symbol)?;
+
+    if right == 0xFFF {
+        return Some(0);
+    }
+
+    let left = btree_left(bytes, pairs, symbol)?;
+
+    // Un par que apunta fuera del árbol solo aparece en un archivo dañado
+    if left >= visited.len() || right >= visited.len() {
+        return None;
+    }
+
+    if !visited[left] {
+        let length = set_symlen(bytes, pairs, left, visited)?;
+        pairs.symlen[left] = length;
+    }
+
+    if !visited[right] {
+        let length = set_symlen(bytes, pairs, right, visited)?;
+        pairs.symlen[right] = length;
+    }
+
+    Some(
+        pairs.symlen[left]
+            .wrapping_add(pairs.symlen[right])
+            .wrapping_add(1),
+    )
+}
+
+// Obtiene el valor guardado en el índice `idx` de la subtabla comprimida, o `None` si los datos
+// no alcanzan o no tienen sentido
+fn decompress_pairs(bytes: &[u8], pairs: &PairsData, idx: u64) -> Option<u16> {
+    if pairs.flags & SINGLE_VALUE_FLAG != 0 {
+        return Some(pairs.min_sym_len as u16);
+    }
+
+    // Buscamos el bloque con el índice disperso
+    let k = usize::try_from(idx / pairs.span).ok()?;
+    let sparse = pairs.sparse_index.checked_add(k.checked_mul(6)?)?;
+    let mut block = read_u32(bytes, sparse)? as usize;
+    let mut offset = read_u16(bytes, sparse + 4)? as i64;
+
+    offset += (idx % pairs.span) as i64 - (pairs.span / 2) as i64;
+
+    let block_length = |block: usize| {
+        read_u16(
+            bytes,
+            pairs.block_length.checked_add(block.checked_mul(2)?)?,
+        )
+        .map(|length| length as i64)
+    };
+
+    while offset < 0 {
+        block = block.checked_sub(1)?;
+        offset += block_length(block)? + 1;
+    }
+
+    while offset > block_length(block)? {
+        offset -= block_length(block)? + 1;
+        block += 1;
+    }
+
+    // Decodificamos los símbolos del bloque hasta llegar al que contiene el índice
+    let mut pointer = pairs
+        .data
+        .checked_add(block.checked_mul(pairs.block_size as usize)?)?;
+    let mut buffer =
+        ((read_u32_be(bytes, pointer)? as u64) << 32) | read_u32_be(bytes, pointer + 4)? as u64;
+    pointer += 8;
+    let mut buffer_size = 64;
+    let mut symbol;
+
+    loop {
+        let mut length = 0;
+
+        while buffer < *pairs.base64.get(length)? {
+            length += 1;
+        }
+
+        let shift = 64_usize.checked_sub(length + pairs.min_sym_len as usize)?;
+        symbol = (buffer - pairs.base64[length])
+            .checked_shr(shift as u32)
+            .unwrap_or(0) as usize;
+        symbol += read_u16(bytes, pairs.lowest_sym + 2 * length)? as usize;
+
+        let symbol_length = *pairs.symlen.get(symbol)? as i64;
+        if offset < symbol_length + 1 {
+            break;
+        }
+
+        offset -= symbol_length + 1;
+        length += pairs.min_sym_len as usize;
+        if length >= 64 {
+            return None;
+        }
+        buffer <<= length;
+        buffer_size -= length as i32;
+
+        if buffer_size <= 0 {
+            return None;
+        }
+        if buffer_size <= 32 {
+            buffer_size += 32;
+            buffer |= (read_u32_be(bytes, pointer)? as u64) << (64 - buffer_size);
+            pointer += 4;
+        }
+    }
+
+    // Expandimos el símbolo hasta llegar a una hoja; cada paso baja a un símbolo más corto
+    while pairs.symlen[symbol] > 0 {
+        let left = btree_left(bytes, pairs, symbol)?;
+        let left_length = *pairs.symlen.get(left)?;
+
+        let next = if offset < left_length as i64 + 1 {
+            left
+        } else {
+            offset -= left_length as i64 + 1;
+            btree_right(bytes, pairs, symbol)?
+        };
+
+        if *pairs.symlen.get(next)? >= pairs.symlen[symbol] {
+            return None;
+        }
+        symbol = next;
+    }
+
+    btree_left(bytes, pairs, symbol).map(|value| value as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IA::random::Random;
+
+    // Tablas KQvK de un solo valor: con las blancas en turno ganan y con las negras pierden
+    fn kqvk_wdl() -> Vec<u8> {
+        let mut bytes = WDL_MAGIC.to_vec();
+        // Sin peones, orden de los grupos, rey y dama blancos y rey negro, relleno
+        bytes.extend_from_slice(&[0, 0x00, 0x66, 0x55, 0xEE, 0]);
+        // Cada turno guarda un único valor: 4 es victoria y 0 derrota
+        bytes.extend_from_slice(&[SINGLE_VALUE_FLAG, 4, SINGLE_VALUE_FLAG, 0]);
+        bytes.resize(64, 0);
+        bytes
+    }
+
+    // La tabla DTZ solo guarda el turno de las blancas, con 9 jugadas hasta reiniciar la cuenta
+    fn kqvk_dtz() -> Vec<u8> {
+        let mut bytes = DTZ_MAGIC.to_vec();
+        bytes.extend_from_slice(&[0, 0x00, 0x66, 0x55, 0xEE, 0]);
+        bytes.extend_from_slice(&[SINGLE_VALUE_FLAG, 9]);
+        bytes.resize(64, 0);
+        bytes
+    }
+
+    fn tablebase(directory: &str) -> Tablebase {
+        let directory = std::env::temp_dir().join(directory);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("KQvK.rtbw"), kqvk_wdl()).unwrap();
+        fs::write(directory.join("KQvK.rtbz"), kqvk_dtz()).unwrap();
+        Tablebase::open(directory.to_str().unwrap()).ok().unwrap()
+    }
+
+    fn board(fen: &str) -> Chessboard {
+        Chessboard::from_fen(fen).ok().unwrap()
+    }
+
+    #[test]
+    fn refuses_castling_rights_and_too_many_pieces() {
+        let tablebase = tablebase("ajedrez-syzygy-limites");
+        assert_eq!(tablebase.max_pieces(), 3);
+
+        assert!(tablebase.can_probe(&board("4k3/8/8/8/8/8/8/3QK3 w - - 0 1")));
+        assert!(!tablebase.can_probe(&board("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1")));
+        assert!(!tablebase.can_probe(&board("4k3/8/8/8/8/8/8/R2QK3 w - - 0 1")));
+        assert!(tablebase.probe_wdl(&Chessboard::new()).is_none());
+        assert!(tablebase.probe_dtz(&Chessboard::new()).is_none());
+    }
+
+    #[test]
+    fn probes_a_bundled_table() {
+        let tablebase = tablebase("ajedrez-syzygy-kqvk");
+
+        let white = board("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        let black = board("4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+        assert!(tablebase.probe_wdl(&white) == Some(Wdl::Win));
+        assert!(tablebase.probe_wdl(&black) == Some(Wdl::Loss));
+
+        // El valor 9 se guarda en jugadas completas: 18 medias jugadas más la que reinicia
+        assert_eq!(tablebase.probe_dtz(&white), Some(19));
+        // Las negras no están en la tabla DTZ: se busca una jugada y se suma la de ellas
+        assert_eq!(tablebase.probe_dtz(&black), Some(-20));
+
+        // Capturar la dama lleva a rey contra rey, que es mejor que lo que dice la tabla
+        let capture = board("4k3/3Q4/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(tablebase.probe_wdl(&capture) == Some(Wdl::Draw));
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupt_files() {
+        let wdl = kqvk_wdl();
+        for length in 0..wdl.len() {
+            assert!(Table::new("KQvK", wdl[..length].to_vec(), false).is_none());
+        }
+        assert!(Table::new("KQvK", kqvk_dtz()[..40].to_vec(), true).is_none());
+        assert!(Table::new("KQvK", kqvk_dtz(), false).is_none());
+        assert!(Table::new("KQQQQQQvK", wdl.clone(), false).is_none());
+
+        // Encabezados comprimidos con datos al azar: nunca debe entrar en pánico
+        let positions = [
+            TablePosition::new(&board("4k3/8/8/8/8/8/8/3QK3 w - - 0 1")),
+            TablePosition::new(&board("8/8/2k5/8/5Q2/8/8/7K b - - 0 1")),
+        ];
+        let mut random = Random::new(7);
+        for _ in 0..500 {
+            let mut bytes = wdl[..10].to_vec();
+            let length = 16 + (random.next_u64() % 4096) as usize;
+            bytes.extend((0..length).map(|_| random.next_u64() as u8));
+
+            let Some(table) = Table::new("KQvK", bytes, false) else {
+                continue;
+            };
+            for position in positions.iter() {
+                let _ = table.probe(position, Wdl::Draw);
+            }
+        }
+    }
+
+    #[test]
+    fn decompression_stops_at_the_end_of_the_file() {
+        let pairs = PairsData {
+            span: 1 << 10,
+            sparse_index: 60,
+            sparse_index_size: 4,
+            ..PairsData::default()
+        };
+        assert_eq!(decompress_pairs(&[0; 64], &pairs, 5000), None);
+
+        let mut pairs = PairsData::default();
+        pairs.group_len[0] = 3;
+        pairs.group_idx[1] = 31332;
+        // Bloques de 2^200 bytes
+        assert_eq!(
+            set_sizes(&[0, 200, 10, 0, 1, 0, 0, 0, 3, 1], &mut pairs, 0),
+            None
+        );
+        // Anuncia nueve símbolos pero el archivo se corta antes del árbol
+        assert_eq!(
+            set_sizes(&[0, 5, 10, 0, 1, 0, 0, 0, 1, 1, 0, 0, 9, 0], &mut pairs, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn index_tables_match_the_syzygy_encoding() {
+        assert_eq!(binomial(2, 5), Some(10));
+        assert_eq!(binomial(5, 63), Some(7_028_847));
+        assert_eq!(binomial(6, 10), None);
+
+        let indexes = indexes();
+        // Las 462 posiciones de dos reyes con el primero en el triángulo a1-d1-d4
+        let kings = indexes.map_kk.iter().flatten().max().unwrap();
+        assert_eq!(*kings, 461);
+        assert_eq!(indexes.map_a1d1d4[0], 6);
+        assert_eq!(indexes.map_a1d1d4[1], 0);
+        assert_eq!(*indexes.map_b1h1h7.iter().max().unwrap(), 27);
+        // Un peón líder puede estar en seis filas de su columna
+        assert_eq!(indexes.lead_pawns_size[1], [6, 6, 6, 6]);
+
+        assert_eq!(off_a1h8(0), 0);
+        assert!(off_a1h8(1) < 0);
+        assert!(off_a1h8(8) > 0);
+    }
+
+    #[test]
+    fn converts_positions_to_table_numbering() {
+        let position = TablePosition::new(&board("4k3/8/8/8/8/8/4P3/3QK3 b - - 0 1"));
+        assert_eq!(position.material, "KQPvK");
+        assert!(!position.white_to_move);
+        assert_eq!(position.squares, vec![(3, 5), (4, 6), (12, 1), (60, 14)]);
+
+        assert_eq!(piece_code(ChessPieceType::Pawn), 1);
+        assert_eq!(piece_code(ChessPieceType::King), 6);
+        assert!(wdl_from_dtz(-101) == Wdl::BlessedLoss);
+        assert!(wdl_from_dtz(101) == Wdl::CursedWin);
+        assert_eq!(dtz_before_zeroing(Wdl::Win), 1);
+    }
+}
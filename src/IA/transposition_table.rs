@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::pieces::piece_type::ChessPieceType;
+
+use super::move_generator::{square_index, Move};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+pub struct TranspositionEntry {
+    pub key: u64,
+    pub depth: i32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+// Bit que marca que la casilla de la tabla tiene una entrada
+const VALID_BIT: u64 = 1 << 63;
+
+/**
+ Tabla de transposición compartida entre los hilos de búsqueda. Cada entrada se guarda en dos
+ enteros atómicos: los datos y la clave combinada con los datos por XOR, así una entrada escrita a
+ medias por dos hilos a la vez no coincide con su clave y se descarta.
+*/
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    // Crea una tabla con el tamaño aproximado indicado en megabytes
+    pub fn new(size_mb: usize) -> Self {
+        let entry_size = std::mem::size_of::<[AtomicU64; 2]>();
+        let length = (size_mb * 1024 * 1024 / entry_size).max(1);
+
+        TranspositionTable {
+            entries: (0..length)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TranspositionEntry> {
+        let [checked_key, data] = &self.entries[self.index(key)];
+        let data = data.load(Ordering::Relaxed);
+
+        if data & VALID_BIT == 0 || checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+
+        Some(unpack(key, data))
+    }
+
+    // Guarda la entrada, reemplazando la anterior salvo que sea de la misma posición con más profundidad
+    pub fn store(&self, entry: TranspositionEntry) {
+        let index = self.index(entry.key);
+
+        if let Some(previous) = self.probe(entry.key) {
+            if previous.depth > entry.depth {
+                return;
+            }
+        }
+
+        let data = pack(&entry);
+        let [checked_key, stored_data] = &self.entries[index];
+        checked_key.store(entry.key ^ data, Ordering::Relaxed);
+        stored_data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for [checked_key, data] in self.entries.iter() {
+            checked_key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+}
+
+// Empaqueta la entrada en 64 bits: puntuación, profundidad, cota y movimiento
+fn pack(entry: &TranspositionEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+
+    let chess_move = match entry.best_move {
+        Some(chess_move) => {
+            let promotion = match chess_move.promotion {
+                Some(ChessPieceType::Knight) => 1,
+                Some(ChessPieceType::Bishop) => 2,
+                Some(ChessPieceType::Rook) => 3,
+                Some(ChessPieceType::Queen) => 4,
+                _ => 0,
+            };
+
+            1 << 15
+                | promotion << 12
+                | (square_index(chess_move.from) as u64) << 6
+                | square_index(chess_move.to) as u64
+        }
+        None => 0,
+    };
+
+    VALID_BIT
+        | chess_move << 26
+        | bound << 24
+        | (entry.depth as i8 as u8 as u64) << 16
+        | entry.score as i16 as u16 as u64
+}
+
+fn unpack(key: u64, data: u64) -> TranspositionEntry {
+    let bound = match (data >> 24) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+
+    let chess_move = (data >> 26) & 0xFFFF;
+    let best_move = if chess_move & (1 << 15) != 0 {
+        let square = |index: u64| [(index / 8) as usize, (index % 8) as usize];
+        let promotion = match (chess_move >> 12) & 7 {
+            1 => Some(ChessPieceType::Knight),
+            2 => Some(ChessPieceType::Bishop),
+            3 => Some(ChessPieceType::Rook),
+            4 => Some(ChessPieceType::Queen),
+            _ => None,
+        };
+
+        Some(Move {
+            from: square((chess_move >> 6) & 63),
+            to: square(chess_move & 63),
+            promotion,
+        })
+    } else {
+        None
+    };
+
+    TranspositionEntry {
+        key,
+        depth: ((data >> 16) & 0xFF) as u8 as i8 as i32,
+        score: (data & 0xFFFF) as u16 as i16 as i32,
+        bound,
+        best_move,
+    }
+}
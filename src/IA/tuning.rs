@@ -0,0 +1,347 @@
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+use super::evaluation::{weight_index, EvaluationWeights};
+use super::move_ordering::captured_piece;
+use super::pgn::{GameResult, PgnGame};
+use super::san::parse_san;
+
+// Cantidad de pesos de material antes de las tablas de posición
+const MATERIAL_WEIGHTS: usize = 5;
+
+pub struct TuningOptions {
+    // Medias jugadas del comienzo de cada partida que no se usan, porque suelen venir de libro
+    pub skip_plies: usize,
+    pub iterations: u32,
+    // Cuánto se mueve cada peso en cada intento, en centipeones
+    pub step: i32,
+}
+
+impl Default for TuningOptions {
+    fn default() -> Self {
+        TuningOptions {
+            skip_plies: 8,
+            iterations: 100,
+            step: 1,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TuningSummary {
+    pub games: usize,
+    pub skipped_games: usize,
+    pub positions: usize,
+    // Constante de escala de la sigmoide que mejor ajusta los pesos iniciales
+    pub scale: f64,
+    pub initial_error: f64,
+    pub final_error: f64,
+    pub iterations: u32,
+}
+
+// Posición tranquila con el resultado de su partida para las blancas: 1, 0.5 o 0
+struct TrainingPosition {
+    // Índice de cada peso que participa en la evaluación y su signo desde las blancas
+    features: Vec<(usize, i32)>,
+    result: f64,
+}
+
+/**
+ Ajusta los pesos de la evaluación con el método de Texel: busca los pesos que minimizan el error
+ cuadrático entre el resultado de cada partida y la probabilidad de ganar que predice la
+ evaluación de sus posiciones tranquilas.
+ # Arguments
+ * `games` - Las partidas con resultado.
+ * `initial` - Los pesos desde los que se empieza a ajustar.
+ * `options` - Las medias jugadas que se saltan, las iteraciones y el tamaño del paso.
+ * `progress` - Se llama al final de cada iteración con su número y el error.
+ # Returns
+ Los pesos ajustados y un resumen con las partidas, las posiciones y el error.
+*/
+pub fn tune(
+    games: &[PgnGame],
+    initial: &EvaluationWeights,
+    options: &TuningOptions,
+    mut progress: impl FnMut(u32, f64),
+) -> (EvaluationWeights, TuningSummary) {
+    let mut summary = TuningSummary::default();
+    let positions = extract_positions(games, options.skip_plies, &mut summary);
+    summary.positions = positions.len();
+
+    let mut params = to_params(initial);
+    if positions.is_empty() {
+        return (initial.clone(), summary);
+    }
+
+    // Posiciones en las que aparece cada peso, para recalcular solo las que cambian
+    let mut occurrences: Vec<Vec<(usize, i32)>> = vec![Vec::new(); params.len()];
+    for (index, position) in positions.iter().enumerate() {
+        for (param, sign) in position.features.iter() {
+            occurrences[*param].push((index, *sign));
+        }
+    }
+
+    let mut evaluations: Vec<i32> = positions
+        .iter()
+        .map(|position| evaluate_features(position, &params))
+        .collect();
+
+    summary.scale = fit_scale(&positions, &evaluations);
+    let scale = summary.scale;
+    let error = |position: &TrainingPosition, evaluation: i32| {
+        (position.result - win_probability(evaluation, scale)).powi(2)
+    };
+
+    let mut total_error: f64 = positions
+        .iter()
+        .zip(evaluations.iter())
+        .map(|(position, evaluation)| error(position, *evaluation))
+        .sum();
+    summary.initial_error = total_error / positions.len() as f64;
+
+    for iteration in 1..=options.iterations {
+        let mut improved = false;
+
+        for param in 0..params.len() {
+            if occurrences[param].is_empty() {
+                continue;
+            }
+
+            // Probamos subir el peso y, si no mejora, bajarlo
+            for delta in [options.step, -options.step] {
+                let change: f64 = occurrences[param]
+                    .iter()
+                    .map(|(index, sign)| {
+                        let position = &positions[*index];
+                        let evaluation = evaluations[*index];
+                        error(position, evaluation + sign * delta) - error(position, evaluation)
+                    })
+                    .sum();
+
+                if change < 0.0 {
+                    params[param] += delta;
+                    total_error += change;
+                    for (index, sign) in occurrences[param].iter() {
+                        evaluations[*index] += sign * delta;
+                    }
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        summary.iterations = iteration;
+        progress(iteration, total_error / positions.len() as f64);
+
+        if !improved {
+            break;
+        }
+    }
+
+    // Recalculamos el error desde cero para no arrastrar el redondeo de las sumas parciales
+    summary.final_error = positions
+        .iter()
+        .map(|position| error(position, evaluate_features(position, &params)))
+        .sum::<f64>()
+        / positions.len() as f64;
+
+    (from_params(&params), summary)
+}
+
+/**
+ Recorre las partidas y guarda las posiciones tranquilas: sin jaque y sin capturas ni coronaciones
+ en la jugada anterior ni en la siguiente, para que la evaluación estática tenga sentido.
+*/
+fn extract_positions(
+    games: &[PgnGame],
+    skip_plies: usize,
+    summary: &mut TuningSummary,
+) -> Vec<TrainingPosition> {
+    let mut positions = Vec::new();
+
+    for game in games {
+        let result = match game.result {
+            GameResult::WhiteWins => 1.0,
+            GameResult::BlackWins => 0.0,
+            GameResult::Draw => 0.5,
+            GameResult::Unknown => {
+                summary.skipped_games += 1;
+                continue;
+            }
+        };
+
+        let mut chessboard = match game.tag("FEN") {
+            Some(fen) => match Chessboard::from_fen(fen) {
+                Ok(chessboard) => chessboard,
+                Err(_) => {
+                    summary.skipped_games += 1;
+                    continue;
+                }
+            },
+            None => Chessboard::new(),
+        };
+
+        summary.games += 1;
+        let mut previous_was_tactical = false;
+
+        for (ply, san) in game.moves.iter().enumerate() {
+            // Movimientos que el tablero no admite, como la captura al paso, cortan la partida
+            let Some((chess_move, new_chessboard)) = parse_san(&chessboard, san) else {
+                break;
+            };
+
+            let tactical = chess_move.promotion.is_some()
+                || captured_piece(&chessboard, &chess_move).is_some();
+
+            if ply >= skip_plies
+                && !tactical
+                && !previous_was_tactical
+                && is_check(&chessboard, chessboard.player_turn).is_none()
+            {
+                positions.push(TrainingPosition {
+                    features: features(&chessboard),
+                    result,
+                });
+            }
+
+            previous_was_tactical = tactical;
+            chessboard = new_chessboard;
+        }
+    }
+
+    positions
+}
+
+// Pesos que suma cada pieza del tablero, con signo positivo para las blancas
+fn features(chessboard: &Chessboard) -> Vec<(usize, i32)> {
+    let mut features = Vec::new();
+
+    for (i, row) in chessboard.board.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            let Some(piece) = cell else {
+                continue;
+            };
+
+            let (sign, table_row) = match piece.color {
+                ChessPieceColor::White => (1, i),
+                ChessPieceColor::Black => (-1, 7 - i),
+            };
+
+            let index = weight_index(piece.piece);
+            if piece.piece != ChessPieceType::King {
+                features.push((index, sign));
+            }
+            features.push((MATERIAL_WEIGHTS + index * 64 + table_row * 8 + j, sign));
+        }
+    }
+
+    features
+}
+
+fn evaluate_features(position: &TrainingPosition, params: &[i32]) -> i32 {
+    position
+        .features
+        .iter()
+        .map(|(param, sign)| sign * params[*param])
+        .sum()
+}
+
+// Probabilidad de que ganen las blancas según la evaluación
+fn win_probability(evaluation: i32, scale: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scale * evaluation as f64 / 400.0))
+}
+
+// Busca la escala de la sigmoide con menor error, primero a grandes pasos y luego afinando
+fn fit_scale(positions: &[TrainingPosition], evaluations: &[i32]) -> f64 {
+    let mean_error = |scale: f64| {
+        positions
+            .iter()
+            .zip(evaluations.iter())
+            .map(|(position, evaluation)| {
+                (position.result - win_probability(*evaluation, scale)).powi(2)
+            })
+            .sum::<f64>()
+            / positions.len() as f64
+    };
+
+    let mut best = 1.0;
+    for step in [0.1, 0.01, 0.001] {
+        let center = best;
+        for offset in -10..=10 {
+            let scale = center + offset as f64 * step;
+            if scale > 0.0 && mean_error(scale) < mean_error(best) {
+                best = scale;
+            }
+        }
+    }
+
+    best
+}
+
+fn to_params(weights: &EvaluationWeights) -> Vec<i32> {
+    let mut params = weights.material.to_vec();
+    params.extend(weights.tables.iter().flatten().flatten());
+    params
+}
+
+fn from_params(params: &[i32]) -> EvaluationWeights {
+    let mut weights = EvaluationWeights::default();
+    weights.material.copy_from_slice(&params[..MATERIAL_WEIGHTS]);
+
+    for (index, value) in params[MATERIAL_WEIGHTS..].iter().enumerate() {
+        weights.tables[index / 64][(index / 8) % 8][index % 8] = *value;
+    }
+
+    weights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IA::pgn::parse_pgn;
+
+    // Finales tranquilos cuyo resultado coincide con el material
+    const GAMES: &str = r#"
+[FEN "4k3/8/8/8/8/8/8/3QK3 w - - 0 1"]
+1. Kf2 Kf7 2. Kg3 Ke6 1-0
+
+[FEN "3qk3/8/8/8/8/8/8/4K3 w - - 0 1"]
+1. Kf2 Kf7 2. Kg3 Kg6 0-1
+
+[FEN "4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1"]
+1. Kf2 Kf7 2. Kg3 Kg6 1/2-1/2
+
+[FEN "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"]
+1. Kf2 Kd7 *
+"#;
+
+    #[test]
+    fn one_pass_does_not_increase_the_error() {
+        let games = parse_pgn(GAMES);
+        let options = TuningOptions {
+            skip_plies: 0,
+            iterations: 1,
+            step: 5,
+        };
+
+        let (tuned, summary) = tune(&games, &EvaluationWeights::default(), &options, |_, _| {});
+        assert_eq!(summary.games, 3);
+        assert_eq!(summary.skipped_games, 1);
+        assert_eq!(summary.positions, 12);
+        assert_eq!(summary.iterations, 1);
+        assert!(summary.final_error <= summary.initial_error);
+        assert!(tuned != EvaluationWeights::default());
+    }
+
+    #[test]
+    fn params_keep_every_weight() {
+        let mut weights = EvaluationWeights::default();
+        weights.material[4] = 1000;
+        weights.tables[3][6][1] = 25;
+
+        let params = to_params(&weights);
+        assert_eq!(params.len(), MATERIAL_WEIGHTS + 6 * 64);
+        assert!(from_params(&params) == weights);
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::OnceLock;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+
+struct ZobristKeys {
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    castling: [u64; 4],
+}
+
+static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+
+// Generador splitmix64 con semilla fija, para que las claves sean siempre las mismas
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn keys() -> &'static ZobristKeys {
+    KEYS.get_or_init(|| {
+        let mut state = 0x0A1E_D2E5_u64;
+        let mut pieces = [[[0u64; 64]; 6]; 2];
+
+        for color in pieces.iter_mut() {
+            for piece in color.iter_mut() {
+                for square in piece.iter_mut() {
+                    *square = next_random(&mut state);
+                }
+            }
+        }
+
+        let black_to_move = next_random(&mut state);
+        let castling = [
+            next_random(&mut state),
+            next_random(&mut state),
+            next_random(&mut state),
+            next_random(&mut state),
+        ];
+
+        ZobristKeys {
+            pieces,
+            black_to_move,
+            castling,
+        }
+    })
+}
+
+pub fn piece_index(piece: ChessPieceType) -> usize {
+    match piece {
+        ChessPieceType::Pawn => 0,
+        ChessPieceType::Knight => 1,
+        ChessPieceType::Bishop => 2,
+        ChessPieceType::Rook => 3,
+        ChessPieceType::Queen => 4,
+        ChessPieceType::King => 5,
+    }
+}
+
+/**
+ Obtiene los derechos de enroque a partir de si el rey y las torres se han movido.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ # Returns
+ Un arreglo con los derechos en el orden: corto blanco, largo blanco, corto negro, largo negro.
+*/
+pub fn castling_rights(chessboard: &Chessboard) -> [bool; 4] {
+    let unmoved = |position: [usize; 2], piece: ChessPieceType, color: ChessPieceColor| {
+        match chessboard.board[position[0]][position[1]] {
+            Some(p) => p.piece == piece && p.color == color && p.before_position.is_none(),
+            None => false,
+        }
+    };
+
+    let white_king = unmoved([7, 4], ChessPieceType::King, ChessPieceColor::White);
+    let black_king = unmoved([0, 4], ChessPieceType::King, ChessPieceColor::Black);
+
+    [
+        white_king && unmoved([7, 7], ChessPieceType::Rook, ChessPieceColor::White),
+        white_king && unmoved([7, 0], ChessPieceType::Rook, ChessPieceColor::White),
+        black_king && unmoved([0, 7], ChessPieceType::Rook, ChessPieceColor::Black),
+        black_king && unmoved([0, 0], ChessPieceType::Rook, ChessPieceColor::Black),
+    ]
+}
+
+/**
+ Calcula la clave Zobrist del tablero.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ # Returns
+ `u64` - La clave que identifica la posición, el turno y los derechos de enroque.
+*/
+pub fn hash(chessboard: &Chessboard) -> u64 {
+    let keys = keys();
+    let mut key = 0;
+
+    for (i, row) in chessboard.board.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            if let Some(piece) = cell {
+                let color = match piece.color {
+                    ChessPieceColor::White => 0,
+                    ChessPieceColor::Black => 1,
+                };
+                key ^= keys.pieces[color][piece_index(piece.piece)][i * 8 + j];
+            }
+        }
+    }
+
+    for (index, right) in castling_rights(chessboard).iter().enumerate() {
+        if *right {
+            key ^= keys.castling[index];
+        }
+    }
+
+    if chessboard.player_turn == ChessPieceColor::Black {
+        key ^= keys.black_to_move;
+    }
+
+    key
+}
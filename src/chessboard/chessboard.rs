@@ -2,7 +2,7 @@ use crate::chessboard::utilities::clear_console;
 use crate::pieces::colors;
 use crate::pieces::piece_type::{ChessPiece, ChessPieceColor, ChessPieceType, Message};
 
-use super::chessboard_validation::{is_check, is_checkmate, validate_move};
+use super::chessboard_validation::{is_check, is_checkmate, validate_move_with_promotion};
 use super::player::Player;
 
 #[derive(Clone)]
@@ -61,6 +61,16 @@ impl Chessboard {
     }
 
     pub fn print_board(&self, message: String) {
+        self.print_board_with_panel(message, &[]);
+    }
+
+    /**
+     Igual que `print_board`, pero muestra un panel a la derecha del tablero, una línea por fila.
+     # Arguments
+     * `message` - El mensaje que se muestra junto al tablero.
+     * `panel` - Las líneas del panel; las que no caben junto a las filas se muestran debajo.
+    */
+    pub fn print_board_with_panel(&self, message: String, panel: &[String]) {
         let player_turn = match self.player_turn {
             ChessPieceColor::White => {
                 colors::BRIGHT_WHITE.to_string()
@@ -82,7 +92,7 @@ impl Chessboard {
         println!("      a   b   c   d   e   f   g   h");
         print!("    ┌───┬───┬───┬───┬───┬───┬───┬───┐\n  {} ", row_index);
 
-        for row in self.board.iter() {
+        for (index, row) in self.board.iter().enumerate() {
             for cell in row.iter() {
                 match cell {
                     // Some(piece) => print!("│ {:?} ", piece.position),
@@ -92,7 +102,10 @@ impl Chessboard {
             }
             row_index -= 1;
 
-            println!("│");
+            match panel.get(index) {
+                Some(line) => println!("│\t{}", line),
+                None => println!("│"),
+            }
 
             if row_index != 0 {
                 print!(
@@ -109,14 +122,37 @@ impl Chessboard {
             }
         }
         println!("    └───┴───┴───┴───┴───┴───┴───┴───┘");
+
+        for line in panel.iter().skip(8) {
+            println!("\t\t\t\t\t{}", line);
+        }
     }
 
     pub fn move_piece(&mut self, from: [usize; 2], to: [usize; 2]) -> Message {
+        self.move_piece_with_promotion(from, to, None)
+    }
+
+    /**
+     Igual que `move_piece`, pero permite indicar la pieza de coronación sin preguntarle al usuario.
+     # Arguments
+     * `from` - La posición de la pieza que se va a mover.
+     * `to` - La posición destino del movimiento.
+     * `promotion` - La pieza a coronar, si el movimiento es una coronación.
+     # Returns
+     Retorna el `Message` con el resultado del movimiento.
+    */
+    pub fn move_piece_with_promotion(
+        &mut self,
+        from: [usize; 2],
+        to: [usize; 2],
+        promotion: Option<ChessPieceType>,
+    ) -> Message {
         let from_position = self.board[from[0]][from[1]];
         let to_position = self.board[to[0]][to[1]];
 
         // Validamos el movimiento
-        let validation_result = validate_move(self, &from_position, &to_position, to);
+        let validation_result =
+            validate_move_with_promotion(self, &from_position, &to_position, to, promotion);
 
         match validation_result {
             Ok(new_chessboard) => {
@@ -1,4 +1,7 @@
-use super::{chessboard::Chessboard, utilities::new_chessboard_instance_after_move};
+use super::chessboard::Chessboard;
+use super::utilities::{
+    new_chessboard_instance_after_move, new_chessboard_instance_after_move_with_promotion,
+};
 use crate::pieces::allowed_moves::king_allowed_moves;
 use crate::pieces::piece_actions::PieceActions;
 use crate::pieces::piece_type::{ChessPiece, ChessPieceColor, ChessPieceType, Message};
@@ -20,6 +23,27 @@ pub fn validate_move(
     from_position: &Option<ChessPiece>,
     to_position: &Option<ChessPiece>,
     to: [usize; 2],
+) -> Result<Chessboard, Message> {
+    validate_move_with_promotion(chessboard, from_position, to_position, to, None)
+}
+
+/**
+ Igual que `validate_move`, pero sin preguntarle al usuario por la coronación.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `from_position` - Una referencia a una opción que contiene la pieza que se va a mover, si existe.
+ * `to_position` - Una referencia a una opción que contiene la pieza en la posición destino, si existe.
+ * `to` - La posición destino del movimiento.
+ * `promotion` - La pieza a coronar si el movimiento es una coronación. Con `None` se le pregunta al usuario.
+ # Returns
+ Retorna el mismo `Result` que `validate_move`.
+*/
+pub fn validate_move_with_promotion(
+    chessboard: &Chessboard,
+    from_position: &Option<ChessPiece>,
+    to_position: &Option<ChessPiece>,
+    to: [usize; 2],
+    promotion: Option<ChessPieceType>,
 ) -> Result<Chessboard, Message> {
     match from_position {
         Some(from_piece) => {
@@ -75,7 +99,9 @@ pub fn validate_move(
             }
 
             // Creamos una instancia temporal de chessboard
-            let temp_chessboard = new_chessboard_instance_after_move(chessboard, from_piece, to);
+            let temp_chessboard = new_chessboard_instance_after_move_with_promotion(
+                chessboard, from_piece, to, promotion,
+            );
 
             // Validamos que no quede en jaque después del movimiento
             if is_check(&temp_chessboard, chessboard.player_turn).is_some() {
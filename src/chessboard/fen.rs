@@ -0,0 +1,207 @@
+use crate::pieces::piece_type::{ChessPiece, ChessPieceColor, ChessPieceType, Message};
+
+use super::chessboard::Chessboard;
+
+impl Chessboard {
+    /**
+     Crea un tablero a partir de una posición en notación FEN.
+     El tablero no registra capturas al paso ni contadores de jugadas, así que esos campos se ignoran.
+     # Arguments
+     * `fen` - La posición, por ejemplo "8/8/8/8/8/8/4K3/4k2R w K - 0 1".
+     # Returns
+     Retorna el tablero, o `Message::InvalidFen` si la posición no es válida.
+    */
+    pub fn from_fen(fen: &str) -> Result<Chessboard, Message> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+
+        if fields.len() < 2 {
+            return Err(Message::InvalidFen);
+        }
+
+        let mut chessboard = Chessboard::new();
+        chessboard.board = [[None; 8]; 8];
+
+        let rows: Vec<&str> = fields[0].split('/').collect();
+        if rows.len() != 8 {
+            return Err(Message::InvalidFen);
+        }
+
+        let mut kings = [0, 0];
+
+        for (i, row) in rows.iter().enumerate() {
+            let mut j = 0;
+
+            for c in row.chars() {
+                // Los números indican casillas vacías
+                if let Some(empty) = c.to_digit(10) {
+                    j += empty as usize;
+                    continue;
+                }
+
+                if j > 7 {
+                    return Err(Message::InvalidFen);
+                }
+
+                let color = if c.is_ascii_uppercase() {
+                    ChessPieceColor::White
+                } else {
+                    ChessPieceColor::Black
+                };
+
+                let piece = match c.to_ascii_lowercase() {
+                    'k' => ChessPieceType::King,
+                    'q' => ChessPieceType::Queen,
+                    'r' => ChessPieceType::Rook,
+                    'b' => ChessPieceType::Bishop,
+                    'n' => ChessPieceType::Knight,
+                    'p' => ChessPieceType::Pawn,
+                    _ => return Err(Message::InvalidFen),
+                };
+
+                if piece == ChessPieceType::King {
+                    match color {
+                        ChessPieceColor::White => {
+                            kings[0] += 1;
+                            chessboard.player1.king_position = [i, j];
+                        }
+                        ChessPieceColor::Black => {
+                            kings[1] += 1;
+                            chessboard.player2.king_position = [i, j];
+                        }
+                    }
+                }
+
+                // Por defecto marcamos reyes y torres como movidos, el enroque se habilita abajo
+                let moved = matches!(piece, ChessPieceType::King | ChessPieceType::Rook);
+
+                chessboard.board[i][j] = Some(ChessPiece {
+                    piece,
+                    color,
+                    position: [i, j],
+                    before_position: if moved { Some([i, j]) } else { None },
+                });
+
+                j += 1;
+            }
+
+            if j != 8 {
+                return Err(Message::InvalidFen);
+            }
+        }
+
+        // Cada jugador debe tener exactamente un rey
+        if kings != [1, 1] {
+            return Err(Message::InvalidFen);
+        }
+
+        chessboard.player_turn = match fields[1] {
+            "w" => ChessPieceColor::White,
+            "b" => ChessPieceColor::Black,
+            _ => return Err(Message::InvalidFen),
+        };
+
+        // Derechos de enroque: el rey y la torre correspondiente quedan sin moverse
+        for right in fields.get(2).unwrap_or(&"-").chars() {
+            let (king, rook, color) = match right {
+                'K' => ([7, 4], [7, 7], ChessPieceColor::White),
+                'Q' => ([7, 4], [7, 0], ChessPieceColor::White),
+                'k' => ([0, 4], [0, 7], ChessPieceColor::Black),
+                'q' => ([0, 4], [0, 0], ChessPieceColor::Black),
+                '-' => continue,
+                _ => return Err(Message::InvalidFen),
+            };
+
+            for (position, piece_type) in
+                [(king, ChessPieceType::King), (rook, ChessPieceType::Rook)]
+            {
+                match &mut chessboard.board[position[0]][position[1]] {
+                    Some(piece) if piece.piece == piece_type && piece.color == color => {
+                        piece.before_position = None;
+                    }
+                    _ => return Err(Message::InvalidFen),
+                }
+            }
+        }
+
+        Ok(chessboard)
+    }
+
+    /**
+     Convierte el tablero a notación FEN.
+     # Returns
+     La posición en FEN. Como el tablero no registra capturas al paso ni contadores, se escriben
+     "-", "0" y "1" en esos campos.
+    */
+    pub fn to_fen(&self) -> String {
+        let mut rows: Vec<String> = Vec::new();
+
+        for row in self.board.iter() {
+            let mut text = String::new();
+            let mut empty = 0;
+
+            for cell in row.iter() {
+                match cell {
+                    Some(piece) => {
+                        if empty > 0 {
+                            text.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+
+                        let letter = match piece.piece {
+                            ChessPieceType::King => 'k',
+                            ChessPieceType::Queen => 'q',
+                            ChessPieceType::Rook => 'r',
+                            ChessPieceType::Bishop => 'b',
+                            ChessPieceType::Knight => 'n',
+                            ChessPieceType::Pawn => 'p',
+                        };
+
+                        text.push(match piece.color {
+                            ChessPieceColor::White => letter.to_ascii_uppercase(),
+                            ChessPieceColor::Black => letter,
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+            rows.push(text);
+        }
+
+        let turn = match self.player_turn {
+            ChessPieceColor::White => "w",
+            ChessPieceColor::Black => "b",
+        };
+
+        // Derechos de enroque según si el rey y las torres se han movido
+        let unmoved = |position: [usize; 2], piece_type: ChessPieceType, color: ChessPieceColor| {
+            matches!(
+                self.board[position[0]][position[1]],
+                Some(piece) if piece.piece == piece_type && piece.color == color && piece.before_position.is_none()
+            )
+        };
+
+        let mut castling = String::new();
+        for (letter, king, rook, color) in [
+            ('K', [7, 4], [7, 7], ChessPieceColor::White),
+            ('Q', [7, 4], [7, 0], ChessPieceColor::White),
+            ('k', [0, 4], [0, 7], ChessPieceColor::Black),
+            ('q', [0, 4], [0, 0], ChessPieceColor::Black),
+        ] {
+            if unmoved(king, ChessPieceType::King, color)
+                && unmoved(rook, ChessPieceType::Rook, color)
+            {
+                castling.push(letter);
+            }
+        }
+
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        format!("{} {} {} - 0 1", rows.join("/"), turn, castling)
+    }
+}
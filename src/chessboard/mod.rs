@@ -1,4 +1,5 @@
 pub mod chessboard;
 pub mod utilities;
-mod chessboard_validation;
+pub mod chessboard_validation;
+pub mod fen;
 pub mod player;
\ No newline at end of file
@@ -17,7 +17,28 @@ pub fn clear_console() {
     }
 }
 
+// Lo que puede escribir el jugador: una casilla o uno de los comandos permitidos
+pub enum PlayerInput {
+    Coordinates([usize; 2]),
+    Command(String),
+}
+
 pub fn get_coordinates(message: &str) -> [usize; 2] {
+    match get_input(message, &[]) {
+        PlayerInput::Coordinates(coordinates) => coordinates,
+        PlayerInput::Command(_) => unreachable!(),
+    }
+}
+
+/**
+ Pide al jugador una casilla, aceptando también algunos comandos.
+ # Arguments
+ * `message` - El mensaje que se muestra al pedir la entrada.
+ * `commands` - Los comandos que se aceptan además de las casillas, por ejemplo "pista".
+ # Returns
+ Retorna la casilla o el comando escrito, y vuelve a preguntar si la entrada no es válida.
+*/
+pub fn get_input(message: &str, commands: &[&str]) -> PlayerInput {
     loop {
         println!("{}", message);
         let mut input = String::new();
@@ -26,6 +47,11 @@ pub fn get_coordinates(message: &str) -> [usize; 2] {
             continue;
         }
 
+        let command = input.trim().to_lowercase();
+        if commands.contains(&command.as_str()) {
+            return PlayerInput::Command(command);
+        }
+
         let chess_coords: Vec<&str> = input.trim().split_whitespace().collect();
 
         if chess_coords.len() == 2 {
@@ -50,7 +76,7 @@ pub fn get_coordinates(message: &str) -> [usize; 2] {
                 };
 
                 if x < 8 && y < 8 {
-                    return [x, y];
+                    return PlayerInput::Coordinates([x, y]);
                 }
             }
         }
@@ -63,6 +89,25 @@ pub fn new_chessboard_instance_after_move(
     chessboard: &Chessboard,
     from_piece: &ChessPiece,
     to: [usize; 2],
+) -> Chessboard {
+    new_chessboard_instance_after_move_with_promotion(chessboard, from_piece, to, None)
+}
+
+/**
+ Igual que `new_chessboard_instance_after_move`, pero permite indicar la pieza de coronación.
+ # Arguments
+ * `chessboard` - Una referencia al tablero de ajedrez actual.
+ * `from_piece` - La pieza que se va a mover.
+ * `to` - La posición destino del movimiento.
+ * `promotion` - La pieza a coronar si el peón llega a la última fila. Con `None` se le pregunta al usuario.
+ # Returns
+ Retorna una nueva instancia del tablero después del movimiento.
+*/
+pub fn new_chessboard_instance_after_move_with_promotion(
+    chessboard: &Chessboard,
+    from_piece: &ChessPiece,
+    to: [usize; 2],
+    promotion: Option<ChessPieceType>,
 ) -> Chessboard {
     // Clonamos el tablero y la pieza para trabajar con copias temporales
     let mut temp_chessboard = chessboard.clone();
@@ -85,7 +130,7 @@ pub fn new_chessboard_instance_after_move(
 
     // Si la pieza es un peón, verificamos si se puede promocionar
     if temp_piece.piece == ChessPieceType::Pawn {
-        temp_piece = pawn_promotion(temp_piece);
+        temp_piece = pawn_promotion(temp_piece, promotion);
     }
 
     // Actualizamos el tablero: movemos la pieza y vaciamos su posición anterior
@@ -133,29 +178,20 @@ fn get_piece_type(message: &str) -> ChessPieceType {
     }
 }
 
-fn pawn_promotion(pawn_piece: ChessPiece) -> ChessPiece {
+fn pawn_promotion(pawn_piece: ChessPiece, promotion: Option<ChessPieceType>) -> ChessPiece {
     let mut temp_pawn_piece = pawn_piece.clone();
 
-    match temp_pawn_piece.color {
-        ChessPieceColor::White => {
-            if temp_pawn_piece.position[0] == 0 {
-                // Preguntamos al usuario que ficha quiere
-                let piece_type =
-                    get_piece_type("Reina: \"D\"\nAlfil: \"A\"\nTorre: \"T\"\nCaballo: \"C\"");
-
-                temp_pawn_piece.piece = piece_type;
-            }
-        }
-        ChessPieceColor::Black => {
-            if temp_pawn_piece.position[0] == 7 {
-                // Preguntamos al usuario que ficha quiere
-                let piece_type =
-                    get_piece_type("Reina: \"D\"\nAlfil: \"A\"\nTorre: \"T\"\nCaballo: \"C\"");
-
-                temp_pawn_piece.piece = piece_type;
-            }
-        }
+    let last_row = match temp_pawn_piece.color {
+        ChessPieceColor::White => 0,
+        ChessPieceColor::Black => 7,
+    };
 
+    if temp_pawn_piece.position[0] == last_row {
+        temp_pawn_piece.piece = match promotion {
+            Some(piece_type) => piece_type,
+            // Preguntamos al usuario que ficha quiere
+            None => get_piece_type("Reina: \"D\"\nAlfil: \"A\"\nTorre: \"T\"\nCaballo: \"C\""),
+        };
     }
     
     temp_pawn_piece
@@ -0,0 +1,281 @@
+use crate::chessboard::chessboard::Chessboard;
+use crate::protocols::external_engine::ExternalEngine;
+use crate::IA::mate_solver::{format_mate_line, MateResult, MateSolver, ProblemType};
+use crate::IA::search::{Engine, RootMove, SearchLimits, MATE_SCORE, MAX_PLY, TABLEBASE_WIN_SCORE};
+use crate::IA::syzygy::Tablebase;
+
+use super::{create_engine, option_value, parse_depth};
+
+// Variantes que se muestran al analizar y cuántos movimientos de cada una
+const DEFAULT_ANALYSIS_LINES: usize = 3;
+const MAX_PANEL_VARIATION: usize = 8;
+
+/**
+ Analiza una posición mostrando las mejores variantes, con el motor propio o con el motor UCI
+ externo de --programa.
+ # Arguments
+ * `args` - Los argumentos del programa: el FEN, la profundidad y las opciones.
+*/
+pub fn run_analysis(args: &[String]) {
+    let Some(fen) = args.get(1) else {
+        println!("Uso: analizar \"<fen>\" [profundidad] [--lineas N] [--hilos N] [--syzygy directorio] [--programa motor]");
+        return;
+    };
+
+    let board = match Chessboard::from_fen(fen) {
+        Ok(board) => board,
+        Err(message) => {
+            println!("{}", message.get_message());
+            return;
+        }
+    };
+
+    let lines = option_value(args, "--lineas")
+        .and_then(|lines| lines.parse().ok())
+        .unwrap_or(DEFAULT_ANALYSIS_LINES);
+
+    if let Some(command) = option_value(args, "--programa") {
+        analyze_external(&board, command, parse_depth(args.get(2)), lines);
+        return;
+    }
+
+    let Some((mut engine, tablebase)) = create_engine(args) else {
+        return;
+    };
+
+    analyze(
+        &board,
+        &mut engine,
+        tablebase.as_deref(),
+        parse_depth(args.get(2)),
+        lines,
+    );
+}
+
+/**
+ Comprueba un problema de mate en N: directo, ayudado con --ayudado o inverso con --inverso.
+ # Arguments
+ * `args` - Los argumentos del programa: el FEN, las jugadas y el tipo de problema.
+*/
+pub fn run_mate(args: &[String]) {
+    let moves = args
+        .get(2)
+        .and_then(|moves| moves.parse().ok())
+        .filter(|moves: &u32| *moves >= 1);
+    let (Some(fen), Some(moves)) = (args.get(1), moves) else {
+        println!("Uso: mate \"<fen>\" <jugadas> [--ayudado|--inverso]");
+        return;
+    };
+
+    let problem_type = if args.iter().any(|arg| arg == "--ayudado") {
+        ProblemType::Helpmate
+    } else if args.iter().any(|arg| arg == "--inverso") {
+        ProblemType::Selfmate
+    } else {
+        ProblemType::Direct
+    };
+
+    match Chessboard::from_fen(fen) {
+        Ok(board) => solve_mate(&board, moves, problem_type),
+        Err(message) => println!("{}", message.get_message()),
+    }
+}
+
+// Muestra todas las claves con el árbol de defensas, o la refutación de cada intento
+fn solve_mate(board: &Chessboard, moves: u32, problem_type: ProblemType) {
+    let genre = match problem_type {
+        ProblemType::Direct => "Mate",
+        ProblemType::Helpmate => "Mate ayudado",
+        ProblemType::Selfmate => "Mate inverso",
+    };
+
+    let mut solver = MateSolver::new(problem_type);
+    let start = std::time::Instant::now();
+    let result = solver.solve(board, moves);
+    let mut lines = Vec::new();
+
+    match result {
+        MateResult::Solved(keys) => {
+            if keys.len() > 1 {
+                lines.push(format!(
+                    "Problema cocinado: {} claves resuelven el {} en {} o menos",
+                    keys.len(),
+                    genre.to_lowercase(),
+                    moves
+                ));
+            }
+
+            for (length, key) in keys.iter() {
+                lines.push(format!("{} en {}:", genre, length));
+                format_mate_line(key, &mut lines);
+            }
+        }
+        MateResult::Refuted(refutations) => {
+            lines.push(format!("No hay {} en {}", genre.to_lowercase(), moves));
+
+            for refutation in refutations.iter() {
+                lines.push(match &refutation.defence {
+                    Some(defence) => format!("1. {}? {}!", refutation.san, defence),
+                    None => format!("1. {}? (el rival no puede mover)", refutation.san),
+                });
+            }
+        }
+        MateResult::Helpmates(solutions) => {
+            match solutions.len() {
+                0 => lines.push(format!("No hay {} en {}", genre.to_lowercase(), moves)),
+                1 => lines.push(format!("{} en {}:", genre, moves)),
+                count => lines.push(format!(
+                    "Problema cocinado: {} soluciones del {} en {}",
+                    count,
+                    genre.to_lowercase(),
+                    moves
+                )),
+            }
+
+            lines.extend(solutions);
+        }
+    }
+
+    lines.push(format!(
+        "{} posiciones en {} ms",
+        solver.nodes,
+        start.elapsed().as_millis()
+    ));
+
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+// Muestra el tablero con las mejores variantes del motor en un panel a la derecha
+fn analyze(
+    board: &Chessboard,
+    engine: &mut Engine,
+    tablebase: Option<&Tablebase>,
+    depth: u32,
+    lines: usize,
+) {
+    let limits = SearchLimits {
+        depth,
+        time: None,
+        nodes: None,
+    };
+    let result = engine.search_multi_pv(board, limits, lines);
+
+    let mut panel = vec![format!(
+        "Análisis a profundidad {}: {} nodos en {} ms",
+        result.depth,
+        result.stats.nodes,
+        result.elapsed.as_millis()
+    )];
+
+    push_variations(&mut panel, &result.root_moves);
+
+    // Resultado teórico de la posición según las tablas de finales
+    if let Some(tablebase) = tablebase {
+        panel.push(
+            match (tablebase.probe_wdl(board), tablebase.probe_dtz(board)) {
+                (Some(wdl), Some(dtz)) => {
+                    format!("Tablas de finales: {} (DTZ {})", wdl.description(), dtz)
+                }
+                (Some(wdl), None) => format!("Tablas de finales: {}", wdl.description()),
+                _ => "Tablas de finales: la posición no está en las tablas".to_string(),
+            },
+        );
+
+        if result.stats.tablebase_hits > 0 {
+            panel.push(format!(
+                "Consultas a las tablas durante la búsqueda: {}",
+                result.stats.tablebase_hits
+            ));
+        }
+    }
+
+    board.print_board_with_panel(format!("FEN: {}", board.to_fen()), &panel);
+}
+
+/**
+ Analiza la posición con un motor UCI externo y muestra sus variantes igual que las del propio.
+ # Arguments
+ * `board` - La posición.
+ * `command` - El programa del motor y sus argumentos.
+ * `depth` - La profundidad que se pide al motor.
+ * `lines` - Cuántas variantes se muestran.
+*/
+fn analyze_external(board: &Chessboard, command: &str, depth: u32, lines: usize) {
+    let limits = SearchLimits {
+        depth,
+        time: None,
+        nodes: None,
+    };
+
+    let result = ExternalEngine::start(command).and_then(|mut engine| {
+        engine
+            .search(board, limits, lines)
+            .map(|result| (engine, result))
+    });
+
+    let (engine, result) = match result {
+        Ok(analysis) => analysis,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let mut panel = vec![format!(
+        "Análisis de {} a profundidad {}: {} nodos en {} ms",
+        engine.name,
+        result.depth,
+        result.nodes,
+        result.elapsed.as_millis()
+    )];
+    push_variations(&mut panel, &result.root_moves);
+
+    board.print_board_with_panel(format!("FEN: {}", board.to_fen()), &panel);
+}
+
+// Agrega al panel una línea por variante con su evaluación y sus primeros movimientos
+fn push_variations(panel: &mut Vec<String>, root_moves: &[RootMove]) {
+    for (index, root_move) in root_moves.iter().enumerate() {
+        let variation: Vec<String> = root_move
+            .principal_variation
+            .iter()
+            .take(MAX_PANEL_VARIATION)
+            .map(|chess_move| chess_move.to_coordinates())
+            .collect();
+
+        panel.push(format!(
+            "{}. {:>8}  {}",
+            index + 1,
+            describe_score(root_move.score),
+            variation.join(" ")
+        ));
+    }
+
+    if root_moves.is_empty() {
+        panel.push("No hay movimientos legales".to_string());
+    }
+}
+
+// Muestra la evaluación en peones, en jugadas si es un mate o el resultado de las tablas de finales
+pub fn describe_score(score: i32) -> String {
+    if score.abs() >= MATE_SCORE - MAX_PLY as i32 {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        return if score > 0 {
+            format!("#{}", moves)
+        } else {
+            format!("#-{}", moves)
+        };
+    }
+
+    if score.abs() >= TABLEBASE_WIN_SCORE / 2 - MAX_PLY as i32 {
+        return if score > 0 {
+            "victoria".to_string()
+        } else {
+            "derrota".to_string()
+        };
+    }
+
+    format!("{:+.2}", score as f64 / 100.0)
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use crate::IA::opening_book::OpeningBook;
+use crate::IA::search::{Engine, SearchConfig};
+use crate::IA::syzygy::Tablebase;
+
+pub mod analysis;
+pub mod play;
+pub mod tools;
+
+// Profundidad por defecto del oponente computadora
+const DEFAULT_DEPTH: u32 = 5;
+
+// Devuelve el valor que sigue a una opción, por ejemplo la ruta en "--libro ruta.bin"
+pub fn option_value<'a>(args: &'a [String], option: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == option)
+        .and_then(|index| args.get(index + 1))
+}
+
+// Cantidad de hilos de búsqueda de la opción --hilos, por defecto uno
+pub fn parse_threads(args: &[String]) -> usize {
+    option_value(args, "--hilos")
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or(1)
+}
+
+pub fn parse_depth(arg: Option<&String>) -> u32 {
+    arg.and_then(|depth| depth.parse().ok())
+        .unwrap_or(DEFAULT_DEPTH)
+}
+
+// Crea el motor con los hilos, el libro de aperturas y las tablas de finales indicados en las opciones
+pub fn create_engine(args: &[String]) -> Option<(Engine, Option<Arc<Tablebase>>)> {
+    let mut engine = Engine::new(SearchConfig::default());
+    let mut tablebase = None;
+
+    engine.set_threads(parse_threads(args));
+
+    if let Some(path) = option_value(args, "--libro") {
+        match OpeningBook::open(path) {
+            Ok(book) if book.is_empty() => println!("El libro {} está vacío", path),
+            Ok(book) => engine.set_book(book),
+            Err(error) => {
+                println!("No se pudo leer el libro {}: {}", path, error);
+                return None;
+            }
+        }
+    }
+
+    if let Some(directory) = option_value(args, "--syzygy") {
+        match Tablebase::open(directory) {
+            Ok(tables) => {
+                println!(
+                    "Tablas de finales de hasta {} piezas en {}",
+                    tables.max_pieces(),
+                    directory
+                );
+                let tables = Arc::new(tables);
+                engine.set_tablebase(Arc::clone(&tables));
+                tablebase = Some(tables);
+            }
+            Err(error) => {
+                println!("No se pudieron leer las tablas de {}: {}", directory, error);
+                return None;
+            }
+        }
+    }
+
+    Some((engine, tablebase))
+}
@@ -0,0 +1,446 @@
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::utilities::{get_coordinates, get_input, PlayerInput};
+use crate::pieces::piece_type::{ChessPieceColor, Message};
+use crate::protocols::external_engine::ExternalEngine;
+use crate::IA::hint::hint;
+use crate::IA::mcts::{MctsConfig, MctsEngine, MctsPolicy};
+use crate::IA::move_generator::{generate_moves, square_name, Move};
+use crate::IA::random::Random;
+use crate::IA::search::{Engine, SearchConfig, SearchLimits, SearchResult};
+use crate::IA::strength::{choose_move, DifficultyLevel, Strength};
+
+use super::analysis::describe_score;
+use super::{create_engine, option_value, parse_depth};
+
+// Comandos para pedir una pista durante la partida
+const HINT_COMMANDS: [&str; 2] = ["pista", "hint"];
+
+/**
+ Partida contra la computadora con el color, la fuerza y el motor indicados en las opciones.
+ # Arguments
+ * `args` - Los argumentos del programa: el color de la computadora ("blanco", "negro" o
+   "ambos"), la profundidad y las opciones.
+*/
+pub fn play_against_computer(args: &[String]) {
+    let colors: &[&str] = match args.get(1).map(|arg| arg.as_str()) {
+        Some("blanco") => &["blanco"],
+        Some("ambos") => &["blanco", "negro"],
+        _ => &["negro"],
+    };
+
+    let Some(strength) = parse_strength(args) else {
+        return;
+    };
+
+    let mut players = [None, None];
+    for (index, color) in ["blanco", "negro"].iter().enumerate() {
+        if colors.contains(color) {
+            let Some(player) = parse_computer(args, color, strength) else {
+                return;
+            };
+            players[index] = Some(player);
+        }
+    }
+
+    let Some((engine, _)) = create_engine(args) else {
+        return;
+    };
+
+    play(players, engine, args.iter().any(|arg| arg == "--ponder"));
+}
+
+// Partida entre dos personas en la misma computadora
+pub fn play_two_players() {
+    play([None, None], Engine::new(SearchConfig::default()), false);
+}
+
+// Fuerza de la computadora según --nivel o --elo, o sin límites con la profundidad indicada
+fn parse_strength(args: &[String]) -> Option<Strength> {
+    if let Some(name) = option_value(args, "--nivel") {
+        let Some(level) = DifficultyLevel::from_name(name) else {
+            let names: Vec<&str> = DifficultyLevel::ALL
+                .iter()
+                .map(|level| level.name())
+                .collect();
+            println!("Nivel desconocido: {}. Niveles: {}", name, names.join(", "));
+            return None;
+        };
+
+        println!("Nivel {} (Elo aproximado {})", level.name(), level.elo());
+        return Some(level.strength());
+    }
+
+    if let Some(elo) = option_value(args, "--elo") {
+        let Ok(elo) = elo.parse() else {
+            println!("Elo inválido: {}", elo);
+            return None;
+        };
+
+        println!("Elo objetivo {}", elo);
+        return Some(Strength::from_elo(elo));
+    }
+
+    // La profundidad es el argumento que sigue al color, si no es una opción
+    Some(Strength::full(parse_depth(args.get(2))))
+}
+
+/**
+ Crea la computadora de un color con el motor indicado en --motor-<color> o en --motor.
+ # Arguments
+ * `args` - Los argumentos del programa.
+ * `color` - "blanco" o "negro".
+ * `strength` - La fuerza del motor alfa-beta.
+ # Returns
+ La computadora, o `None` si alguna opción no es válida.
+*/
+fn parse_computer(args: &[String], color: &str, strength: Strength) -> Option<ComputerPlayer> {
+    let engine = option_value(args, &format!("--motor-{}", color))
+        .or_else(|| option_value(args, "--motor"))
+        .map_or("alfabeta", |engine| engine.as_str());
+
+    match engine {
+        "alfabeta" => Some(ComputerPlayer::AlphaBeta(strength)),
+        "mcts" => {
+            let mut config = MctsConfig::default();
+
+            if let Some(name) = option_value(args, "--politica") {
+                let Some(policy) = MctsPolicy::from_name(name) else {
+                    let names: Vec<&str> =
+                        MctsPolicy::ALL.iter().map(|policy| policy.name()).collect();
+                    println!(
+                        "Política desconocida: {}. Políticas: {}",
+                        name,
+                        names.join(", ")
+                    );
+                    return None;
+                };
+                config.policy = policy;
+            }
+
+            if let Some(iterations) = option_value(args, "--iteraciones") {
+                let Ok(iterations) = iterations.parse() else {
+                    println!("Cantidad de iteraciones inválida: {}", iterations);
+                    return None;
+                };
+                config.iterations = iterations;
+            }
+
+            if let Some(exploration) = option_value(args, "--exploracion") {
+                let Ok(exploration) = exploration.parse() else {
+                    println!("Constante de exploración inválida: {}", exploration);
+                    return None;
+                };
+                config.exploration = exploration;
+            }
+
+            println!(
+                "Computadora {}: MCTS, política {}, {} iteraciones",
+                color,
+                config.policy.name(),
+                config.iterations
+            );
+            Some(ComputerPlayer::Mcts(MctsEngine::new(config)))
+        }
+        "externo" => {
+            let Some(command) = option_value(args, &format!("--programa-{}", color))
+                .or_else(|| option_value(args, "--programa"))
+            else {
+                println!("Falta el programa del motor externo: --programa <ruta>");
+                return None;
+            };
+
+            let time = match option_value(args, "--tiempo").map(|time| time.parse()) {
+                Some(Ok(millis)) => Some(Duration::from_millis(millis)),
+                Some(Err(_)) => {
+                    println!("Tiempo inválido, se esperan milisegundos");
+                    return None;
+                }
+                None => None,
+            };
+
+            let mut external = match ExternalEngine::start(command) {
+                Ok(external) => external,
+                Err(error) => {
+                    println!("{}", error);
+                    return None;
+                }
+            };
+
+            if let Err(error) = external.new_game() {
+                println!("{}", error);
+                return None;
+            }
+
+            println!("Computadora {}: {}", color, external.name);
+            let limits = SearchLimits {
+                depth: strength.depth,
+                time,
+                nodes: strength.nodes,
+            };
+            Some(ComputerPlayer::External(Box::new(external), limits))
+        }
+        _ => {
+            println!(
+                "Motor desconocido: {}. Motores: alfabeta, mcts, externo",
+                engine
+            );
+            None
+        }
+    }
+}
+
+// Cómo elige sus movimientos cada computadora
+enum ComputerPlayer {
+    AlphaBeta(Strength),
+    Mcts(MctsEngine),
+    // Un programa UCI que corre aparte, con sus límites de búsqueda
+    External(Box<ExternalEngine>, SearchLimits),
+}
+
+/**
+ Calcula el movimiento de la computadora con su motor.
+ # Arguments
+ * `computer` - La computadora en turno.
+ * `engine` - El motor alfa-beta compartido.
+ * `board` - El tablero actual.
+ * `random` - El generador de números aleatorios para limitar la fuerza.
+ * `pondered` - La búsqueda hecha durante el turno del rival, si jugó la jugada esperada.
+ # Returns
+ El movimiento, un texto que indica de dónde salió y la respuesta que espera del rival, o `None`
+ si no hay movimientos legales o el motor externo falló.
+*/
+fn computer_move(
+    computer: &mut ComputerPlayer,
+    engine: &mut Engine,
+    board: &Chessboard,
+    random: &mut Random,
+    pondered: Option<SearchResult>,
+) -> Option<(Move, String, Option<Move>)> {
+    match computer {
+        ComputerPlayer::AlphaBeta(strength) => {
+            let was_pondered = pondered.is_some();
+            let result = pondered.unwrap_or_else(|| choose_move(engine, board, strength, random));
+
+            let source = if result.from_book {
+                " (libro)".to_string()
+            } else if let Some(probe) = result.tablebase {
+                format!(
+                    " (tablas de finales: {}, DTZ {})",
+                    probe.wdl.description(),
+                    probe.dtz
+                )
+            } else if was_pondered {
+                " (pensada durante tu turno)".to_string()
+            } else {
+                String::new()
+            };
+
+            let expected_reply = result.principal_variation.get(1).copied();
+            result
+                .best_move
+                .map(|best_move| (best_move, source, expected_reply))
+        }
+        ComputerPlayer::Mcts(mcts) => {
+            let result = mcts.search(board);
+            let source = format!(
+                " (MCTS: {} de {} simulaciones en {} ms, {:.0}% de victoria)",
+                result.visits,
+                result.iterations,
+                result.elapsed.as_millis(),
+                result.win_rate * 100.0
+            );
+
+            result.best_move.map(|best_move| (best_move, source, None))
+        }
+        ComputerPlayer::External(external, limits) => {
+            let result = match external.search(board, *limits, 1) {
+                Ok(result) => result,
+                Err(error) => {
+                    println!("{}", error);
+                    return None;
+                }
+            };
+
+            let source = format!(
+                " ({}: profundidad {}, {})",
+                external.name,
+                result.depth,
+                result
+                    .root_moves
+                    .first()
+                    .map_or("sin evaluación".to_string(), |root_move| describe_score(
+                        root_move.score
+                    ))
+            );
+            let expected_reply = result
+                .root_moves
+                .first()
+                .and_then(|root_move| root_move.principal_variation.get(1).copied());
+
+            result
+                .best_move
+                .map(|best_move| (best_move, source, expected_reply))
+        }
+    }
+}
+
+// Lo que hace el jugador en su turno: mover una pieza o escribir un comando
+enum HumanTurn {
+    Move([usize; 2], [usize; 2]),
+    Command(String),
+}
+
+/**
+ Pide el movimiento al jugador. Si hay una jugada esperada, mientras tanto el motor busca en otro
+ hilo su respuesta a esa jugada; si el jugador hace otra cosa, la búsqueda se cancela.
+ # Arguments
+ * `engine` - El motor alfa-beta.
+ * `random` - El generador de números aleatorios para limitar la fuerza.
+ * `ponder` - La jugada esperada, el tablero después de ella y la fuerza de la computadora.
+ # Returns
+ Lo que hizo el jugador y, si hizo la jugada esperada, la búsqueda de la respuesta.
+*/
+fn read_human_turn(
+    engine: &mut Engine,
+    random: &mut Random,
+    ponder: Option<(Move, Chessboard, Strength)>,
+) -> (HumanTurn, Option<SearchResult>) {
+    let stop = engine.stop_handle();
+
+    thread::scope(|scope| {
+        let pondering = ponder.as_ref().map(|(_, expected_board, strength)| {
+            scope.spawn(move || choose_move(engine, expected_board, strength, random))
+        });
+
+        let turn = match get_input(
+            "Ingrese la posición de la pieza que desea mover (a h), o \"pista\" para recibir una sugerencia:",
+            &HINT_COMMANDS,
+        ) {
+            PlayerInput::Coordinates(from) => HumanTurn::Move(
+                from,
+                get_coordinates("Ingrese la posición hacia donde desea mover (a h):"),
+            ),
+            PlayerInput::Command(command) => HumanTurn::Command(command),
+        };
+
+        // Las coronaciones se preguntan después, así que no cuentan como acierto
+        let hit = match (&turn, &ponder) {
+            (HumanTurn::Move(from, to), Some((expected, _, _))) => {
+                expected.from == *from && expected.to == *to && expected.promotion.is_none()
+            }
+            _ => false,
+        };
+
+        let result = pondering.map(|search| {
+            if !hit {
+                stop.store(true, Ordering::Relaxed);
+            }
+            search.join().unwrap()
+        });
+        stop.store(false, Ordering::Relaxed);
+
+        (turn, result.filter(|_| hit))
+    })
+}
+
+fn play(mut players: [Option<ComputerPlayer>; 2], mut engine: Engine, ponder: bool) {
+    let mut board = Chessboard::new();
+    let mut random = Random::from_time();
+    let mut expected_reply: Option<Move> = None;
+    let mut pondered: Option<SearchResult> = None;
+
+    board.print_board("Bienvenido al juego de ajedrez".to_string());
+
+    loop {
+        let turn = match board.player_turn {
+            ChessPieceColor::White => 0,
+            ChessPieceColor::Black => 1,
+        };
+
+        // Solo se piensa en el turno del rival si la computadora que responde usa alfa-beta
+        let ponder_strength = match &players[1 - turn] {
+            Some(ComputerPlayer::AlphaBeta(strength)) if ponder => Some(*strength),
+            _ => None,
+        };
+
+        let message = match &mut players[turn] {
+            // Turno de la computadora
+            Some(computer) => {
+                match computer_move(computer, &mut engine, &board, &mut random, pondered.take()) {
+                    Some((best_move, source, reply)) => {
+                        let message = board.move_piece_with_promotion(
+                            best_move.from,
+                            best_move.to,
+                            best_move.promotion,
+                        );
+                        expected_reply = reply;
+
+                        board.print_board(format!(
+                            "La computadora jugó {} {}{}: {}",
+                            square_name(best_move.from),
+                            square_name(best_move.to),
+                            source,
+                            message.get_message()
+                        ));
+                        message
+                    }
+                    None => {
+                        board.print_board("La computadora no tiene movimientos".to_string());
+                        break;
+                    }
+                }
+            }
+            None => {
+                let ponder = expected_reply
+                    .zip(ponder_strength)
+                    .and_then(|(reply, strength)| {
+                        generate_moves(&board, false)
+                            .into_iter()
+                            .find(|(legal_move, _)| *legal_move == reply)
+                            .map(|(_, expected_board)| (reply, expected_board, strength))
+                    });
+
+                let (from, to) = match read_human_turn(&mut engine, &mut random, ponder) {
+                    (HumanTurn::Move(from, to), result) => {
+                        pondered = result;
+                        (from, to)
+                    }
+                    // La pista no cambia el turno, volvemos a pedir el movimiento
+                    (HumanTurn::Command(command), _) => {
+                        let message = if HINT_COMMANDS.contains(&command.as_str()) {
+                            match hint(&mut engine, &board) {
+                                Some(hint) => hint.describe(),
+                                None => "No hay movimientos legales".to_string(),
+                            }
+                        } else {
+                            format!("Comando desconocido: {}", command)
+                        };
+                        board.print_board(message);
+                        continue;
+                    }
+                };
+
+                let player_turn = board.player_turn;
+                let message = board.move_piece(from, to);
+
+                // Si el movimiento no era válido el jugador vuelve a mover y lo pensado no sirve
+                if board.player_turn == player_turn {
+                    pondered = None;
+                }
+
+                board.print_board(message.get_message());
+                message
+            }
+        };
+
+        // Validamoss si es jaque mate
+        if message == Message::CheckMate {
+            println!("Juego terminado");
+            break;
+        }
+    }
+}
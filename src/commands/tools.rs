@@ -0,0 +1,221 @@
+use crate::IA::book_builder::{build_book, BookBuilderOptions};
+use crate::IA::evaluation::weights as evaluation_weights;
+use crate::IA::match_runner::{
+    default_openings, load_openings, run_match, EngineSettings, MatchOptions, MatchStats, Sprt,
+    SprtDecision,
+};
+use crate::IA::pgn::{read_pgn_file, GameResult};
+use crate::IA::tuning::{tune, TuningOptions};
+
+use super::option_value;
+
+// Construye un libro Polyglot con las partidas de un PGN y las opciones de la línea de comandos
+pub fn create_book(pgn_path: &str, book_path: &str, args: &[String]) {
+    let defaults = BookBuilderOptions::default();
+    let number = |option: &str, default: u32| {
+        option_value(args, option)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+
+    let options = BookBuilderOptions {
+        max_ply: number("--max-ply", defaults.max_ply as u32) as usize,
+        min_games: number("--min-partidas", defaults.min_games),
+        win_weight: number("--victoria", defaults.win_weight),
+        draw_weight: number("--tablas", defaults.draw_weight),
+        loss_weight: number("--derrota", defaults.loss_weight),
+    };
+
+    let games = match read_pgn_file(pgn_path) {
+        Ok(games) => games,
+        Err(error) => {
+            println!("No se pudo leer {}: {}", pgn_path, error);
+            return;
+        }
+    };
+
+    match build_book(&games, book_path, options) {
+        Ok(summary) => println!(
+            "Libro {} creado: {} entradas de {} partidas ({} omitidas, {} cortadas por movimientos no admitidos)",
+            book_path, summary.entries, summary.games, summary.skipped_games, summary.truncated_games
+        ),
+        Err(error) => println!("No se pudo escribir {}: {}", book_path, error),
+    }
+}
+
+// Enfrenta dos configuraciones del motor e informa cada partida, el Elo y la decisión del SPRT
+pub fn run_engine_match(first: &str, second: &str, args: &[String]) {
+    let (first, second) = match (EngineSettings::parse(first), EngineSettings::parse(second)) {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(error), _) | (_, Err(error)) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let openings = match option_value(args, "--aperturas") {
+        Some(path) => match load_openings(path) {
+            Ok(openings) => openings,
+            Err(error) => {
+                println!("No se pudieron leer las aperturas de {}: {}", path, error);
+                return;
+            }
+        },
+        None => default_openings(),
+    };
+
+    let number = |option: &str, default: f64| {
+        option_value(args, option)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+
+    let defaults = Sprt::default();
+    let sprt = Sprt {
+        elo0: number("--elo0", defaults.elo0),
+        elo1: number("--elo1", defaults.elo1),
+        alpha: number("--alfa", defaults.alpha),
+        beta: number("--beta", defaults.beta),
+    };
+
+    let parallelism = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let options = MatchOptions {
+        games: number("--partidas", 100.0) as usize,
+        concurrency: number("--paralelo", parallelism as f64) as usize,
+        max_plies: number("--max-jugadas", 300.0) as usize,
+        openings,
+        sprt: (!args.iter().any(|arg| arg == "--sin-sprt")).then_some(sprt),
+    };
+
+    println!(
+        "{} contra {}: hasta {} partidas, {} a la vez, {} aperturas",
+        first.name,
+        second.name,
+        options.games,
+        options.concurrency,
+        options.openings.len()
+    );
+    if let Some(sprt) = &options.sprt {
+        let (lower, upper) = sprt.bounds();
+        println!(
+            "SPRT: H0 Elo {} contra H1 Elo {}, alfa {}, beta {}, límites ({:.2}, {:.2})",
+            sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta, lower, upper
+        );
+    }
+
+    let describe_elo = |stats: &MatchStats| match stats.elo() {
+        Some((elo, margin)) => format!("Elo {:+.1} ± {:.1}", elo, margin),
+        None => "Elo sin calcular".to_string(),
+    };
+
+    let summary = run_match(&first, &second, &options, |report, stats| {
+        let (white, black) = if report.first_is_white {
+            (&first.name, &second.name)
+        } else {
+            (&second.name, &first.name)
+        };
+        let result = match report.result {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            _ => "1/2-1/2",
+        };
+        let llr = match &options.sprt {
+            Some(sprt) => format!(", LLR {:.2}", stats.llr(sprt)),
+            None => String::new(),
+        };
+
+        println!(
+            "Partida {} (apertura {}): {} - {} {} por {} en {} jugadas | +{} ={} -{} | {}{}",
+            report.number,
+            report.opening + 1,
+            white,
+            black,
+            result,
+            report.reason,
+            report.plies.div_ceil(2),
+            stats.wins,
+            stats.draws,
+            stats.losses,
+            describe_elo(stats),
+            llr
+        );
+    });
+
+    let stats = summary.stats;
+    println!(
+        "Resultado de {} contra {}: {} victorias, {} tablas, {} derrotas ({:.1}%), {}",
+        first.name,
+        second.name,
+        stats.wins,
+        stats.draws,
+        stats.losses,
+        stats.score() * 100.0,
+        describe_elo(&stats)
+    );
+
+    match summary.decision {
+        Some(SprtDecision::AcceptH1) => {
+            println!("SPRT: se acepta H1, {} es más fuerte", first.name)
+        }
+        Some(SprtDecision::AcceptH0) => {
+            println!("SPRT: se acepta H0, {} no es más fuerte", first.name)
+        }
+        None if options.sprt.is_some() => println!("SPRT: sin decisión"),
+        None => {}
+    }
+}
+
+// Ajusta los pesos de la evaluación con las partidas de un PGN y los guarda en un archivo
+pub fn tune_weights(pgn_path: &str, weights_path: &str, args: &[String]) {
+    let defaults = TuningOptions::default();
+    let number = |option: &str, default: u32| {
+        option_value(args, option)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+
+    let options = TuningOptions {
+        skip_plies: number("--saltar", defaults.skip_plies as u32) as usize,
+        iterations: number("--iteraciones", defaults.iterations),
+        step: number("--paso", defaults.step as u32) as i32,
+    };
+
+    let games = match read_pgn_file(pgn_path) {
+        Ok(games) => games,
+        Err(error) => {
+            println!("No se pudo leer {}: {}", pgn_path, error);
+            return;
+        }
+    };
+
+    // Se parte de los pesos cargados con --pesos, o de los de por defecto
+    let (weights, summary) = tune(
+        &games,
+        evaluation_weights(),
+        &options,
+        |iteration, error| {
+            println!("Iteración {}: error {:.6}", iteration, error);
+        },
+    );
+
+    println!(
+        "{} posiciones de {} partidas ({} omitidas), escala {:.3}, error {:.6} -> {:.6} en {} iteraciones",
+        summary.positions,
+        summary.games,
+        summary.skipped_games,
+        summary.scale,
+        summary.initial_error,
+        summary.final_error,
+        summary.iterations
+    );
+
+    if summary.positions == 0 {
+        println!("No hay posiciones para ajustar");
+        return;
+    }
+
+    match weights.write(weights_path) {
+        Ok(()) => println!("Pesos guardados en {}", weights_path),
+        Err(error) => println!("No se pudo escribir {}: {}", weights_path, error),
+    }
+}
@@ -1,27 +1,190 @@
-use chessboard::chessboard::Chessboard;
-use chessboard::utilities::get_coordinates;
-use pieces::piece_type::Message;
+use commands::analysis::{run_analysis, run_mate};
+use commands::play::{play_against_computer, play_two_players};
+use commands::tools::{create_book, run_engine_match, tune_weights};
+use commands::{option_value, parse_depth, parse_threads};
+use network::correspondence::run_correspondence;
+use network::http::{serve_http, DEFAULT_HTTP_PORT};
+use network::ics::{serve_ics, DEFAULT_ICS_DIRECTORY, DEFAULT_ICS_PORT};
+use network::lan::{host, join, parse_lan_options, DEFAULT_PORT};
+use network::rooms::{run_client, serve_rooms, DEFAULT_ROOMS_PORT};
+use network::store::{show_history, DEFAULT_STORE_DIRECTORY};
+use protocols::uci::run_uci;
+use protocols::xboard::run_xboard;
+use IA::bench::run_bench;
+use IA::evaluation::load_weights;
+use IA::search::SearchConfig;
 
 mod pieces;
 mod chessboard;
+mod commands;
+#[allow(non_snake_case)]
+mod IA;
+mod network;
+mod protocols;
 
 fn main() {
-    let mut board = Chessboard::new();
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    board.print_board("Bienvenido al juego de ajedrez".to_string());
+    // Pesos de evaluación ajustados con ajustar-pesos, para cualquier modo: --pesos pesos.txt
+    if let Some(path) = option_value(&args, "--pesos") {
+        if let Err(error) = load_weights(path) {
+            println!("No se pudieron cargar los pesos de {}: {}", path, error);
+            return;
+        }
+    }
+
+    match args.first().map(|arg| arg.as_str()) {
+        // Mide los nodos y la eficiencia del ordenamiento y de la poda, por ejemplo: bench 6 --hilos 4
+        Some("bench") => {
+            let depth = parse_depth(args.get(1));
+            let mut config = SearchConfig::default();
+
+            // Cada heurística se puede desactivar con --sin-<nombre>
+            for flag in args.iter().filter_map(|arg| arg.strip_prefix("--sin-")) {
+                if !config.disable(flag) {
+                    println!("Heurística desconocida: {}", flag);
+                    return;
+                }
+            }
+
+            run_bench(depth, config, parse_threads(&args));
+        }
+        // Partida contra la computadora, por ejemplo: ia negro 4 --libro aperturas.bin --syzygy tablas/
+        // La fuerza se puede limitar con --nivel principiante|novato|intermedio|avanzado|experto|maestro
+        // o con un Elo objetivo, por ejemplo --elo 1200
+        // El motor se elige con --motor alfabeta|mcts|externo, o por color con --motor-blanco y --motor-negro
+        // El motor externo es cualquier programa UCI: --motor externo --programa stockfish [--tiempo ms]
+        // Con --ponder la computadora piensa su respuesta mientras el jugador decide su movimiento
+        Some("ia") => play_against_computer(&args),
+        // Analiza una posición mostrando las mejores variantes, por ejemplo:
+        // analizar "8/8/8/8/8/8/4K3/4k2R w - - 0 1" 6 --lineas 3 --syzygy tablas/
+        // Con --programa stockfish el análisis lo hace ese motor UCI externo
+        Some("analizar") => run_analysis(&args),
+        // Comprueba un problema de mate en N, por ejemplo: mate "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1" 1
+        // Con --ayudado se resuelve un mate ayudado y con --inverso un mate inverso
+        Some("mate") => run_mate(&args),
+        // Construye un libro Polyglot, por ejemplo: crear-libro partidas.pgn libro.bin --max-ply 16
+        Some("crear-libro") => {
+            let (Some(pgn_path), Some(book_path)) = (args.get(1), args.get(2)) else {
+                println!("Uso: crear-libro <partidas.pgn> <libro.bin> [--max-ply N] [--min-partidas N] [--victoria N] [--tablas N] [--derrota N]");
+                return;
+            };
+
+            create_book(pgn_path, book_path, &args);
+        }
+        // Enfrenta dos configuraciones del motor, por ejemplo:
+        // match "nombre=nuevo prof=5" "nombre=base prof=5 sin=lmr" --partidas 200 --paralelo 4
+        Some("match") => {
+            let (Some(first), Some(second)) = (args.get(1), args.get(2)) else {
+                println!("Uso: match \"<motor A>\" \"<motor B>\" [--partidas N] [--paralelo N] [--aperturas archivo] [--max-jugadas N] [--elo0 N] [--elo1 N] [--alfa N] [--beta N] [--sin-sprt]");
+                return;
+            };
+
+            run_engine_match(first, second, &args);
+        }
+        // Protocolo UCI para usar el motor desde una interfaz gráfica o un administrador de torneos
+        Some("uci") => run_uci(),
+        // Protocolo xboard (CECP v2) para las interfaces que no hablan UCI
+        Some("xboard") => run_xboard(),
+        // Partida entre dos personas por la red local, por ejemplo:
+        // host 7878 --color negro --minutos 5 --incremento 3, y en la otra computadora join 192.168.0.10
+        Some("host") => {
+            let port = match args.get(1).filter(|arg| !arg.starts_with("--")) {
+                Some(port) => match port.parse() {
+                    Ok(port) => port,
+                    Err(_) => {
+                        println!("Puerto inválido: {}", port);
+                        return;
+                    }
+                },
+                None => DEFAULT_PORT,
+            };
 
-    loop {
-        let from = get_coordinates("Ingrese la posición de la pieza que desea mover (a h):");
-        let to = get_coordinates("Ingrese la posición hacia donde desea mover (a h):");
+            let Some(options) = parse_lan_options(&args) else {
+                return;
+            };
 
-        let message = board.move_piece(from, to);
+            host(port, options);
+        }
+        // API HTTP local para manejar partidas desde otros programas, por ejemplo: api 8080
+        Some("api") => match args.get(1).map(|port| port.parse()) {
+            Some(Ok(port)) => serve_http(port),
+            Some(Err(_)) => println!("Puerto inválido: {}", args[1]),
+            None => serve_http(DEFAULT_HTTP_PORT),
+        },
+        Some("join") => {
+            let Some(address) = args.get(1) else {
+                println!("Uso: join <dirección>[:puerto]");
+                return;
+            };
+
+            join(address);
+        }
+        // Servidor WebSocket de salas con jugadores y espectadores, por ejemplo:
+        // salas 9090 --datos datos-salas
+        Some("salas") => {
+            let directory =
+                option_value(&args, "--datos").map_or(DEFAULT_STORE_DIRECTORY, String::as_str);
+            let port = args.get(1).filter(|arg| !arg.starts_with("--"));
+
+            match port.map(|port| port.parse()) {
+                Some(Ok(port)) => serve_rooms(port, directory),
+                Some(Err(_)) => println!("Puerto inválido: {}", args[1]),
+                None => serve_rooms(DEFAULT_ROOMS_PORT, directory),
+            }
+        }
+        // Servidor por telnet al estilo de FICS, para nc, telnet o clientes ICS, por ejemplo:
+        // ics 5000 --datos datos-ics
+        Some("ics") => {
+            let directory =
+                option_value(&args, "--datos").map_or(DEFAULT_ICS_DIRECTORY, String::as_str);
+            let port = args.get(1).filter(|arg| !arg.starts_with("--"));
 
-        board.print_board(message.get_message());
+            match port.map(|port| port.parse()) {
+                Some(Ok(port)) => serve_ics(port, directory),
+                Some(Err(_)) => println!("Puerto inválido: {}", args[1]),
+                None => serve_ics(DEFAULT_ICS_PORT, directory),
+            }
+        }
+        // Partidas terminadas del servidor de salas, por ejemplo: historial --jugador ana --fecha 2026-10-19
+        // Las del servidor ICS están en su propia carpeta: historial --datos datos-ics
+        Some("historial") => show_history(&args),
+        // Cliente de salas sin tablero, por ejemplo: sala 127.0.0.1 amigos --token 3f2a...
+        // Sin nombre de sala se queda en el lobby para buscar rival
+        Some("sala") => {
+            let Some(address) = args.get(1) else {
+                println!("Uso: sala <dirección>[:puerto] [sala] [--espectador] [--token clave] [--jugador nombre]");
+                return;
+            };
 
-        // Validamoss si es jaque mate
-        if message == Message::CheckMate {
-            println!("Juego terminado");
-            break;
+            run_client(
+                address,
+                args.get(2)
+                    .filter(|room| !room.starts_with("--"))
+                    .map(String::as_str),
+                args.iter().any(|arg| arg == "--espectador"),
+                option_value(&args, "--token").map(String::as_str),
+                option_value(&args, "--jugador").map(String::as_str),
+            );
+        }
+        // Partida por correspondencia con archivos de jugada firmados, por ejemplo:
+        // correspondencia nueva partida.txt blanco, y después jugar, importar o ver
+        Some("correspondencia") => {
+            if let Err(error) = run_correspondence(&args) {
+                println!("{}", error);
+            }
         }
+        // Ajusta los pesos de la evaluación con partidas, por ejemplo:
+        // ajustar-pesos partidas.pgn pesos.txt --iteraciones 50 --saltar 8
+        Some("ajustar-pesos") => {
+            let (Some(pgn_path), Some(weights_path)) = (args.get(1), args.get(2)) else {
+                println!("Uso: ajustar-pesos <partidas.pgn> <pesos.txt> [--iteraciones N] [--saltar N] [--paso N] [--pesos inicial.txt]");
+                return;
+            };
+
+            tune_weights(pgn_path, weights_path, &args);
+        }
+        _ => play_two_players(),
     }
-}
\ No newline at end of file
+}
+
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::ChessPieceColor;
+use crate::IA::random::Random;
+use crate::IA::san::parse_san;
+use crate::IA::zobrist;
+
+use super::game::{color_from_name, color_name, opposite, parse_move, ServerGame};
+use super::websocket::sha1;
+
+// Tamaño de bloque de SHA-1, que usa HMAC para la clave
+const SHA1_BLOCK_SIZE: usize = 64;
+
+/**
+ Partida por correspondencia tal como la guarda cada jugador en su máquina: el identificador,
+ la clave compartida con la que se firman las jugadas, el color propio y las jugadas en
+ coordenadas desde la posición inicial.
+*/
+pub struct CorrespondenceGame {
+    id: String,
+    secret: String,
+    color: ChessPieceColor,
+    game: ServerGame,
+}
+
+// Jugada que se manda al rival: la posición antes de jugarla, su número y la firma de todo eso
+struct MoveFile {
+    game: String,
+    number: usize,
+    position: String,
+    chess_move: String,
+    signature: String,
+}
+
+impl CorrespondenceGame {
+    // Empieza una partida nueva con un identificador y una clave al azar
+    pub fn create(color: ChessPieceColor) -> CorrespondenceGame {
+        let mut random = Random::from_time();
+        CorrespondenceGame {
+            id: format!("{:016x}", random.next_u64()),
+            secret: format!("{:016x}{:016x}", random.next_u64(), random.next_u64()),
+            color,
+            game: ServerGame::new(Chessboard::new()),
+        }
+    }
+
+    /**
+     Arma la invitación para el rival, con el color que le toca. Lleva la clave, así que hay que
+     mandarla por un medio en el que se confíe: con ella se pueden firmar jugadas.
+     # Returns
+     El nombre sugerido del archivo y su contenido.
+    */
+    pub fn invitation(&self) -> (String, String) {
+        let text = format!(
+            "game {}\nsecret {}\ncolor {}\n",
+            self.id,
+            self.secret,
+            color_name(opposite(self.color))
+        );
+        (format!("invitacion-{}.txt", self.id), text)
+    }
+
+    // Se une a la partida de una invitación, con el color que esta le asigna
+    pub fn join(invitation: &str) -> Result<CorrespondenceGame, String> {
+        let fields = fields(invitation);
+        let color = fields
+            .get("color")
+            .and_then(|color| color_from_name(color))
+            .ok_or("La invitación no dice el color")?;
+
+        Ok(CorrespondenceGame {
+            id: field(&fields, "game")?,
+            secret: field(&fields, "secret")?,
+            color,
+            game: ServerGame::new(Chessboard::new()),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<CorrespondenceGame, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("No se pudo leer {}: {}", path.display(), error))?;
+        let fields = fields(&text);
+
+        let mut game = CorrespondenceGame::join(&text)?;
+        for chess_move in fields
+            .get("moves")
+            .map_or("", |moves| moves)
+            .split_whitespace()
+        {
+            game.game.play(chess_move).map_err(|error| {
+                format!("Jugada inválida en {}: {}", path.display(), error.message())
+            })?;
+        }
+        Ok(game)
+    }
+
+    // Escribe primero un archivo temporal para no perder la partida si algo falla
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = format!(
+            "game {}\nsecret {}\ncolor {}\nmoves {}\n",
+            self.id,
+            self.secret,
+            color_name(self.color),
+            self.game.moves.join(" ")
+        );
+
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, text)
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(|error| format!("No se pudo guardar {}: {}", path.display(), error))
+    }
+
+    /**
+     Juega una jugada propia, en coordenadas o en notación algebraica.
+     # Returns
+     El nombre sugerido y el contenido del archivo de jugada firmado para mandarle al rival,
+     o por qué no se pudo jugar.
+    */
+    pub fn play(&mut self, text: &str) -> Result<(String, String), String> {
+        if self.game.end.is_some() {
+            return Err("La partida ya terminó".to_string());
+        }
+        if self.game.board.player_turn != self.color {
+            return Err("Es el turno del rival: hay que esperar su jugada".to_string());
+        }
+
+        let coordinates = match parse_move(text) {
+            Some(chess_move) => chess_move.to_coordinates(),
+            None => parse_san(&self.game.board, text)
+                .map(|(chess_move, _)| chess_move.to_coordinates())
+                .ok_or_else(|| format!("No se entiende la jugada \"{}\"", text))?,
+        };
+
+        let position = self.position();
+        let chess_move = self
+            .game
+            .play(&coordinates)
+            .map_err(|error| error.message())?;
+
+        let mut file = MoveFile {
+            game: self.id.clone(),
+            number: self.game.moves.len(),
+            position,
+            chess_move: chess_move.to_coordinates(),
+            signature: String::new(),
+        };
+        file.signature = self.sign(&file);
+
+        Ok((format!("{}-{}.txt", self.id, file.number), file.to_text()))
+    }
+
+    /**
+     Importa la jugada del rival, comprobando que sea de esta partida, que la firma coincida,
+     que sea la que sigue y que salga de la posición actual.
+     # Returns
+     La jugada en coordenadas, o por qué se rechazó el archivo.
+    */
+    pub fn import(&mut self, text: &str) -> Result<String, String> {
+        let file = MoveFile::from_text(text)?;
+
+        if file.game != self.id {
+            return Err("El archivo es de otra partida".to_string());
+        }
+        if file.signature != self.sign(&file) {
+            return Err("La firma no coincide: el archivo fue modificado".to_string());
+        }
+
+        let expected = self.game.moves.len() + 1;
+        if file.number < expected {
+            return Err(format!("La jugada {} ya se importó", file.number));
+        }
+        if file.number > expected {
+            return Err(format!(
+                "Es la jugada {} y falta la {}: hay que importarlas en orden",
+                file.number, expected
+            ));
+        }
+        if self.game.board.player_turn == self.color {
+            return Err("Es el turno propio: ese archivo no puede ser del rival".to_string());
+        }
+        if file.position != self.position() {
+            return Err("La posición del archivo no es la de la partida".to_string());
+        }
+
+        self.game
+            .play(&file.chess_move)
+            .map(|chess_move| chess_move.to_coordinates())
+            .map_err(|error| error.message())
+    }
+
+    // El tablero con el estado de la partida
+    pub fn show(&self) {
+        let status = match self.game.end {
+            Some(end) => end.describe(),
+            None if self.game.board.player_turn == self.color => "Es tu turno".to_string(),
+            None => "Esperando la jugada del rival".to_string(),
+        };
+
+        self.game.board.print_board(format!(
+            "Partida {} con {}, jugada {}. {}",
+            self.id,
+            color_name(self.color),
+            self.game.moves.len(),
+            status
+        ));
+    }
+
+    fn position(&self) -> String {
+        format!("{:016x}", zobrist::hash(&self.game.board))
+    }
+
+    fn sign(&self, file: &MoveFile) -> String {
+        hex(&hmac_sha1(
+            self.secret.as_bytes(),
+            file.signed_text().as_bytes(),
+        ))
+    }
+}
+
+impl MoveFile {
+    // Lo que cubre la firma: todas las líneas menos la de la firma
+    fn signed_text(&self) -> String {
+        format!(
+            "game {}\nnumber {}\nposition {}\nmove {}\n",
+            self.game, self.number, self.position, self.chess_move
+        )
+    }
+
+    fn to_text(&self) -> String {
+        format!("{}signature {}\n", self.signed_text(), self.signature)
+    }
+
+    fn from_text(text: &str) -> Result<MoveFile, String> {
+        let fields = fields(text);
+
+        Ok(MoveFile {
+            game: field(&fields, "game")?,
+            number: field(&fields, "number")?
+                .parse()
+                .map_err(|_| "El número de jugada no es válido".to_string())?,
+            position: field(&fields, "position")?,
+            chess_move: field(&fields, "move")?,
+            signature: field(&fields, "signature")?,
+        })
+    }
+}
+
+/**
+ Atiende los comandos de correspondencia. Cada jugador guarda la partida en su archivo y se
+ intercambian la invitación y los archivos de jugada, por ejemplo por correo:
+ * `nueva <partida> [blanco|negro]` - Crea la partida y la invitación para el rival.
+ * `unirse <invitación> <partida>` - Crea la partida del rival a partir de la invitación.
+ * `jugar <partida> <jugada>` - Juega y escribe el archivo de jugada firmado.
+ * `importar <partida> <archivo>` - Aplica la jugada del rival si el archivo es válido.
+ * `ver <partida>` - Muestra el tablero.
+*/
+pub fn run_correspondence(args: &[String]) -> Result<(), String> {
+    let usage = "Uso: correspondencia nueva <partida> [blanco|negro] | unirse <invitación> <partida> | jugar <partida> <jugada> | importar <partida> <archivo> | ver <partida>";
+    let argument = |index: usize| {
+        args.get(index)
+            .map(|arg| Path::new(arg.as_str()))
+            .ok_or(usage)
+    };
+    let write = |name: &str, text: &str| {
+        fs::write(name, text).map_err(|error| format!("No se pudo escribir {}: {}", name, error))
+    };
+
+    match args.get(1).map(String::as_str) {
+        Some("nueva") => {
+            let path = argument(2)?;
+            let color = match args.get(3) {
+                Some(name) => color_from_name(name).ok_or(usage)?,
+                None => ChessPieceColor::White,
+            };
+
+            let game = CorrespondenceGame::create(color);
+            game.save(path)?;
+            let (name, invitation) = game.invitation();
+            write(&name, &invitation)?;
+            println!(
+                "Partida creada en {}; mandale {} al rival",
+                path.display(),
+                name
+            );
+        }
+        Some("unirse") => {
+            let invitation = argument(2)?;
+            let path = argument(3)?;
+
+            let text = fs::read_to_string(invitation)
+                .map_err(|error| format!("No se pudo leer {}: {}", invitation.display(), error))?;
+            CorrespondenceGame::join(&text)?.save(path)?;
+            println!("Partida creada en {}", path.display());
+        }
+        Some("jugar") => {
+            let path = argument(2)?;
+            let text = args.get(3).ok_or(usage)?;
+
+            let mut game = CorrespondenceGame::load(path)?;
+            let (name, file) = game.play(text)?;
+            game.save(path)?;
+            write(&name, &file)?;
+            println!("Jugada guardada en {}; mandásela al rival", name);
+        }
+        Some("importar") => {
+            let path = argument(2)?;
+            let file = argument(3)?;
+
+            let mut game = CorrespondenceGame::load(path)?;
+            let text = fs::read_to_string(file)
+                .map_err(|error| format!("No se pudo leer {}: {}", file.display(), error))?;
+            let chess_move = game.import(&text)?;
+            game.save(path)?;
+            println!("El rival jugó {}", chess_move);
+        }
+        Some("ver") => CorrespondenceGame::load(argument(2)?)?.show(),
+        _ => return Err(usage.to_string()),
+    }
+
+    Ok(())
+}
+
+// Una línea por campo, con el nombre adelante
+fn fields(text: &str) -> HashMap<&str, &str> {
+    text.lines()
+        .map(|line| line.trim().split_once(' ').unwrap_or((line.trim(), "")))
+        .collect()
+}
+
+fn field(fields: &HashMap<&str, &str>, key: &str) -> Result<String, String> {
+    fields
+        .get(key)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .ok_or_else(|| format!("Falta el campo {}", key))
+}
+
+// HMAC (RFC 2104) con SHA-1: una firma que solo puede hacer quien conoce la clave
+fn hmac_sha1(key: &[u8], message: &[u8]) -> [u8; 20] {
+    let mut block = [0u8; SHA1_BLOCK_SIZE];
+    if key.len() > SHA1_BLOCK_SIZE {
+        block[..20].copy_from_slice(&sha1(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|byte| byte ^ 0x5C).collect();
+    outer.extend_from_slice(&sha1(&inner));
+    sha1(&outer)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Las dos copias de una partida, blancas y negras, como las tendría cada jugador
+    fn players() -> (CorrespondenceGame, CorrespondenceGame) {
+        let white = CorrespondenceGame::create(ChessPieceColor::White);
+        let (_, invitation) = white.invitation();
+        let black = CorrespondenceGame::join(&invitation).unwrap();
+        (white, black)
+    }
+
+    // Un archivo bien firmado con cualquier jugada, como lo armaría quien conoce la clave
+    fn signed(
+        game: &CorrespondenceGame,
+        number: usize,
+        position: &str,
+        chess_move: &str,
+    ) -> String {
+        let mut file = MoveFile {
+            game: game.id.clone(),
+            number,
+            position: position.to_string(),
+            chess_move: chess_move.to_string(),
+            signature: String::new(),
+        };
+        file.signature = game.sign(&file);
+        file.to_text()
+    }
+
+    #[test]
+    fn imports_the_opponent_moves_in_order() {
+        let (mut white, mut black) = players();
+
+        let (_, first) = white.play("e4").unwrap();
+        assert_eq!(black.import(&first).unwrap(), "e2e4");
+        let (_, second) = black.play("e7e5").unwrap();
+        assert_eq!(white.import(&second).unwrap(), "e7e5");
+        assert_eq!(white.game.moves, black.game.moves);
+    }
+
+    #[test]
+    fn rejects_tampered_files() {
+        let (mut white, mut black) = players();
+        let (_, file) = white.play("e2e4").unwrap();
+
+        let tampered = file.replace("move e2e4", "move e2e3");
+        assert!(black.import(&tampered).unwrap_err().contains("firma"));
+        let renumbered = file.replace("number 1", "number 2");
+        assert!(black.import(&renumbered).unwrap_err().contains("firma"));
+        assert!(black.game.moves.is_empty());
+
+        let (_, mut stranger) = players();
+        assert!(stranger.import(&file).unwrap_err().contains("otra partida"));
+    }
+
+    #[test]
+    fn rejects_replayed_and_out_of_order_files() {
+        let (mut white, mut black) = players();
+        let (_, first) = white.play("e4").unwrap();
+        black.import(&first).unwrap();
+        assert!(black.import(&first).unwrap_err().contains("ya se importó"));
+
+        let (_, second) = black.play("e5").unwrap();
+        white.import(&second).unwrap();
+        let (_, third) = white.play("Nf3").unwrap();
+        let fifth = signed(&black, 5, &black.position(), "f1c4");
+        assert!(black.import(&fifth).unwrap_err().contains("en orden"));
+        assert_eq!(black.import(&third).unwrap(), "g1f3");
+
+        // Una jugada propia de las negras no se puede importar como si fuera del rival
+        let own = signed(&black, 4, &black.position(), "b8c6");
+        assert!(black.import(&own).unwrap_err().contains("turno propio"));
+    }
+
+    #[test]
+    fn rejects_signed_illegal_moves() {
+        let (_, mut black) = players();
+
+        // Enroque largo con todas las piezas en su lugar, bien firmado
+        let castle = signed(&black, 1, &black.position(), "e1c1");
+        assert!(black.import(&castle).is_err());
+        let elsewhere = signed(&black, 1, "0123456789abcdef", "e2e4");
+        assert!(black.import(&elsewhere).unwrap_err().contains("posición"));
+        assert!(black.game.moves.is_empty());
+    }
+
+    #[test]
+    fn rejects_saved_games_with_illegal_moves() {
+        let path = std::env::temp_dir().join("ajedrez-correspondencia.txt");
+        let (white, _) = players();
+        white.save(&path).unwrap();
+        assert!(CorrespondenceGame::load(&path).is_ok());
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(&path, text.replace("moves ", "moves e1c1")).unwrap();
+        assert!(CorrespondenceGame::load(&path).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
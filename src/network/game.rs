@@ -0,0 +1,465 @@
+use std::time::{Duration, Instant};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::{is_check, validate_move_with_promotion};
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType, Message};
+use crate::IA::move_generator::{find_move, generate_moves, Move};
+use crate::IA::pgn::GameResult;
+
+use super::json;
+
+// Por qué terminó una partida en red
+#[derive(Clone, Copy, PartialEq)]
+pub enum EndReason {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    Agreement,
+    Time,
+    Disconnection,
+}
+
+impl EndReason {
+    pub const ALL: [EndReason; 6] = [
+        EndReason::Checkmate,
+        EndReason::Stalemate,
+        EndReason::Resignation,
+        EndReason::Agreement,
+        EndReason::Time,
+        EndReason::Disconnection,
+    ];
+
+    // Nombre que viaja por la red
+    pub fn name(self) -> &'static str {
+        match self {
+            EndReason::Checkmate => "mate",
+            EndReason::Stalemate => "ahogado",
+            EndReason::Resignation => "abandono",
+            EndReason::Agreement => "acuerdo",
+            EndReason::Time => "tiempo",
+            EndReason::Disconnection => "desconexion",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EndReason> {
+        EndReason::ALL
+            .into_iter()
+            .find(|reason| reason.name() == name)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            EndReason::Checkmate => "jaque mate",
+            EndReason::Stalemate => "ahogado",
+            EndReason::Resignation => "abandono",
+            EndReason::Agreement => "tablas de común acuerdo",
+            EndReason::Time => "tiempo agotado",
+            EndReason::Disconnection => "desconexión",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct GameEnd {
+    pub result: GameResult,
+    pub reason: EndReason,
+}
+
+impl GameEnd {
+    // Victoria del bando indicado
+    pub fn win(color: ChessPieceColor, reason: EndReason) -> GameEnd {
+        let result = match color {
+            ChessPieceColor::White => GameResult::WhiteWins,
+            ChessPieceColor::Black => GameResult::BlackWins,
+        };
+        GameEnd { result, reason }
+    }
+
+    pub fn draw(reason: EndReason) -> GameEnd {
+        GameEnd {
+            result: GameResult::Draw,
+            reason,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let winner = match self.result {
+            GameResult::WhiteWins => "ganan las blancas",
+            GameResult::BlackWins => "ganan las negras",
+            _ => "tablas",
+        };
+        format!(
+            "{} ({}, {})",
+            self.result.to_token(),
+            winner,
+            self.reason.description()
+        )
+    }
+}
+
+/**
+ Reloj de ajedrez de los dos jugadores con incremento por jugada. Solo corre el tiempo del
+ jugador en turno, desde que se llama a `start`.
+*/
+#[derive(Clone)]
+pub struct Clock {
+    remaining: [Duration; 2],
+    increment: Duration,
+    turn: ChessPieceColor,
+    // Desde cuándo corre el tiempo del jugador en turno; `None` con el reloj detenido
+    running_since: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(base: Duration, increment: Duration) -> Clock {
+        Clock {
+            remaining: [base; 2],
+            increment,
+            turn: ChessPieceColor::White,
+            running_since: None,
+        }
+    }
+
+    // Empieza a correr el tiempo del bando indicado
+    pub fn start(&mut self, turn: ChessPieceColor) {
+        self.turn = turn;
+        self.running_since = Some(Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        let turn = self.turn;
+        self.remaining[color_index(turn)] = self.remaining(turn);
+        self.running_since = None;
+    }
+
+    // Tiempo que le queda al bando indicado en este momento
+    pub fn remaining(&self, color: ChessPieceColor) -> Duration {
+        let remaining = self.remaining[color_index(color)];
+        match self.running_since {
+            Some(since) if color == self.turn => remaining.saturating_sub(since.elapsed()),
+            _ => remaining,
+        }
+    }
+
+    pub fn increment(&self) -> Duration {
+        self.increment
+    }
+
+    // El bando en turno se quedó sin tiempo
+    pub fn flagged(&self) -> Option<ChessPieceColor> {
+        (self.running_since.is_some() && self.remaining(self.turn).is_zero()).then_some(self.turn)
+    }
+
+    /**
+     Detiene el tiempo del bando en turno después de su jugada, le suma el incremento y pone en
+     marcha el del rival.
+     # Returns
+     `false` si al jugador ya se le había acabado el tiempo, y entonces el reloj se detiene.
+    */
+    pub fn press(&mut self) -> bool {
+        let turn = self.turn;
+        self.stop();
+
+        if self.remaining[color_index(turn)].is_zero() {
+            return false;
+        }
+
+        self.remaining[color_index(turn)] += self.increment;
+        self.start(opposite(turn));
+        true
+    }
+
+    // Pone los tiempos que informa el otro extremo, que es quien manda sobre el reloj
+    pub fn set(&mut self, white: Duration, black: Duration, turn: ChessPieceColor) {
+        self.remaining = [white, black];
+        self.start(turn);
+    }
+}
+
+// Muestra un tiempo como minutos y segundos, por ejemplo "4:05"
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn color_index(color: ChessPieceColor) -> usize {
+    match color {
+        ChessPieceColor::White => 0,
+        ChessPieceColor::Black => 1,
+    }
+}
+
+pub fn opposite(color: ChessPieceColor) -> ChessPieceColor {
+    match color {
+        ChessPieceColor::White => ChessPieceColor::Black,
+        ChessPieceColor::Black => ChessPieceColor::White,
+    }
+}
+
+pub fn color_name(color: ChessPieceColor) -> &'static str {
+    match color {
+        ChessPieceColor::White => "blanco",
+        ChessPieceColor::Black => "negro",
+    }
+}
+
+pub fn color_from_name(name: &str) -> Option<ChessPieceColor> {
+    match name {
+        "blanco" => Some(ChessPieceColor::White),
+        "negro" => Some(ChessPieceColor::Black),
+        _ => None,
+    }
+}
+
+/**
+ Lee un movimiento en coordenadas, con o sin espacio entre las casillas, por ejemplo "e2e4",
+ "e2 e4" o "e7e8q" para coronar.
+ # Returns
+ El movimiento, sin comprobar si es legal, o `None` si el texto no tiene ese formato.
+*/
+pub fn parse_move(text: &str) -> Option<Move> {
+    let text: String = text.split_whitespace().collect::<String>().to_lowercase();
+    let bytes = text.as_bytes();
+
+    if bytes.len() != 4 && bytes.len() != 5 {
+        return None;
+    }
+
+    let square = |file: u8, rank: u8| -> Option<[usize; 2]> {
+        if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+            return None;
+        }
+        Some([(b'8' - rank) as usize, (file - b'a') as usize])
+    };
+
+    let promotion = match bytes.get(4) {
+        None => None,
+        Some(b'q') => Some(ChessPieceType::Queen),
+        Some(b'r') => Some(ChessPieceType::Rook),
+        Some(b'b') => Some(ChessPieceType::Bishop),
+        Some(b'n') => Some(ChessPieceType::Knight),
+        Some(_) => return None,
+    };
+
+    Some(Move {
+        from: square(bytes[0], bytes[1])?,
+        to: square(bytes[2], bytes[3])?,
+        promotion,
+    })
+}
+
+/**
+ Valida un movimiento recibido por la red contra los movimientos legales de `generate_moves`, la
+ misma lista que usan el motor y `final_position`. Sin pieza indicada los peones coronan dama,
+ para no preguntarle nada al jugador que valida.
+ # Arguments
+ * `chessboard` - El tablero actual.
+ * `chess_move` - El movimiento.
+ # Returns
+ El movimiento con la coronación que se aplicó y el tablero resultante, o el `Message` que
+ explica por qué no es válido.
+*/
+pub fn play_move(chessboard: &Chessboard, chess_move: Move) -> Result<(Move, Chessboard), Message> {
+    let from_piece = chessboard.board[chess_move.from[0]][chess_move.from[1]];
+    let to_piece = chessboard.board[chess_move.to[0]][chess_move.to[1]];
+
+    let promoted = from_piece.is_some_and(|piece| piece.piece == ChessPieceType::Pawn)
+        && (chess_move.to[0] == 0 || chess_move.to[0] == 7);
+    // Una coronación en un movimiento que no corona no está entre los legales
+    if !promoted && chess_move.promotion.is_some() {
+        return Err(Message::CannotMovePieceToPosition);
+    }
+    let chess_move = Move {
+        promotion: promoted.then(|| chess_move.promotion.unwrap_or(ChessPieceType::Queen)),
+        ..chess_move
+    };
+
+    if let Some(legal) = find_move(chessboard, &chess_move.to_coordinates()) {
+        return Ok(legal);
+    }
+
+    // El validador del tablero explica por qué no es legal; si no encuentra nada es un enroque
+    // que no se puede hacer o un movimiento que deja al rey atacado
+    validate_move_with_promotion(
+        chessboard,
+        &from_piece,
+        &to_piece,
+        chess_move.to,
+        chess_move.promotion,
+    )?;
+    let castling = from_piece.is_some_and(|piece| piece.piece == ChessPieceType::King)
+        && chess_move.from[1].abs_diff(chess_move.to[1]) == 2;
+    Err(if castling {
+        Message::CannotCastle
+    } else {
+        Message::CannotMovePieceToPosition
+    })
+}
+
+// Mate o ahogado del bando en turno
+pub fn final_position(chessboard: &Chessboard) -> Option<GameEnd> {
+    if !generate_moves(chessboard, false).is_empty() {
+        return None;
+    }
+
+    if is_check(chessboard, chessboard.player_turn).is_some() {
+        Some(GameEnd::win(
+            opposite(chessboard.player_turn),
+            EndReason::Checkmate,
+        ))
+    } else {
+        Some(GameEnd::draw(EndReason::Stalemate))
+    }
+}
+
+// Por qué no se pudo jugar un movimiento en una partida de un servidor
+pub enum MoveError {
+    Unreadable(String),
+    GameOver,
+    Illegal(Message),
+}
+
+impl MoveError {
+    // Los errores del tablero usan el código de cada variante de `Message`
+    pub fn code(&self) -> &'static str {
+        match self {
+            MoveError::Unreadable(_) => "unreadable_move",
+            MoveError::GameOver => "game_over",
+            MoveError::Illegal(message) => message.code(),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            MoveError::Unreadable(text) => format!("No se entiende la jugada \"{}\"", text),
+            MoveError::GameOver => "La partida ya terminó".to_string(),
+            MoveError::Illegal(message) => message.get_message(),
+        }
+    }
+}
+
+// Partida que lleva un servidor: el tablero, las jugadas en coordenadas y el final si lo hubo
+pub struct ServerGame {
+    pub board: Chessboard,
+    pub moves: Vec<String>,
+    pub end: Option<GameEnd>,
+}
+
+impl ServerGame {
+    pub fn new(board: Chessboard) -> ServerGame {
+        ServerGame {
+            end: final_position(&board),
+            board,
+            moves: Vec::new(),
+        }
+    }
+
+    /**
+     Juega un movimiento en coordenadas, validado con `play_move`.
+     # Returns
+     El movimiento con la coronación que se aplicó, o por qué no se pudo jugar.
+    */
+    pub fn play(&mut self, text: &str) -> Result<Move, MoveError> {
+        let chess_move = parse_move(text).ok_or_else(|| MoveError::Unreadable(text.to_string()))?;
+
+        if self.end.is_some() {
+            return Err(MoveError::GameOver);
+        }
+
+        let (chess_move, new_board) =
+            play_move(&self.board, chess_move).map_err(MoveError::Illegal)?;
+        self.board = new_board;
+        self.moves.push(chess_move.to_coordinates());
+        self.end = final_position(&self.board);
+
+        Ok(chess_move)
+    }
+
+    // Termina la partida, por ejemplo por abandono; una partida terminada no cambia
+    pub fn finish(&mut self, end: GameEnd) {
+        if self.end.is_none() {
+            self.end = Some(end);
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self.end {
+            Some(end) if end.reason == EndReason::Checkmate => "checkmate",
+            Some(end) if end.reason == EndReason::Stalemate => "stalemate",
+            Some(_) => "finished",
+            None if is_check(&self.board, self.board.player_turn).is_some() => "check",
+            None => "in_progress",
+        }
+    }
+
+    // Los campos JSON del estado: FEN, turno, estado, resultado, movimientos legales y jugadas
+    pub fn json_fields(&self) -> Vec<(&'static str, String)> {
+        let legal_moves: Vec<String> = match self.end {
+            Some(_) => Vec::new(),
+            None => generate_moves(&self.board, false)
+                .into_iter()
+                .map(|(chess_move, _)| chess_move.to_coordinates())
+                .collect(),
+        };
+
+        let turn = match self.board.player_turn {
+            ChessPieceColor::White => "white",
+            ChessPieceColor::Black => "black",
+        };
+        let result = self.end.map_or(GameResult::Unknown, |end| end.result);
+
+        let mut fields = vec![
+            ("fen", json::string(&self.board.to_fen())),
+            ("turn", json::string(turn)),
+            ("status", json::string(self.status())),
+            ("result", json::string(result.to_token())),
+            ("legal_moves", json::string_array(&legal_moves)),
+            ("moves", json::string_array(&self.moves)),
+        ];
+
+        if let Some(end) = self.end {
+            fields.push(("reason", json::string(end.reason.name())));
+        }
+
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Si el movimiento se acepta en la posición
+    fn legal(fen: &str, text: &str) -> bool {
+        let mut game = ServerGame::new(Chessboard::from_fen(fen).ok().unwrap());
+        game.play(text).is_ok()
+    }
+
+    #[test]
+    fn rejects_castling_through_pieces() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(!legal(start, "e1c1"));
+        assert!(!legal(start, "e1g1"));
+    }
+
+    #[test]
+    fn rejects_castling_out_of_or_through_check() {
+        // Con la torre negra en e8 el rey está en jaque; en f8 ataca f1
+        assert!(!legal("4r2k/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+        assert!(!legal("5r1k/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+        assert!(legal("7k/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+    }
+
+    #[test]
+    fn promotes_to_queen_by_default() {
+        let mut game = ServerGame::new(
+            Chessboard::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1")
+                .ok()
+                .unwrap(),
+        );
+        let chess_move = game.play("a7a8").ok().unwrap();
+        assert_eq!(chess_move.to_coordinates(), "a7a8q");
+        assert!(game.play("h8g8").is_err());
+        assert!(game.play("h8h7").is_ok());
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::Message;
+
+use super::game::{MoveError, ServerGame};
+use super::json;
+
+pub const DEFAULT_HTTP_PORT: u16 = 8080;
+
+// Tamaño máximo del cuerpo de una petición
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpRequest {
+    // Los nombres de las cabeceras no distinguen mayúsculas
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/**
+ Lee una petición HTTP/1.1: la línea inicial, las cabeceras y el cuerpo según Content-Length.
+ # Arguments
+ * `reader` - La conexión con el cliente.
+ # Returns
+ La petición, o un mensaje si la conexión se cerró o la petición no es válida.
+*/
+pub fn read_request(reader: &mut impl BufRead) -> Result<HttpRequest, String> {
+    let mut line = String::new();
+    if reader
+        .read_line(&mut line)
+        .map_err(|error| error.to_string())?
+        == 0
+    {
+        return Err("La conexión se cerró".to_string());
+    }
+
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(format!("Línea de petición inválida: {}", line.trim()));
+    };
+
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        // La consulta después de "?" no se usa
+        path: path.split('?').next().unwrap_or(path).to_string(),
+        headers: Vec::new(),
+        body: String::new(),
+    };
+
+    loop {
+        line.clear();
+        reader
+            .read_line(&mut line)
+            .map_err(|error| error.to_string())?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            request
+                .headers
+                .push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length: usize = request
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY_SIZE {
+        return Err(format!("El cuerpo supera los {} bytes", MAX_BODY_SIZE));
+    }
+
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|error| error.to_string())?;
+    request.body = String::from_utf8_lossy(&body).into_owned();
+
+    Ok(request)
+}
+
+// Escribe una respuesta JSON completa y avisa que la conexión se cierra
+pub fn write_response(stream: &mut TcpStream, status: u16, body: &str) {
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        body.len(),
+        body
+    );
+    let _ = stream.flush();
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+// Cuerpo de un error, con un código para los programas y el texto para las personas
+pub fn error_json(code: &str, message: &str) -> String {
+    json::object(&[(
+        "error",
+        json::object(&[
+            ("code", json::string(code)),
+            ("message", json::string(message)),
+        ]),
+    )])
+}
+
+// Los errores de validación del tablero usan el código de cada variante de `Message`
+pub fn message_error_json(message: &Message) -> String {
+    error_json(message.code(), &message.get_message())
+}
+
+// Estado de una partida con su identificador
+fn game_json(id: u64, game: &ServerGame) -> String {
+    let mut fields = vec![("id", id.to_string())];
+    fields.extend(game.json_fields());
+    json::object(&fields)
+}
+
+#[derive(Default)]
+struct GameRegistry {
+    games: HashMap<u64, ServerGame>,
+    next_id: u64,
+}
+
+/**
+ Servidor HTTP local para manejar partidas desde otros programas, con respuestas en JSON:
+ * `GET /games`: los identificadores de las partidas.
+ * `POST /games`: crea una partida, desde la posición inicial o desde {"fen": "..."}.
+ * `GET /games/<id>`: el FEN, el turno, el estado, el resultado y los movimientos legales.
+ * `POST /games/<id>/moves`: juega {"move": "e2e4"} y devuelve el estado nuevo.
+ # Arguments
+ * `port` - El puerto local en el que se atiende.
+*/
+pub fn serve_http(port: u16) {
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!("No se pudo abrir el puerto {}: {}", port, error);
+            return;
+        }
+    };
+
+    println!("API de partidas en http://127.0.0.1:{}/games", port);
+    let registry = Arc::new(Mutex::new(GameRegistry::default()));
+
+    for stream in listener.incoming().flatten() {
+        let registry = Arc::clone(&registry);
+        thread::spawn(move || handle_connection(stream, &registry));
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, registry: &Mutex<GameRegistry>) {
+    let request = match stream.try_clone() {
+        Ok(reader) => read_request(&mut BufReader::new(reader)),
+        Err(error) => Err(error.to_string()),
+    };
+
+    let (status, body) = match request {
+        Ok(request) => route(&request, &mut registry.lock().unwrap()),
+        Err(error) => (400, error_json("bad_request", &error)),
+    };
+
+    write_response(&mut stream, status, &body);
+}
+
+fn route(request: &HttpRequest, registry: &mut GameRegistry) -> (u16, String) {
+    let segments: Vec<&str> = request
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match segments.as_slice() {
+        ["games"] => match request.method.as_str() {
+            "GET" => {
+                let mut ids: Vec<u64> = registry.games.keys().copied().collect();
+                ids.sort_unstable();
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                (
+                    200,
+                    json::object(&[("games", format!("[{}]", ids.join(",")))]),
+                )
+            }
+            "POST" => create_game(request, registry),
+            _ => method_not_allowed(),
+        },
+        ["games", id] => match request.method.as_str() {
+            "GET" => with_game(registry, id, |id, game| (200, game_json(id, game))),
+            _ => method_not_allowed(),
+        },
+        ["games", id, "moves"] => match request.method.as_str() {
+            "POST" => with_game(registry, id, |id, game| post_move(request, id, game)),
+            _ => method_not_allowed(),
+        },
+        _ => (
+            404,
+            error_json("not_found", &format!("No existe la ruta {}", request.path)),
+        ),
+    }
+}
+
+fn create_game(request: &HttpRequest, registry: &mut GameRegistry) -> (u16, String) {
+    let board = match json::string_field(&request.body, "fen") {
+        Some(fen) => match Chessboard::from_fen(&fen) {
+            Ok(board) => board,
+            Err(message) => return (400, message_error_json(&message)),
+        },
+        None => Chessboard::new(),
+    };
+
+    registry.next_id += 1;
+    let id = registry.next_id;
+    let game = ServerGame::new(board);
+
+    let body = game_json(id, &game);
+    registry.games.insert(id, game);
+    (201, body)
+}
+
+fn post_move(request: &HttpRequest, id: u64, game: &mut ServerGame) -> (u16, String) {
+    let Some(text) = json::string_field(&request.body, "move") else {
+        return (
+            400,
+            error_json(
+                "missing_move",
+                "Falta el campo \"move\", por ejemplo {\"move\": \"e2e4\"}",
+            ),
+        );
+    };
+
+    match game.play(&text) {
+        Ok(_) => (200, game_json(id, game)),
+        Err(error) => {
+            let status = match error {
+                MoveError::Unreadable(_) => 400,
+                MoveError::GameOver => 409,
+                MoveError::Illegal(_) => 422,
+            };
+            (status, error_json(error.code(), &error.message()))
+        }
+    }
+}
+
+// Busca la partida del identificador y le aplica la acción
+fn with_game(
+    registry: &mut GameRegistry,
+    id: &str,
+    action: impl FnOnce(u64, &mut ServerGame) -> (u16, String),
+) -> (u16, String) {
+    let game = id
+        .parse()
+        .ok()
+        .and_then(|id| registry.games.get_mut(&id).map(|game| (id, game)));
+
+    match game {
+        Some((id, game)) => action(id, game),
+        None => (
+            404,
+            error_json("game_not_found", &format!("No existe la partida {}", id)),
+        ),
+    }
+}
+
+fn method_not_allowed() -> (u16, String) {
+    (
+        405,
+        error_json("method_not_allowed", "Método no permitido para esta ruta"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(game: &mut ServerGame, chess_move: &str) -> u16 {
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            path: "/games/1/moves".to_string(),
+            headers: Vec::new(),
+            body: format!("{{\"move\": \"{}\"}}", chess_move),
+        };
+        post_move(&request, 1, game).0
+    }
+
+    #[test]
+    fn rejects_moves_missing_from_legal_moves() {
+        let mut game = ServerGame::new(Chessboard::new());
+        let legal_moves = game_json(1, &game);
+        assert!(legal_moves.contains("\"e2e4\""));
+
+        for chess_move in ["e1c1", "e1g1", "e2e4q", "e2e5"] {
+            assert!(!legal_moves.contains(&format!("\"{}\"", chess_move)));
+            assert_eq!(post(&mut game, chess_move), 422, "{}", chess_move);
+        }
+        assert!(game.moves.is_empty());
+
+        assert_eq!(post(&mut game, "e2e4"), 200);
+        assert_eq!(game.moves, ["e2e4"]);
+    }
+}
@@ -0,0 +1,1271 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::{ChessPieceColor, ChessPieceType};
+use crate::IA::move_generator::Move;
+use crate::IA::pgn::GameResult;
+use crate::IA::random::Random;
+use crate::IA::san::{parse_san, to_san};
+
+use super::game::{color_index, opposite, parse_move, Clock, EndReason, GameEnd, ServerGame};
+use super::lobby::{Lobby, Pairing, Seek, SeekOutcome};
+use super::store::{now, valid_player_name, Store, StoredGame};
+
+// El puerto de siempre de los servidores ICS
+pub const DEFAULT_ICS_PORT: u16 = 5000;
+
+// Cada servidor lleva su almacén: dos procesos en la misma carpeta se pisarían las partidas y las
+// fichas, porque cada uno numera y reescribe por su cuenta
+pub const DEFAULT_ICS_DIRECTORY: &str = "datos-ics";
+
+const PROMPT: &str = "fics% ";
+
+// Control de tiempo de un "seek" o un "match" sin números, en minutos y segundos
+const DEFAULT_MINUTES: f64 = 5.0;
+const DEFAULT_INCREMENT: f64 = 0.0;
+
+// Cada cuánto se revisan los relojes
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Comandos de telnet (RFC 854): IAC, y las negociaciones que llevan un byte más
+const TELNET_IAC: u8 = 255;
+const TELNET_SUBNEGOTIATION: u8 = 250;
+const TELNET_SUBNEGOTIATION_END: u8 = 240;
+
+struct User {
+    name: String,
+    guest: bool,
+    stream: TcpStream,
+    game: Option<u64>,
+    observing: Vec<u64>,
+}
+
+// Un desafío de "match" que todavía no se aceptó; la búsqueda guarda las condiciones
+struct Challenge {
+    to: u64,
+    seek: Seek,
+}
+
+struct IcsGame {
+    players: [u64; 2],
+    names: [String; 2],
+    game: ServerGame,
+    seek: Seek,
+    clock: Option<Clock>,
+    observers: Vec<u64>,
+    draw_offer: Option<ChessPieceColor>,
+    // La última jugada en formato largo ("P/e2-e4") y en notación algebraica
+    last_move: Option<(String, String)>,
+    // Jugadas desde la última captura o movimiento de peón, y la columna de un peón que avanzó dos
+    halfmove_clock: u32,
+    double_push_file: i32,
+    started: u64,
+}
+
+struct IcsServer {
+    users: HashMap<u64, User>,
+    games: HashMap<u64, IcsGame>,
+    challenges: Vec<Challenge>,
+    lobby: Lobby,
+    store: Store,
+    random: Random,
+    next_user: u64,
+    next_game: u64,
+}
+
+/**
+ Servidor de ajedrez por telnet al estilo de FICS, para jugar desde `nc`, `telnet` o un cliente
+ ICS. Los mensajes que leen los clientes ("login:", "Creating:", "{Game ...}" y las líneas
+ `<12>` del style 12) siguen el formato de FICS; las ayudas y los errores están en castellano.
+ Las partidas y las fichas se guardan con el mismo formato que el servidor de salas, pero en una
+ carpeta propia que no puede usar otro servidor al mismo tiempo.
+ # Arguments
+ * `port` - El puerto en el que se atiende.
+ * `directory` - La carpeta del almacén de partidas y jugadores.
+*/
+pub fn serve_ics(port: u16, directory: &str) {
+    let store = match Store::open(directory) {
+        Ok(store) => store,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let listener = match TcpListener::bind(("0.0.0.0", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!("No se pudo abrir el puerto {}: {}", port, error);
+            return;
+        }
+    };
+
+    println!(
+        "Servidor ICS en el puerto {}, por ejemplo: telnet localhost {}",
+        port, port
+    );
+    let server = Arc::new(Mutex::new(IcsServer {
+        users: HashMap::new(),
+        games: HashMap::new(),
+        challenges: Vec::new(),
+        lobby: Lobby::default(),
+        store,
+        random: Random::from_time(),
+        next_user: 0,
+        next_game: 0,
+    }));
+
+    let maintenance = Arc::clone(&server);
+    thread::spawn(move || loop {
+        thread::sleep(MAINTENANCE_INTERVAL);
+        maintenance.lock().unwrap().check_flags();
+    });
+
+    for stream in listener.incoming().flatten() {
+        let server = Arc::clone(&server);
+        thread::spawn(move || handle_connection(stream, &server));
+    }
+}
+
+fn handle_connection(stream: TcpStream, server: &Mutex<IcsServer>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+
+    write_text(
+        &mut writer,
+        "Bienvenido al servidor de ajedrez. Entrá con tu nombre, o con \"guest\" para jugar como invitado.\n\nlogin: ",
+    );
+
+    let (name, guest) = loop {
+        let Some(line) = read_line(&mut reader) else {
+            return;
+        };
+        let name = line.trim();
+
+        let (name, guest) = if name.is_empty() || name.eq_ignore_ascii_case("guest") {
+            (server.lock().unwrap().guest_name(), true)
+        } else if valid_player_name(name) && !name.to_ascii_lowercase().starts_with("guest") {
+            (name.to_string(), false)
+        } else {
+            write_text(
+                &mut writer,
+                "Los nombres son una palabra de hasta 20 letras, números, - o _, y no empiezan con \"guest\".\n\nlogin: ",
+            );
+            continue;
+        };
+
+        if server.lock().unwrap().user_by_name(&name).is_some() {
+            write_text(
+                &mut writer,
+                &format!("\"{}\" is already logged in.\n\nlogin: ", name),
+            );
+            continue;
+        }
+
+        // Sin cuentas ni contraseñas: los clientes ICS esperan esta línea en lugar de "password:"
+        write_text(
+            &mut writer,
+            &format!("\nPress return to enter the server as \"{}\":\n", name),
+        );
+        if read_line(&mut reader).is_none() {
+            return;
+        }
+        break (name, guest);
+    };
+
+    let Some(id) = server.lock().unwrap().login(&name, guest, writer) else {
+        return;
+    };
+
+    while let Some(line) = read_line(&mut reader) {
+        if !server.lock().unwrap().handle(id, line.trim()) {
+            break;
+        }
+    }
+    server.lock().unwrap().logout(id);
+}
+
+// Lee una línea sin los comandos de telnet; `None` si se cerró la conexión
+fn read_line(reader: &mut impl BufRead) -> Option<String> {
+    let mut bytes = Vec::new();
+    if reader.read_until(b'\n', &mut bytes).ok()? == 0 {
+        return None;
+    }
+
+    let mut text = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != TELNET_IAC {
+            text.push(bytes[index]);
+            index += 1;
+            continue;
+        }
+
+        match bytes.get(index + 1) {
+            // Un IAC doble es el byte 255
+            Some(&TELNET_IAC) => index += 2,
+            Some(&TELNET_SUBNEGOTIATION) => {
+                index += 2;
+                while index < bytes.len()
+                    && !(bytes[index] == TELNET_IAC
+                        && bytes.get(index + 1) == Some(&TELNET_SUBNEGOTIATION_END))
+                {
+                    index += 1;
+                }
+                index += 2;
+            }
+            // WILL, WONT, DO y DONT llevan la opción en el byte siguiente
+            Some(251..=254) => index += 3,
+            _ => index += 2,
+        }
+    }
+
+    Some(String::from_utf8_lossy(&text).trim_end().to_string())
+}
+
+// Telnet separa las líneas con "\r\n"
+fn write_text(stream: &mut TcpStream, text: &str) {
+    let _ = stream.write_all(text.replace('\n', "\r\n").as_bytes());
+    let _ = stream.flush();
+}
+
+impl IcsServer {
+    fn guest_name(&mut self) -> String {
+        loop {
+            let letters: String = (0..4)
+                .map(|_| (b'A' + self.random.range(26) as u8) as char)
+                .collect();
+            let name = format!("Guest{}", letters);
+            if self.user_by_name(&name).is_none() {
+                return name;
+            }
+        }
+    }
+
+    fn user_by_name(&self, name: &str) -> Option<u64> {
+        self.users
+            .iter()
+            .find(|(_, user)| user.name.eq_ignore_ascii_case(name))
+            .map(|(id, _)| *id)
+    }
+
+    // Registra al usuario si el nombre sigue libre
+    fn login(&mut self, name: &str, guest: bool, mut stream: TcpStream) -> Option<u64> {
+        if self.user_by_name(name).is_some() {
+            write_text(
+                &mut stream,
+                &format!("\"{}\" is already logged in.\n", name),
+            );
+            return None;
+        }
+
+        self.next_user += 1;
+        let id = self.next_user;
+        self.users.insert(
+            id,
+            User {
+                name: name.to_string(),
+                guest,
+                stream,
+                game: None,
+                observing: Vec::new(),
+            },
+        );
+
+        self.reply(
+            id,
+            &format!(
+                "\n**** Starting FICS session as {} ****\n\nEscribí \"help\" para ver los comandos.\n",
+                name
+            ),
+        );
+        Some(id)
+    }
+
+    fn logout(&mut self, id: u64) {
+        if let Some(number) = self.users.get(&id).and_then(|user| user.game) {
+            let color = self.games[&number].color_of(id);
+            self.end_game(
+                number,
+                GameEnd::win(opposite(color), EndReason::Disconnection),
+            );
+        }
+
+        for game in self.games.values_mut() {
+            game.observers.retain(|observer| *observer != id);
+        }
+        self.challenges
+            .retain(|challenge| challenge.to != id && challenge.seek.connection != id);
+        self.lobby.cancel(id);
+        self.users.remove(&id);
+    }
+
+    /**
+     Atiende una línea de un usuario: un comando o, durante una partida, una jugada en
+     coordenadas ("e2e4", "e2-e4") o en notación algebraica ("Nf3").
+     # Returns
+     `false` si el usuario pidió salir.
+    */
+    fn handle(&mut self, id: u64, line: &str) -> bool {
+        let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+        let arguments: Vec<&str> = arguments.split_whitespace().collect();
+
+        match command.to_ascii_lowercase().as_str() {
+            "" => self.reply(id, ""),
+            "quit" | "exit" => {
+                if let Some(user) = self.users.get_mut(&id) {
+                    write_text(&mut user.stream, "Logging you out.\n");
+                }
+                return false;
+            }
+            "help" => self.reply(id, HELP),
+            "seek" => self.seek(id, &arguments),
+            "unseek" => {
+                self.lobby.cancel(id);
+                self.reply(id, "Your seeks have been removed.");
+            }
+            "sought" => self.sought(id),
+            "play" => self.play_seek(id, &arguments),
+            "match" => self.challenge(id, &arguments),
+            "accept" => self.accept(id, &arguments),
+            "decline" => self.decline(id, &arguments),
+            "observe" => self.observe(id, &arguments),
+            "unobserve" => {
+                for game in self.games.values_mut() {
+                    game.observers.retain(|observer| *observer != id);
+                }
+                if let Some(user) = self.users.get_mut(&id) {
+                    user.observing.clear();
+                }
+                self.reply(id, "You are no longer observing any game.");
+            }
+            "tell" => self.tell(id, line),
+            "finger" => self.finger(id, &arguments),
+            "who" => self.who(id),
+            "games" => self.list_games(id),
+            "resign" => self.resign(id),
+            "draw" => self.draw(id),
+            "refresh" => match self.users[&id]
+                .game
+                .or(self.users[&id].observing.first().copied())
+            {
+                Some(number) => {
+                    let board = self.style12(number, id);
+                    self.reply(id, &board);
+                }
+                None => self.reply(id, "You are neither playing nor observing a game."),
+            },
+            _ if self.users[&id].game.is_some() => self.play_move(id, line),
+            _ => self.reply(id, &format!("{}: Command not found.", command)),
+        }
+        true
+    }
+
+    // Publica una búsqueda: seek [minutos] [incremento] [rated|unrated] [white|black]
+    fn seek(&mut self, id: u64, arguments: &[&str]) {
+        if self.users[&id].game.is_some() {
+            self.reply(id, "You are already playing a game.");
+            return;
+        }
+
+        let seek = match self.parse_terms(id, arguments) {
+            Ok(seek) => seek,
+            Err(error) => {
+                self.reply(id, &error);
+                return;
+            }
+        };
+
+        match self.lobby.seek(seek, &mut self.random) {
+            SeekOutcome::Paired(pairing) => self.start_game(pairing),
+            SeekOutcome::Published(seek) => {
+                let advert = format!(
+                    "{} ({}) seeking {} (\"play {}\" to respond)",
+                    self.users[&id].name,
+                    self.rating(&self.users[&id].name),
+                    terms(&seek),
+                    seek.id
+                );
+
+                let others: Vec<u64> = self
+                    .users
+                    .iter()
+                    .filter(|(other, user)| **other != id && user.game.is_none())
+                    .map(|(other, _)| *other)
+                    .collect();
+                for other in &others {
+                    self.notify(*other, &advert);
+                }
+                self.reply(
+                    id,
+                    &format!(
+                        "Your seek has been posted with index {}.\n({} player(s) saw the seek.)",
+                        seek.id,
+                        others.len()
+                    ),
+                );
+            }
+        }
+    }
+
+    /**
+     Lee las condiciones de un "seek" o un "match": los minutos y el incremento, en ese orden, y
+     en cualquier lugar "rated", "unrated", "white" o "black".
+     # Returns
+     La búsqueda con las condiciones, o el mensaje de error.
+    */
+    fn parse_terms(&self, id: u64, arguments: &[&str]) -> Result<Seek, String> {
+        let mut numbers = Vec::new();
+        let mut color = None;
+        let mut rated = false;
+
+        for argument in arguments {
+            match argument.to_ascii_lowercase().as_str() {
+                "rated" | "r" => rated = true,
+                "unrated" | "u" => rated = false,
+                "white" | "w" => color = Some(ChessPieceColor::White),
+                "black" | "b" => color = Some(ChessPieceColor::Black),
+                number => match number.parse::<f64>() {
+                    Ok(number) if numbers.len() < 2 => numbers.push(number),
+                    _ => return Err(format!("No se entiende \"{}\".", argument)),
+                },
+            }
+        }
+
+        if rated && self.users[&id].guest {
+            return Err("Los invitados solo pueden jugar partidas unrated.".to_string());
+        }
+
+        let minutes = numbers.first().copied().unwrap_or(DEFAULT_MINUTES);
+        let increment = numbers.get(1).copied().unwrap_or(DEFAULT_INCREMENT);
+        Seek::new(id, minutes, increment, color, rated).map_err(|(_, message)| message.to_string())
+    }
+
+    fn sought(&mut self, id: u64) {
+        let mut lines: Vec<String> = self
+            .lobby
+            .seeks()
+            .iter()
+            .map(|seek| {
+                let name = &self.users[&seek.connection].name;
+                format!(
+                    "{:>3} {:>4} {:<17} {}",
+                    seek.id,
+                    self.rating(name),
+                    name,
+                    terms(seek)
+                )
+            })
+            .collect();
+        lines.push(format!("{} ad(s) displayed.", lines.len()));
+        self.reply(id, &lines.join("\n"));
+    }
+
+    fn play_seek(&mut self, id: u64, arguments: &[&str]) {
+        if self.users[&id].game.is_some() {
+            self.reply(id, "You are already playing a game.");
+            return;
+        }
+
+        let pairing = arguments
+            .first()
+            .and_then(|number| number.parse().ok())
+            .and_then(|number| self.lobby.accept(number, id, &mut self.random));
+        match pairing {
+            Some(pairing) => self.start_game(pairing),
+            None => self.reply(id, "That seek is not available."),
+        }
+    }
+
+    // Desafía a un usuario: match <usuario> [minutos] [incremento] [rated|unrated] [white|black]
+    fn challenge(&mut self, id: u64, arguments: &[&str]) {
+        if self.users[&id].game.is_some() {
+            self.reply(id, "You are already playing a game.");
+            return;
+        }
+
+        let Some(opponent) = arguments.first().and_then(|name| self.user_by_name(name)) else {
+            self.reply(id, "Uso: match <usuario> [minutos] [incremento] [rated|unrated] [white|black]; el usuario tiene que estar conectado.");
+            return;
+        };
+        if opponent == id {
+            self.reply(id, "You can't match yourself.");
+            return;
+        }
+        if self.users[&opponent].game.is_some() {
+            let name = self.users[&opponent].name.clone();
+            self.reply(id, &format!("{} is playing a game.", name));
+            return;
+        }
+
+        let seek = match self.parse_terms(id, &arguments[1..]) {
+            Ok(seek) => seek,
+            Err(error) => {
+                self.reply(id, &error);
+                return;
+            }
+        };
+        if seek.rated && self.users[&opponent].guest {
+            self.reply(id, "Los invitados solo pueden jugar partidas unrated.");
+            return;
+        }
+
+        let description = format!(
+            "{} ({}) {} ({}) {}",
+            self.users[&id].name,
+            self.rating(&self.users[&id].name),
+            self.users[&opponent].name,
+            self.rating(&self.users[&opponent].name),
+            terms(&seek)
+        );
+
+        // Un desafío nuevo al mismo usuario reemplaza al anterior
+        self.challenges
+            .retain(|challenge| !(challenge.seek.connection == id && challenge.to == opponent));
+        self.challenges.push(Challenge { to: opponent, seek });
+
+        self.notify(
+            opponent,
+            &format!(
+                "Challenge: {}.\nYou can \"accept\" or \"decline\", or propose different parameters.",
+                description
+            ),
+        );
+        self.reply(id, &format!("Issuing: {}.", description));
+    }
+
+    // Busca un desafío recibido, del usuario indicado o el único que haya
+    fn take_challenge(&mut self, id: u64, arguments: &[&str]) -> Option<Challenge> {
+        let from = match arguments.first() {
+            Some(name) => Some(self.user_by_name(name)?),
+            None => None,
+        };
+
+        let received: Vec<usize> = self
+            .challenges
+            .iter()
+            .enumerate()
+            .filter(|(_, challenge)| {
+                challenge.to == id && from.is_none_or(|from| challenge.seek.connection == from)
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        match received[..] {
+            [index] => Some(self.challenges.remove(index)),
+            _ => None,
+        }
+    }
+
+    fn accept(&mut self, id: u64, arguments: &[&str]) {
+        if self.users[&id].game.is_some() {
+            self.reply(id, "You are already playing a game.");
+            return;
+        }
+
+        match self.take_challenge(id, arguments) {
+            Some(challenge) => {
+                let pairing = Pairing::new(id, None, challenge.seek, &mut self.random);
+                self.start_game(pairing);
+            }
+            None => self.reply(
+                id,
+                "No hay un desafío para aceptar; con varios hay que decir de quién.",
+            ),
+        }
+    }
+
+    fn decline(&mut self, id: u64, arguments: &[&str]) {
+        match self.take_challenge(id, arguments) {
+            Some(challenge) => {
+                let name = self.users[&id].name.clone();
+                self.notify(
+                    challenge.seek.connection,
+                    &format!("{} declines the match offer.", name),
+                );
+                self.reply(id, "You decline the match offer.");
+            }
+            None => self.reply(
+                id,
+                "No hay un desafío para rechazar; con varios hay que decir de quién.",
+            ),
+        }
+    }
+
+    // Crea la partida de una pareja y les muestra el tablero a los dos
+    fn start_game(&mut self, pairing: Pairing) {
+        self.next_game += 1;
+        let number = self.next_game;
+        let players = [pairing.white, pairing.black];
+        let names = players.map(|player| self.users[&player].name.clone());
+
+        let mut clock = pairing.seek.clock();
+        if let Some(clock) = clock.as_mut() {
+            clock.start(ChessPieceColor::White);
+        }
+
+        for player in players {
+            self.lobby.cancel(player);
+            self.challenges
+                .retain(|challenge| challenge.to != player && challenge.seek.connection != player);
+            if let Some(user) = self.users.get_mut(&player) {
+                user.game = Some(number);
+            }
+        }
+
+        let creating = format!(
+            "Creating: {} ({}) {} ({}) {}\n{{Game {} ({} vs. {}) Creating {} match.}}",
+            names[0],
+            self.rating(&names[0]),
+            names[1],
+            self.rating(&names[1]),
+            terms(&pairing.seek),
+            number,
+            names[0],
+            names[1],
+            game_kind(&pairing.seek)
+        );
+
+        self.games.insert(
+            number,
+            IcsGame {
+                players,
+                names,
+                game: ServerGame::new(Chessboard::new()),
+                seek: pairing.seek,
+                clock,
+                observers: Vec::new(),
+                draw_offer: None,
+                last_move: None,
+                halfmove_clock: 0,
+                double_push_file: -1,
+                started: now(),
+            },
+        );
+
+        for player in players {
+            let board = self.style12(number, player);
+            self.notify(player, &format!("{}\n\n{}", creating, board));
+        }
+    }
+
+    fn play_move(&mut self, id: u64, text: &str) {
+        let number = self.users[&id].game.unwrap();
+        let game = self.games.get_mut(&number).unwrap();
+        let color = game.color_of(id);
+
+        if game.game.board.player_turn != color {
+            self.reply(id, "It is not your move.");
+            return;
+        }
+        if let Some(flagged) = game.clock.as_ref().and_then(Clock::flagged) {
+            self.end_game(number, GameEnd::win(opposite(flagged), EndReason::Time));
+            return;
+        }
+
+        // Coordenadas con o sin guion, y si no, notación algebraica
+        let before = game.game.board.clone();
+        let coordinates = parse_move(&text.replace('-', ""))
+            .map(Move::to_coordinates)
+            .or_else(|| {
+                parse_san(&before, text).map(|(chess_move, _)| chess_move.to_coordinates())
+            });
+        let Some(coordinates) = coordinates else {
+            self.reply(id, &format!("{}: Command not found.", text));
+            return;
+        };
+
+        let chess_move = match game.game.play(&coordinates) {
+            Ok(chess_move) => chess_move,
+            Err(_) => {
+                self.reply(id, &format!("Illegal move ({}).", text));
+                return;
+            }
+        };
+
+        game.record_move(&before, chess_move);
+        if let Some(clock) = game.clock.as_mut() {
+            clock.press();
+        }
+        game.draw_offer = None;
+
+        self.send_boards(number);
+        if let Some(end) = self.games[&number].game.end {
+            self.end_game(number, end);
+        }
+    }
+
+    fn resign(&mut self, id: u64) {
+        match self.users[&id].game {
+            Some(number) => {
+                let color = self.games[&number].color_of(id);
+                self.end_game(
+                    number,
+                    GameEnd::win(opposite(color), EndReason::Resignation),
+                );
+            }
+            None => self.reply(id, "You are not playing a game."),
+        }
+    }
+
+    // Ofrece tablas, o las acepta si el rival ya las ofreció
+    fn draw(&mut self, id: u64) {
+        let Some(number) = self.users[&id].game else {
+            self.reply(id, "You are not playing a game.");
+            return;
+        };
+
+        let game = self.games.get_mut(&number).unwrap();
+        let color = game.color_of(id);
+        if game.draw_offer == Some(opposite(color)) {
+            self.end_game(number, GameEnd::draw(EndReason::Agreement));
+            return;
+        }
+
+        game.draw_offer = Some(color);
+        let opponent = game.players[color_index(opposite(color))];
+        let name = self.users[&id].name.clone();
+        self.notify(opponent, &format!("{} offers you a draw.", name));
+        self.reply(id, "Offering a draw.");
+    }
+
+    /**
+     Termina una partida: avisa a los jugadores y a los observadores con la línea "{Game ...}",
+     la guarda en el almacén y anota el resultado en las fichas.
+    */
+    fn end_game(&mut self, number: u64, end: GameEnd) {
+        let Some(mut game) = self.games.remove(&number) else {
+            return;
+        };
+        game.game.finish(end);
+        if let Some(clock) = game.clock.as_mut() {
+            clock.stop();
+        }
+
+        let line = format!(
+            "{{Game {} ({} vs. {}) {}}} {}",
+            number,
+            game.names[0],
+            game.names[1],
+            end_description(&game.names, end),
+            end.result.to_token()
+        );
+
+        for player in game.players {
+            if let Some(user) = self.users.get_mut(&player) {
+                user.game = None;
+            }
+        }
+        for id in game.players.iter().chain(&game.observers) {
+            if let Some(user) = self.users.get_mut(id) {
+                user.observing.retain(|observed| *observed != number);
+            }
+            self.notify(*id, &line);
+        }
+
+        let guest = |player: u64| self.users.get(&player).is_none_or(|user| user.guest);
+        let stored = StoredGame {
+            id: self.store.next_id(),
+            room: format!("ics-{}", number),
+            started: game.started,
+            finished: Some(now()),
+            rated: game.seek.rated,
+            // Las partidas de invitados se guardan sin nombre, para no llenar las fichas
+            players: [0, 1]
+                .map(|index| (!guest(game.players[index])).then(|| game.names[index].clone())),
+            tokens: [None, None],
+            clock: None,
+            end: Some(end),
+            moves: game.game.moves.clone(),
+        };
+        let result = self
+            .store
+            .save_game(&stored)
+            .and_then(|_| self.store.record_result(&stored));
+        if let Err(error) = result {
+            println!("{}", error);
+        }
+    }
+
+    fn check_flags(&mut self) {
+        let flagged: Vec<(u64, ChessPieceColor)> = self
+            .games
+            .iter()
+            .filter_map(|(number, game)| {
+                Some((*number, game.clock.as_ref().and_then(Clock::flagged)?))
+            })
+            .collect();
+
+        for (number, color) in flagged {
+            self.end_game(number, GameEnd::win(opposite(color), EndReason::Time));
+        }
+    }
+
+    // observe <usuario | número de partida>
+    fn observe(&mut self, id: u64, arguments: &[&str]) {
+        let number = arguments.first().and_then(|target| {
+            target.parse::<u64>().ok().or_else(|| {
+                let user = self.user_by_name(target)?;
+                self.users[&user].game
+            })
+        });
+        let Some(number) = number.filter(|number| self.games.contains_key(number)) else {
+            self.reply(
+                id,
+                "Uso: observe <usuario|número de partida>; tiene que ser una partida en curso.",
+            );
+            return;
+        };
+
+        let game = self.games.get_mut(&number).unwrap();
+        if !game.observers.contains(&id) && !game.players.contains(&id) {
+            game.observers.push(id);
+        }
+        if let Some(user) = self.users.get_mut(&id) {
+            if !user.observing.contains(&number) {
+                user.observing.push(number);
+            }
+        }
+
+        let board = self.style12(number, id);
+        self.reply(
+            id,
+            &format!("You are now observing game {}.\n\n{}", number, board),
+        );
+    }
+
+    fn tell(&mut self, id: u64, line: &str) {
+        let mut words = line.splitn(3, ' ').skip(1);
+        let (Some(name), Some(message)) = (words.next(), words.next()) else {
+            self.reply(id, "Uso: tell <usuario> <mensaje>");
+            return;
+        };
+        let Some(target) = self.user_by_name(name) else {
+            self.reply(id, &format!("{} is not logged in.", name));
+            return;
+        };
+
+        let (from, to) = (
+            self.users[&id].name.clone(),
+            self.users[&target].name.clone(),
+        );
+        self.notify(target, &format!("{} tells you: {}", from, message.trim()));
+        self.reply(id, &format!("(told {})", to));
+    }
+
+    // La ficha de un usuario, conectado o no, con lo que hay en el almacén
+    fn finger(&mut self, id: u64, arguments: &[&str]) {
+        let name = match arguments.first() {
+            Some(name) => name.to_string(),
+            None => self.users[&id].name.clone(),
+        };
+        let online = self.user_by_name(&name);
+
+        let mut lines = vec![format!("Finger of {}:", name), String::new()];
+        match online {
+            Some(user) => {
+                let user = &self.users[&user];
+                match user.game {
+                    Some(number) => {
+                        lines.push(format!("{} is online, playing game {}.", user.name, number))
+                    }
+                    None => lines.push(format!("{} is online.", user.name)),
+                }
+                if user.guest {
+                    lines.push(format!("{} is a guest (unregistered) player.", user.name));
+                }
+            }
+            None => lines.push(format!("{} is not logged in.", name)),
+        }
+
+        match self.store.player(&name) {
+            Some(record) => {
+                lines.push(String::new());
+                lines.push("          rating     win  loss  draw total".to_string());
+                lines.push(format!(
+                    "Standard  {:>6}  {:>6}{:>6}{:>6}{:>6}",
+                    record.rating, record.wins, record.losses, record.draws, record.games
+                ));
+            }
+            None if online.is_none() => {
+                lines.push("No hay partidas guardadas de ese jugador.".to_string())
+            }
+            None => {}
+        }
+
+        self.reply(id, &lines.join("\n"));
+    }
+
+    fn who(&mut self, id: u64) {
+        let mut users: Vec<&User> = self.users.values().collect();
+        users.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut lines: Vec<String> = users
+            .iter()
+            .map(|user| {
+                let playing = if user.game.is_some() { "^" } else { " " };
+                format!("{:>4}{}{}", self.rating(&user.name), playing, user.name)
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(format!(" {} player(s) displayed.", users.len()));
+        self.reply(id, &lines.join("\n"));
+    }
+
+    fn list_games(&mut self, id: u64) {
+        let mut numbers: Vec<&u64> = self.games.keys().collect();
+        numbers.sort();
+
+        let mut lines: Vec<String> = numbers
+            .iter()
+            .map(|number| {
+                let game = &self.games[number];
+                let time = |color| {
+                    game.clock.as_ref().map_or("-".to_string(), |clock| {
+                        let seconds = clock.remaining(color).as_secs();
+                        format!("{}:{:02}", seconds / 60, seconds % 60)
+                    })
+                };
+                let turn = match game.game.board.player_turn {
+                    ChessPieceColor::White => "W",
+                    ChessPieceColor::Black => "B",
+                };
+                format!(
+                    "{:>3} {:>4} {:<10} {:>4} {:<10} [{}] {:>6} - {:>6} {}: {}",
+                    number,
+                    self.rating(&game.names[0]),
+                    game.names[0],
+                    self.rating(&game.names[1]),
+                    game.names[1],
+                    terms(&game.seek),
+                    time(ChessPieceColor::White),
+                    time(ChessPieceColor::Black),
+                    turn,
+                    game.game.moves.len() / 2 + 1
+                )
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(format!("  {} game(s) displayed.", numbers.len()));
+        self.reply(id, &lines.join("\n"));
+    }
+
+    // La línea style 12 con el tablero de la partida tal como la ve el usuario
+    fn style12(&self, number: u64, id: u64) -> String {
+        let game = &self.games[&number];
+        let board = &game.game.board;
+
+        // Jugador con el turno 1, jugador esperando -1, observador 0
+        let relation = match game.players.iter().position(|player| *player == id) {
+            Some(index) if seat_color(index) == board.player_turn => 1,
+            Some(_) => -1,
+            None => 0,
+        };
+        let flip = game.players[1] == id;
+
+        let fen = board.to_fen();
+        let mut fields = fen.split(' ');
+        let rows: Vec<String> = fields
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .map(|row| {
+                row.chars()
+                    .flat_map(|square| match square.to_digit(10) {
+                        Some(empty) => vec!['-'; empty as usize],
+                        None => vec![square],
+                    })
+                    .collect()
+            })
+            .collect();
+        let castling = fields.nth(1).unwrap_or("-");
+        let right = |letter| u8::from(castling.contains(letter));
+
+        let strength = |white: bool| -> u32 {
+            rows.iter()
+                .flat_map(|row| row.chars())
+                .filter(|piece| piece.is_ascii_alphabetic() && piece.is_ascii_uppercase() == white)
+                .map(|piece| match piece.to_ascii_lowercase() {
+                    'q' => 9,
+                    'r' => 5,
+                    'b' | 'n' => 3,
+                    'p' => 1,
+                    _ => 0,
+                })
+                .sum()
+        };
+        let remaining = |color| {
+            game.clock
+                .as_ref()
+                .map_or(0, |clock| clock.remaining(color).as_secs())
+        };
+        let (verbose, pretty) = game
+            .last_move
+            .clone()
+            .unwrap_or(("none".to_string(), "none".to_string()));
+
+        format!(
+            "<12> {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} (0:00) {} {} {} 0",
+            rows.join(" "),
+            if board.player_turn == ChessPieceColor::White {
+                "W"
+            } else {
+                "B"
+            },
+            game.double_push_file,
+            right('K'),
+            right('Q'),
+            right('k'),
+            right('q'),
+            game.halfmove_clock,
+            number,
+            game.names[0],
+            game.names[1],
+            relation,
+            game.seek.minutes as u64,
+            game.seek.increment as u64,
+            strength(true),
+            strength(false),
+            remaining(ChessPieceColor::White),
+            remaining(ChessPieceColor::Black),
+            game.game.moves.len() / 2 + 1,
+            verbose,
+            pretty,
+            u8::from(flip),
+            u8::from(game.clock.is_some() && game.game.end.is_none()),
+        )
+    }
+
+    // Envía el tablero a los jugadores y a los observadores
+    fn send_boards(&mut self, number: u64) {
+        let game = &self.games[&number];
+        let recipients: Vec<u64> = game
+            .players
+            .iter()
+            .chain(&game.observers)
+            .copied()
+            .collect();
+        for id in recipients {
+            let board = self.style12(number, id);
+            self.notify(id, &board);
+        }
+    }
+
+    // Los invitados no tienen puntuación ("++++") y quien no jugó todavía tampoco ("----")
+    fn rating(&self, name: &str) -> String {
+        let guest = self
+            .user_by_name(name)
+            .is_some_and(|id| self.users[&id].guest);
+        match self.store.player(name) {
+            _ if guest => "++++".to_string(),
+            Some(record) => record.rating.to_string(),
+            None => "----".to_string(),
+        }
+    }
+
+    // Respuesta a un comando, seguida del prompt
+    fn reply(&mut self, id: u64, text: &str) {
+        if let Some(user) = self.users.get_mut(&id) {
+            let text = if text.is_empty() {
+                PROMPT.to_string()
+            } else {
+                format!("{}\n{}", text, PROMPT)
+            };
+            write_text(&mut user.stream, &text);
+        }
+    }
+
+    // Aviso que llega sin que el usuario lo pida, en una línea aparte del prompt
+    fn notify(&mut self, id: u64, text: &str) {
+        if let Some(user) = self.users.get_mut(&id) {
+            write_text(&mut user.stream, &format!("\n{}\n{}", text, PROMPT));
+        }
+    }
+}
+
+impl IcsGame {
+    fn color_of(&self, id: u64) -> ChessPieceColor {
+        if self.players[0] == id {
+            ChessPieceColor::White
+        } else {
+            ChessPieceColor::Black
+        }
+    }
+
+    // Anota la jugada recién hecha para el style 12
+    fn record_move(&mut self, before: &Chessboard, chess_move: Move) {
+        let piece = before.board[chess_move.from[0]][chess_move.from[1]];
+        let pawn = piece.is_some_and(|piece| piece.piece == ChessPieceType::Pawn);
+        let capture = before.board[chess_move.to[0]][chess_move.to[1]].is_some();
+
+        self.halfmove_clock = if pawn || capture {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        self.double_push_file = if pawn && chess_move.from[0].abs_diff(chess_move.to[0]) == 2 {
+            chess_move.to[1] as i32
+        } else {
+            -1
+        };
+
+        let coordinates = chess_move.to_coordinates();
+        let king = piece.is_some_and(|piece| piece.piece == ChessPieceType::King);
+        let verbose = match chess_move.to[1] as i32 - chess_move.from[1] as i32 {
+            2 if king => "o-o".to_string(),
+            -2 if king => "o-o-o".to_string(),
+            _ => {
+                let letter = piece.map_or('P', |piece| piece_letter(piece.piece));
+                let promotion = chess_move.promotion.map_or(String::new(), |promotion| {
+                    format!("={}", piece_letter(promotion))
+                });
+                format!(
+                    "{}/{}-{}{}",
+                    letter,
+                    &coordinates[0..2],
+                    &coordinates[2..4],
+                    promotion
+                )
+            }
+        };
+
+        self.last_move = Some((verbose, to_san(before, chess_move)));
+    }
+}
+
+fn piece_letter(piece: ChessPieceType) -> char {
+    match piece {
+        ChessPieceType::King => 'K',
+        ChessPieceType::Queen => 'Q',
+        ChessPieceType::Rook => 'R',
+        ChessPieceType::Bishop => 'B',
+        ChessPieceType::Knight => 'N',
+        ChessPieceType::Pawn => 'P',
+    }
+}
+
+fn seat_color(index: usize) -> ChessPieceColor {
+    if index == 0 {
+        ChessPieceColor::White
+    } else {
+        ChessPieceColor::Black
+    }
+}
+
+// Tipo de partida según la duración estimada, como en FICS: tiempo + 2/3 del incremento
+fn game_kind(seek: &Seek) -> String {
+    let rated = if seek.rated { "rated" } else { "unrated" };
+    let expected = seek.minutes + seek.increment * 2.0 / 3.0;
+    let speed = match expected {
+        _ if seek.minutes == 0.0 => "untimed",
+        expected if expected < 3.0 => "lightning",
+        expected if expected < 15.0 => "blitz",
+        _ => "standard",
+    };
+    format!("{} {}", rated, speed)
+}
+
+// Las condiciones como las muestra FICS, por ejemplo "unrated blitz 5 0"
+fn terms(seek: &Seek) -> String {
+    let color = match seek.color {
+        Some(ChessPieceColor::White) => " [white]",
+        Some(ChessPieceColor::Black) => " [black]",
+        None => "",
+    };
+    format!(
+        "{} {} {}{}",
+        game_kind(seek),
+        seek.minutes,
+        seek.increment,
+        color
+    )
+}
+
+// El motivo del final como lo escribe FICS, con el nombre de quien perdió
+fn end_description(names: &[String; 2], end: GameEnd) -> String {
+    let loser = match end.result {
+        GameResult::WhiteWins => &names[1],
+        GameResult::BlackWins => &names[0],
+        _ => "",
+    };
+
+    match end.reason {
+        EndReason::Checkmate => format!("{} checkmated", loser),
+        EndReason::Stalemate => "Game drawn by stalemate".to_string(),
+        EndReason::Resignation => format!("{} resigns", loser),
+        EndReason::Agreement => "Game drawn by mutual agreement".to_string(),
+        EndReason::Time => format!("{} forfeits on time", loser),
+        EndReason::Disconnection => format!("{} forfeits by disconnection", loser),
+    }
+}
+
+const HELP: &str = "Comandos:
+  seek [minutos] [incremento] [rated|unrated] [white|black]   busca rival
+  unseek, sought, play <n>                                    retira, lista o acepta búsquedas
+  match <usuario> [minutos] [incremento] [rated|unrated]      desafía a un usuario
+  accept [usuario], decline [usuario]                         responde un desafío
+  observe <usuario|partida>, unobserve, refresh               mira partidas
+  tell <usuario> <mensaje>, finger [usuario], who, games
+  resign, draw                                                durante una partida
+  quit
+Las jugadas se escriben en coordenadas (e2e4, e2-e4) o en notación algebraica (Nf3).";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(directory: &str) -> IcsServer {
+        let directory = std::env::temp_dir().join(directory);
+        let _ = std::fs::remove_dir_all(&directory);
+        IcsServer {
+            users: HashMap::new(),
+            games: HashMap::new(),
+            challenges: Vec::new(),
+            lobby: Lobby::default(),
+            store: Store::open(directory.to_str().unwrap()).ok().unwrap(),
+            random: Random::from_time(),
+            next_user: 0,
+            next_game: 0,
+        }
+    }
+
+    // Conecta un usuario por una conexión local; se devuelve el otro extremo para que no se cierre
+    fn login(server: &mut IcsServer, listener: &TcpListener, name: &str) -> (u64, TcpStream) {
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (server.login(name, false, stream).unwrap(), client)
+    }
+
+    #[test]
+    fn players_in_a_game_cannot_seek_or_match() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut server = server("ajedrez-ics-ocupado");
+        let (ana, _ana) = login(&mut server, &listener, "ana");
+        let (beto, _beto) = login(&mut server, &listener, "beto");
+        let (carla, _carla) = login(&mut server, &listener, "carla");
+
+        server.handle(ana, "match beto 5 0");
+        server.handle(beto, "accept");
+        let game = server.users[&ana].game;
+        assert!(game.is_some());
+
+        server.handle(carla, "seek 5 0");
+        server.handle(ana, "seek 5 0");
+        server.handle(ana, "match carla 5 0");
+        assert!(server.challenges.is_empty());
+        assert_eq!(server.lobby.seeks().len(), 1);
+
+        server.handle(beto, "play 1");
+        assert_eq!(server.users[&ana].game, game);
+        assert_eq!(server.users[&beto].game, game);
+        assert!(server.users[&carla].game.is_none());
+        assert_eq!(server.games.len(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+// Lo justo de JSON para los servidores: escribir valores y leer los campos de un objeto plano
+
+// Texto entre comillas con los caracteres especiales escapados
+pub fn string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            character if (character as u32) < 0x20 => {
+                json.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => json.push(character),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+// Arreglo de textos, por ejemplo ["e2e4","d2d4"]
+pub fn string_array<S: AsRef<str>>(values: &[S]) -> String {
+    let values: Vec<String> = values.iter().map(|value| string(value.as_ref())).collect();
+    format!("[{}]", values.join(","))
+}
+
+// Objeto con los pares indicados; los valores ya deben estar escritos en JSON
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/**
+ Busca un campo de texto en un objeto JSON plano, por ejemplo "move" en {"move": "e2e4"}.
+ # Arguments
+ * `json` - El objeto.
+ * `key` - El nombre del campo.
+ # Returns
+ El valor sin comillas ni escapes, o `None` si el campo no está o no es un texto.
+*/
+pub fn string_field(json: &str, key: &str) -> Option<String> {
+    let mut chars = field_value(json, key)?.strip_prefix('"')?.chars();
+    let mut value = String::new();
+
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                escaped => value.push(escaped),
+            },
+            character => value.push(character),
+        }
+    }
+}
+
+// Campo numérico de un objeto plano, por ejemplo "minutes" en {"minutes": 5}
+pub fn number_field(json: &str, key: &str) -> Option<f64> {
+    let value = field_value(json, key)?;
+    let end = value
+        .find(|character: char| !matches!(character, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+pub fn bool_field(json: &str, key: &str) -> Option<bool> {
+    let value = field_value(json, key)?;
+    if value.starts_with("true") {
+        Some(true)
+    } else if value.starts_with("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// El texto desde el valor del campo hasta el final del objeto
+fn field_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let pattern = string(key);
+    let mut rest = json;
+
+    // El nombre tiene que estar seguido de dos puntos para no confundirlo con un valor
+    loop {
+        let start = rest.find(&pattern)? + pattern.len();
+        rest = rest[start..].trim_start();
+        if let Some(value) = rest.strip_prefix(':') {
+            return Some(value.trim_start());
+        }
+    }
+}
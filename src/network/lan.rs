@@ -0,0 +1,670 @@
+use std::io::{self, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::commands::option_value;
+use crate::pieces::piece_type::ChessPieceColor;
+use crate::IA::move_generator::Move;
+use crate::IA::pgn::GameResult;
+
+use super::game::{
+    color_from_name, color_name, final_position, format_time, opposite, parse_move, play_move,
+    Clock, EndReason, GameEnd,
+};
+use super::spawn_line_reader;
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+// Versión del protocolo que se anuncia en el saludo
+const PROTOCOL_VERSION: u32 = 1;
+
+// Cada cuánto revisa el anfitrión si se cayó la bandera del jugador en turno
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const INPUT_PROMPT: &str =
+    "Tu jugada (por ejemplo e2 e4 o e7e8q para coronar), \"tablas\", \"abandonar\" o \"reloj\":";
+
+pub struct LanOptions {
+    pub host_color: ChessPieceColor,
+    // Tiempo de cada jugador; `None` para jugar sin reloj
+    pub time: Option<Duration>,
+    pub increment: Duration,
+}
+
+enum LanEvent {
+    Input(String),
+    InputClosed,
+    Peer(String),
+    Disconnected,
+}
+
+// El anfitrión valida todo y manda sobre el reloj; el invitado solo propone
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Host,
+    Guest,
+}
+
+/**
+ Partida entre dos personas por la red local. Los dos extremos hablan un protocolo de líneas de
+ texto:
+ * `hello <versión> <color del invitado> <tiempo en ms> <incremento en ms>`: saludo del anfitrión.
+ * `move <jugada> [<reloj blancas ms> <reloj negras ms>]`: el invitado propone una jugada y el
+   anfitrión anuncia cada jugada aceptada, propia o del invitado, con el estado del reloj.
+ * `reject <motivo>`: el anfitrión rechaza la jugada del invitado.
+ * `draw`: ofrece tablas, o las acepta si el rival ya las ofreció.
+ * `resign`: abandona la partida.
+ * `clock [<reloj blancas ms> <reloj negras ms>]`: pide o informa el estado del reloj.
+ * `end <resultado> <motivo>`: el anfitrión anuncia el final de la partida.
+*/
+struct LanSession {
+    role: Role,
+    board: Chessboard,
+    color: ChessPieceColor,
+    clock: Option<Clock>,
+    peer: TcpStream,
+    // Bando que ofreció tablas y espera la respuesta del rival
+    draw_offer: Option<ChessPieceColor>,
+    status: String,
+}
+
+// Color del anfitrión y control de tiempo de una partida en red
+pub fn parse_lan_options(args: &[String]) -> Option<LanOptions> {
+    let host_color = match option_value(args, "--color") {
+        Some(name) => {
+            let Some(color) = color_from_name(name) else {
+                println!("Color desconocido: {}. Colores: blanco, negro", name);
+                return None;
+            };
+            color
+        }
+        None => ChessPieceColor::White,
+    };
+
+    // Los tiempos se escriben en minutos y el incremento en segundos
+    let duration = |option: &str, seconds: f64| match option_value(args, option) {
+        Some(value) => match value.parse::<f64>() {
+            Ok(value) if value >= 0.0 => Some(Some(Duration::from_secs_f64(value * seconds))),
+            _ => {
+                println!("Valor inválido para {}: {}", option, value);
+                None
+            }
+        },
+        None => Some(None),
+    };
+
+    Some(LanOptions {
+        host_color,
+        time: duration("--minutos", 60.0)?,
+        increment: duration("--incremento", 1.0)?.unwrap_or(Duration::ZERO),
+    })
+}
+
+/**
+ Espera a que se conecte el rival y juega la partida como anfitrión.
+ # Arguments
+ * `port` - El puerto en el que se espera la conexión.
+ * `options` - El color del anfitrión y el control de tiempo.
+*/
+pub fn host(port: u16, options: LanOptions) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!("No se pudo abrir el puerto {}: {}", port, error);
+            return;
+        }
+    };
+
+    println!("Esperando al rival en el puerto {}...", port);
+    let (stream, address) = match listener.accept() {
+        Ok(connection) => connection,
+        Err(error) => {
+            println!("No se pudo aceptar la conexión: {}", error);
+            return;
+        }
+    };
+
+    let mut session = LanSession {
+        role: Role::Host,
+        board: Chessboard::new(),
+        color: options.host_color,
+        clock: options.time.map(|time| Clock::new(time, options.increment)),
+        peer: stream,
+        draw_offer: None,
+        status: format!("Se conectó {}", address),
+    };
+
+    let time = options.time.unwrap_or(Duration::ZERO);
+    session.send(&format!(
+        "hello {} {} {} {}",
+        PROTOCOL_VERSION,
+        color_name(opposite(options.host_color)),
+        time.as_millis(),
+        options.increment.as_millis()
+    ));
+
+    if let Some(clock) = session.clock.as_mut() {
+        clock.start(ChessPieceColor::White);
+    }
+
+    session.run();
+}
+
+/**
+ Se conecta a un anfitrión y juega la partida como invitado.
+ # Arguments
+ * `address` - La dirección del anfitrión, con el puerto o sin él, por ejemplo "192.168.0.10:7878".
+*/
+pub fn join(address: &str) {
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    };
+
+    let stream = match TcpStream::connect(&address) {
+        Ok(stream) => stream,
+        Err(error) => {
+            println!("No se pudo conectar a {}: {}", address, error);
+            return;
+        }
+    };
+
+    let mut session = LanSession {
+        role: Role::Guest,
+        board: Chessboard::new(),
+        color: ChessPieceColor::Black,
+        clock: None,
+        peer: stream,
+        draw_offer: None,
+        status: format!("Conectado a {}", address),
+    };
+
+    session.run();
+}
+
+impl LanSession {
+    fn run(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+
+        let reader = match self.peer.try_clone() {
+            Ok(stream) => BufReader::new(stream),
+            Err(error) => {
+                println!("No se pudo leer la conexión: {}", error);
+                return;
+            }
+        };
+        spawn_line_reader(
+            reader,
+            sender.clone(),
+            LanEvent::Peer,
+            LanEvent::Disconnected,
+        );
+        spawn_line_reader(
+            BufReader::new(io::stdin()),
+            sender,
+            LanEvent::Input,
+            LanEvent::InputClosed,
+        );
+
+        // El invitado no muestra nada hasta recibir el saludo con su color
+        if self.role == Role::Host {
+            self.show();
+        }
+
+        loop {
+            let event = match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(end) = self.flag_fall() {
+                        self.finish(end);
+                        return;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            let end = match event {
+                LanEvent::Input(line) => self.local_input(line.trim()),
+                LanEvent::Peer(line) => match self.role {
+                    Role::Host => self.host_receive(line.trim()),
+                    Role::Guest => match self.guest_receive(line.trim()) {
+                        Some(end) => {
+                            self.show_end(format!("Fin de la partida: {}", end.describe()));
+                            return;
+                        }
+                        None => None,
+                    },
+                },
+                // Sin entrada el jugador ya no puede mover, así que abandona
+                LanEvent::InputClosed => match self.role {
+                    Role::Host => Some(GameEnd::win(opposite(self.color), EndReason::Resignation)),
+                    Role::Guest => {
+                        self.send("resign");
+                        return;
+                    }
+                },
+                LanEvent::Disconnected => match self.role {
+                    Role::Host => Some(GameEnd::win(self.color, EndReason::Disconnection)),
+                    Role::Guest => {
+                        self.show_end("El anfitrión se desconectó".to_string());
+                        return;
+                    }
+                },
+            };
+
+            if let Some(end) = end {
+                self.finish(end);
+                return;
+            }
+
+            self.show();
+        }
+    }
+
+    // Lo que escribe el jugador local
+    fn local_input(&mut self, line: &str) -> Option<GameEnd> {
+        match line.to_lowercase().as_str() {
+            "" => None,
+            "reloj" => {
+                if self.role == Role::Guest {
+                    self.send("clock");
+                }
+                self.status = self.clock_status();
+                None
+            }
+            "abandonar" => match self.role {
+                Role::Host => Some(GameEnd::win(opposite(self.color), EndReason::Resignation)),
+                Role::Guest => {
+                    self.send("resign");
+                    None
+                }
+            },
+            "tablas" => match self.role {
+                Role::Host => self.offer_draw(self.color),
+                Role::Guest => {
+                    self.send("draw");
+                    self.status = "Ofreciste tablas".to_string();
+                    None
+                }
+            },
+            text => {
+                let Some(chess_move) = parse_move(text) else {
+                    self.status = format!("No entiendo \"{}\"", text);
+                    return None;
+                };
+
+                if self.board.player_turn != self.color {
+                    self.status = "No es tu turno".to_string();
+                    return None;
+                }
+
+                // Los dos extremos validan; el invitado para avisar antes de enviar la jugada
+                match play_move(&self.board, chess_move) {
+                    Err(message) => {
+                        self.status = message.get_message();
+                        None
+                    }
+                    Ok((chess_move, _)) if self.role == Role::Guest => {
+                        self.send(&format!("move {}", chess_move.to_coordinates()));
+                        self.status = "Esperando la confirmación del anfitrión".to_string();
+                        None
+                    }
+                    Ok((chess_move, new_board)) => self.accept_move(chess_move, new_board),
+                }
+            }
+        }
+    }
+
+    // Mensajes del invitado, que el anfitrión valida antes de aplicar
+    fn host_receive(&mut self, line: &str) -> Option<GameEnd> {
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let peer_color = opposite(self.color);
+
+        match command {
+            "move" => {
+                if self.board.player_turn != peer_color {
+                    self.send("reject No es tu turno");
+                    return None;
+                }
+
+                let Some(chess_move) = parse_move(argument) else {
+                    self.send(&format!("reject Jugada ilegible: {}", argument));
+                    return None;
+                };
+
+                match play_move(&self.board, chess_move) {
+                    Ok((chess_move, new_board)) => self.accept_move(chess_move, new_board),
+                    Err(message) => {
+                        self.send(&format!("reject {}", message.get_message()));
+                        None
+                    }
+                }
+            }
+            "draw" => self.offer_draw(peer_color),
+            "resign" => Some(GameEnd::win(self.color, EndReason::Resignation)),
+            "clock" => {
+                let clock = self.clock_message();
+                self.send(&format!("clock {}", clock));
+                None
+            }
+            _ => {
+                self.send(&format!("reject Mensaje desconocido: {}", command));
+                None
+            }
+        }
+    }
+
+    // Mensajes del anfitrión, que el invitado aplica sin discutir
+    fn guest_receive(&mut self, line: &str) -> Option<GameEnd> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.first().copied() {
+            Some("hello") => {
+                let color = tokens.get(2).and_then(|color| color_from_name(color));
+                let time = tokens.get(3).and_then(|time| time.parse().ok());
+                let increment = tokens.get(4).and_then(|increment| increment.parse().ok());
+
+                let (Some(color), Some(time), Some(increment)) = (color, time, increment) else {
+                    self.status = format!("Saludo inválido del anfitrión: {}", line);
+                    return None;
+                };
+
+                self.color = color;
+                if time > 0 {
+                    let mut clock = Clock::new(
+                        Duration::from_millis(time),
+                        Duration::from_millis(increment),
+                    );
+                    clock.start(ChessPieceColor::White);
+                    self.clock = Some(clock);
+                }
+                self.status = format!("{} - juegas con {}", self.status, color_name(color));
+            }
+            Some("move") => {
+                let Some(chess_move) = tokens.get(1).and_then(|text| parse_move(text)) else {
+                    self.status = format!("Jugada ilegible del anfitrión: {}", line);
+                    return None;
+                };
+
+                match play_move(&self.board, chess_move) {
+                    Ok((chess_move, new_board)) => {
+                        self.board = new_board;
+                        self.update_clock(&tokens[2..]);
+                        self.draw_offer = None;
+                        self.status = format!("Se jugó {}", chess_move.to_coordinates());
+                    }
+                    Err(message) => {
+                        self.status = format!(
+                            "El tablero quedó distinto del anfitrión: {}",
+                            message.get_message()
+                        )
+                    }
+                }
+            }
+            Some("reject") => {
+                self.status = format!("Jugada rechazada: {}", tokens[1..].join(" "));
+            }
+            Some("draw") => {
+                self.draw_offer = Some(opposite(self.color));
+                self.status = "El rival ofrece tablas, escribe \"tablas\" para aceptar".to_string();
+            }
+            Some("clock") => {
+                self.update_clock(&tokens[1..]);
+                self.status = self.clock_status();
+            }
+            Some("end") => {
+                let result = tokens
+                    .get(1)
+                    .and_then(|token| GameResult::from_token(token));
+                let reason = tokens
+                    .get(2)
+                    .and_then(|reason| EndReason::from_name(reason));
+                if let (Some(result), Some(reason)) = (result, reason) {
+                    return Some(GameEnd { result, reason });
+                }
+                self.status = format!("Final inválido del anfitrión: {}", line);
+            }
+            _ => self.status = format!("Mensaje desconocido del anfitrión: {}", line),
+        }
+
+        None
+    }
+
+    // El anfitrión aplica una jugada válida, propia o del invitado, y la anuncia
+    fn accept_move(&mut self, chess_move: Move, new_board: Chessboard) -> Option<GameEnd> {
+        let mover = self.board.player_turn;
+
+        if let Some(clock) = self.clock.as_mut() {
+            if !clock.press() {
+                return Some(GameEnd::win(opposite(mover), EndReason::Time));
+            }
+        }
+
+        self.board = new_board;
+        // Jugar equivale a rechazar las tablas que ofreció el rival
+        if self.draw_offer == Some(opposite(mover)) {
+            self.draw_offer = None;
+        }
+
+        let clock = self.clock_message();
+        self.send(&format!("move {} {}", chess_move.to_coordinates(), clock));
+        self.status = format!("Se jugó {}", chess_move.to_coordinates());
+
+        final_position(&self.board)
+    }
+
+    // Una oferta de tablas del bando indicado; si el rival ya había ofrecido, se aceptan
+    fn offer_draw(&mut self, color: ChessPieceColor) -> Option<GameEnd> {
+        if self.draw_offer == Some(opposite(color)) {
+            return Some(GameEnd::draw(EndReason::Agreement));
+        }
+
+        self.draw_offer = Some(color);
+        if color == self.color {
+            self.send("draw");
+            self.status = "Ofreciste tablas".to_string();
+        } else {
+            self.status = "El rival ofrece tablas, escribe \"tablas\" para aceptar".to_string();
+        }
+        None
+    }
+
+    // Solo el anfitrión decide cuándo se cayó la bandera
+    fn flag_fall(&self) -> Option<GameEnd> {
+        if self.role != Role::Host {
+            return None;
+        }
+
+        let flagged = self.clock.as_ref()?.flagged()?;
+        Some(GameEnd::win(opposite(flagged), EndReason::Time))
+    }
+
+    fn finish(&mut self, end: GameEnd) {
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop();
+        }
+
+        self.send(&format!(
+            "end {} {}",
+            end.result.to_token(),
+            end.reason.name()
+        ));
+        self.show_end(format!("Fin de la partida: {}", end.describe()));
+    }
+
+    fn update_clock(&mut self, tokens: &[&str]) {
+        let turn = self.board.player_turn;
+        let times: Vec<u64> = tokens.iter().filter_map(|time| time.parse().ok()).collect();
+
+        if let (Some(clock), [white, black]) = (self.clock.as_mut(), times.as_slice()) {
+            clock.set(
+                Duration::from_millis(*white),
+                Duration::from_millis(*black),
+                turn,
+            );
+        }
+    }
+
+    // Los tiempos en milisegundos como viajan por la red
+    fn clock_message(&self) -> String {
+        match &self.clock {
+            Some(clock) => format!(
+                "{} {}",
+                clock.remaining(ChessPieceColor::White).as_millis(),
+                clock.remaining(ChessPieceColor::Black).as_millis()
+            ),
+            None => String::new(),
+        }
+    }
+
+    fn clock_status(&self) -> String {
+        match &self.clock {
+            Some(clock) => format!(
+                "Reloj: blancas {}, negras {}",
+                format_time(clock.remaining(ChessPieceColor::White)),
+                format_time(clock.remaining(ChessPieceColor::Black))
+            ),
+            None => "La partida es sin reloj".to_string(),
+        }
+    }
+
+    fn panel(&self) -> Vec<String> {
+        let mut panel = vec![format!("Juegas con {}", color_name(self.color))];
+
+        if let Some(clock) = &self.clock {
+            for color in [ChessPieceColor::White, ChessPieceColor::Black] {
+                panel.push(format!(
+                    "Reloj {}: {}",
+                    color_name(color),
+                    format_time(clock.remaining(color))
+                ));
+            }
+        }
+
+        if self.draw_offer == Some(opposite(self.color)) {
+            panel.push("El rival ofrece tablas".to_string());
+        }
+
+        panel
+    }
+
+    fn show(&self) {
+        self.board
+            .print_board_with_panel(self.status.clone(), &self.panel());
+
+        if self.board.player_turn == self.color {
+            println!("{}", INPUT_PROMPT);
+        } else {
+            println!("Esperando la jugada del rival...");
+        }
+    }
+
+    // Muestra el tablero por última vez, sin pedir más jugadas
+    fn show_end(&self, message: String) {
+        self.board.print_board_with_panel(message, &self.panel());
+    }
+
+    fn send(&mut self, message: &str) {
+        let _ = writeln!(self.peer, "{}", message.trim_end()).and_then(|_| self.peer.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+
+    // Una sesión conectada por loopback y el otro extremo, que hace de rival
+    fn session(role: Role, color: ChessPieceColor) -> (LanSession, BufReader<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let session = LanSession {
+            role,
+            board: Chessboard::new(),
+            color,
+            clock: None,
+            peer: stream,
+            draw_offer: None,
+            status: String::new(),
+        };
+        (session, BufReader::new(peer))
+    }
+
+    fn receive(peer: &mut BufReader<TcpStream>) -> String {
+        let mut line = String::new();
+        peer.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    #[test]
+    fn host_rejects_illegal_guest_moves() {
+        let (mut host, mut guest) = session(Role::Host, ChessPieceColor::White);
+
+        assert!(host.host_receive("move e7e5").is_none());
+        assert_eq!(receive(&mut guest), "reject No es tu turno");
+
+        assert!(host.local_input("e2e4").is_none());
+        assert_eq!(receive(&mut guest), "move e2e4");
+
+        assert!(host.host_receive("move e7e4").is_none());
+        assert!(receive(&mut guest).starts_with("reject "));
+        assert!(host.host_receive("move xyz").is_none());
+        assert_eq!(receive(&mut guest), "reject Jugada ilegible: xyz");
+        assert!(host.board.player_turn == ChessPieceColor::Black);
+
+        assert!(host.host_receive("move e7e5").is_none());
+        assert_eq!(receive(&mut guest), "move e7e5");
+        assert!(host.board.player_turn == ChessPieceColor::White);
+    }
+
+    #[test]
+    fn draw_offers_are_accepted_or_dropped() {
+        let (mut host, mut guest) = session(Role::Host, ChessPieceColor::White);
+
+        // El anfitrión ofrece y el invitado la rechaza jugando
+        assert!(host.local_input("tablas").is_none());
+        assert_eq!(receive(&mut guest), "draw");
+        host.local_input("e2e4");
+        receive(&mut guest);
+        host.host_receive("move e7e5");
+        receive(&mut guest);
+        assert!(host.draw_offer.is_none());
+
+        // El invitado ofrece y el anfitrión acepta
+        assert!(host.host_receive("draw").is_none());
+        let end = host.local_input("tablas").unwrap();
+        assert!(end.result == GameResult::Draw);
+        assert!(end.reason == EndReason::Agreement);
+
+        host.finish(end);
+        assert_eq!(receive(&mut guest), "end 1/2-1/2 acuerdo");
+    }
+
+    #[test]
+    fn resignations_end_the_game() {
+        let (mut host, _guest) = session(Role::Host, ChessPieceColor::Black);
+        let end = host.host_receive("resign").unwrap();
+        assert!(end.result == GameResult::BlackWins);
+        assert!(end.reason == EndReason::Resignation);
+
+        let end = host.local_input("abandonar").unwrap();
+        assert!(end.result == GameResult::WhiteWins);
+
+        // El invitado solo avisa y espera el final que anuncia el anfitrión
+        let (mut guest, mut host) = session(Role::Guest, ChessPieceColor::White);
+        assert!(guest.local_input("abandonar").is_none());
+        assert_eq!(receive(&mut host), "resign");
+
+        let end = guest.guest_receive("end 0-1 abandono").unwrap();
+        assert!(end.result == GameResult::BlackWins);
+        assert!(end.reason == EndReason::Resignation);
+        assert!(guest.guest_receive("end 0-1 aburrimiento").is_none());
+    }
+}
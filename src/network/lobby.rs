@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use crate::pieces::piece_type::ChessPieceColor;
+use crate::IA::random::Random;
+
+use super::game::Clock;
+use super::json;
+
+// Límites de los controles de tiempo que se aceptan
+const MAX_MINUTES: f64 = 180.0;
+const MAX_INCREMENT: f64 = 60.0;
+
+// Búsqueda abierta de rival: el control de tiempo, el color que se prefiere y si es puntuable
+#[derive(Clone)]
+pub struct Seek {
+    pub id: u64,
+    pub connection: u64,
+    pub minutes: f64,
+    pub increment: f64,
+    pub color: Option<ChessPieceColor>,
+    pub rated: bool,
+}
+
+impl Seek {
+    /**
+     Arma una búsqueda, todavía sin publicar, comprobando el control de tiempo.
+     # Arguments
+     * `connection` - Quién busca rival.
+     * `minutes` - El tiempo de cada jugador; 0 para jugar sin reloj.
+     * `increment` - Los segundos que se suman por jugada.
+     * `color` - El color que se quiere, o `None` si da igual.
+     * `rated` - Si la partida es puntuable.
+     # Returns
+     La búsqueda, o el código y el texto del error si el control de tiempo no es válido.
+    */
+    pub fn new(
+        connection: u64,
+        minutes: f64,
+        increment: f64,
+        color: Option<ChessPieceColor>,
+        rated: bool,
+    ) -> Result<Seek, (&'static str, &'static str)> {
+        if !(0.0..=MAX_MINUTES).contains(&minutes) || !(0.0..=MAX_INCREMENT).contains(&increment) {
+            return Err((
+                "invalid_time_control",
+                "El tiempo va de 0 a 180 minutos y el incremento de 0 a 60 segundos",
+            ));
+        }
+        // Sin tiempo de base la partida es sin reloj, y entonces no tiene sentido el incremento
+        if minutes == 0.0 && increment > 0.0 {
+            return Err((
+                "invalid_time_control",
+                "Una partida sin reloj no puede tener incremento",
+            ));
+        }
+
+        Ok(Seek {
+            id: 0,
+            connection,
+            minutes,
+            increment,
+            color,
+            rated,
+        })
+    }
+
+    /**
+     Lee una búsqueda de un mensaje como {"minutes": 5, "increment": 3, "color": "white",
+     "rated": true}. Sin "color" se acepta cualquiera y sin "rated" la partida es amistosa.
+    */
+    pub fn from_json(message: &str, connection: u64) -> Result<Seek, (&'static str, &'static str)> {
+        let color = match json::string_field(message, "color").as_deref() {
+            Some("white") => Some(ChessPieceColor::White),
+            Some("black") => Some(ChessPieceColor::Black),
+            None | Some("any") => None,
+            Some(_) => return Err(("invalid_color", "El color tiene que ser white, black o any")),
+        };
+
+        Seek::new(
+            connection,
+            json::number_field(message, "minutes").unwrap_or(0.0),
+            json::number_field(message, "increment").unwrap_or(0.0),
+            color,
+            json::bool_field(message, "rated").unwrap_or(false),
+        )
+    }
+
+    // Dos búsquedas se emparejan con el mismo control de tiempo, tipo y colores compatibles
+    fn accepts(&self, other: &Seek) -> bool {
+        self.connection != other.connection
+            && self.minutes == other.minutes
+            && self.increment == other.increment
+            && self.rated == other.rated
+            && (self.color.is_none() || self.color != other.color)
+    }
+
+    // El reloj de la partida, o `None` si se juega sin reloj
+    pub fn clock(&self) -> Option<Clock> {
+        (self.minutes > 0.0).then(|| {
+            Clock::new(
+                Duration::from_secs_f64(self.minutes * 60.0),
+                Duration::from_secs_f64(self.increment),
+            )
+        })
+    }
+
+    fn json(&self) -> String {
+        let color = match self.color {
+            Some(ChessPieceColor::White) => "white",
+            Some(ChessPieceColor::Black) => "black",
+            None => "any",
+        };
+
+        json::object(&[
+            ("id", self.id.to_string()),
+            ("minutes", self.minutes.to_string()),
+            ("increment", self.increment.to_string()),
+            ("color", json::string(color)),
+            ("rated", self.rated.to_string()),
+        ])
+    }
+}
+
+// Dos conexiones emparejadas, con los colores ya repartidos y la búsqueda que se aceptó
+pub struct Pairing {
+    pub white: u64,
+    pub black: u64,
+    pub seek: Seek,
+}
+
+impl Pairing {
+    // Reparte los colores según lo que prefiere cada uno, o los sortea si a ninguno le importa
+    pub fn new(
+        connection: u64,
+        color: Option<ChessPieceColor>,
+        open: Seek,
+        random: &mut Random,
+    ) -> Pairing {
+        let first_white = match (color, open.color) {
+            (Some(ChessPieceColor::White), _) | (_, Some(ChessPieceColor::Black)) => true,
+            (Some(ChessPieceColor::Black), _) | (_, Some(ChessPieceColor::White)) => false,
+            (None, None) => random.range(2) == 0,
+        };
+        let (white, black) = if first_white {
+            (connection, open.connection)
+        } else {
+            (open.connection, connection)
+        };
+
+        Pairing {
+            white,
+            black,
+            seek: open,
+        }
+    }
+}
+
+pub enum SeekOutcome {
+    // Quedó publicada, con su número
+    Published(Seek),
+    Paired(Pairing),
+}
+
+#[derive(Default)]
+pub struct Lobby {
+    seeks: Vec<Seek>,
+    next_id: u64,
+}
+
+impl Lobby {
+    /**
+     Publica la búsqueda de una conexión, que reemplaza a la anterior, o la empareja con la más
+     antigua de otra conexión que sea compatible.
+     # Arguments
+     * `seek` - La búsqueda.
+     * `random` - Para sortear los colores si a ninguno le importa.
+     # Returns
+     La pareja si se encontró rival, o la búsqueda con su número si quedó publicada.
+    */
+    pub fn seek(&mut self, mut seek: Seek, random: &mut Random) -> SeekOutcome {
+        self.cancel(seek.connection);
+
+        match self.seeks.iter().position(|open| open.accepts(&seek)) {
+            Some(index) => {
+                let open = self.seeks.remove(index);
+                SeekOutcome::Paired(Pairing::new(seek.connection, seek.color, open, random))
+            }
+            None => {
+                self.next_id += 1;
+                seek.id = self.next_id;
+                self.seeks.push(seek.clone());
+                SeekOutcome::Published(seek)
+            }
+        }
+    }
+
+    // Acepta una búsqueda publicada por su número, si no es de la misma conexión
+    pub fn accept(&mut self, id: u64, connection: u64, random: &mut Random) -> Option<Pairing> {
+        let index = self
+            .seeks
+            .iter()
+            .position(|seek| seek.id == id && seek.connection != connection)?;
+        let open = self.seeks.remove(index);
+        self.cancel(connection);
+        Some(Pairing::new(connection, None, open, random))
+    }
+
+    pub fn seeks(&self) -> &[Seek] {
+        &self.seeks
+    }
+
+    // Retira la búsqueda de la conexión; devuelve si había una
+    pub fn cancel(&mut self, connection: u64) -> bool {
+        let before = self.seeks.len();
+        self.seeks.retain(|seek| seek.connection != connection);
+        self.seeks.len() != before
+    }
+
+    pub fn json(&self) -> String {
+        let seeks: Vec<String> = self.seeks.iter().map(Seek::json).collect();
+        json::object(&[
+            ("type", json::string("seeks")),
+            ("seeks", format!("[{}]", seeks.join(","))),
+        ])
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::BufRead;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+pub mod correspondence;
+pub mod game;
+pub mod http;
+pub mod ics;
+pub mod json;
+pub mod lan;
+pub mod lobby;
+pub mod rooms;
+pub mod store;
+pub mod websocket;
+
+/**
+ Lee líneas en otro hilo y las envía como eventos, para atender varias fuentes a la vez.
+ # Arguments
+ * `reader` - De donde se leen las líneas, por ejemplo la entrada estándar o una conexión.
+ * `sender` - Donde se envía cada línea.
+ * `event` - Convierte cada línea en el evento que se envía.
+ * `closed` - El evento que se envía al terminar la entrada o cortarse la conexión.
+*/
+pub fn spawn_line_reader<R, T>(reader: R, sender: Sender<T>, event: fn(String) -> T, closed: T)
+where
+    R: BufRead + Send + 'static,
+    T: Send + 'static,
+{
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(event(line)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(closed);
+    });
+}
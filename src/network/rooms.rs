@@ -0,0 +1,1349 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::ChessPieceColor;
+use crate::IA::random::Random;
+
+use super::game::{color_index, opposite, Clock, EndReason, GameEnd, MoveError, ServerGame};
+use super::json;
+use super::lobby::{Lobby, Pairing, Seek, SeekOutcome};
+use super::spawn_line_reader;
+use super::store::{now, valid_player_name, Store, StoredClock, StoredGame};
+use super::websocket::{WebSocket, WebSocketWriter};
+
+pub const DEFAULT_ROOMS_PORT: u16 = 9090;
+
+// Un jugador desconectado por más tiempo que esto pierde la partida
+const ABANDON_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Las salas vacías se borran después de este tiempo
+const EMPTY_ROOM_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Cada cuánto se revisan los relojes y las salas abandonadas
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Player(ChessPieceColor),
+    Spectator,
+}
+
+impl Role {
+    fn name(self) -> &'static str {
+        match self {
+            Role::Player(ChessPieceColor::White) => "white",
+            Role::Player(ChessPieceColor::Black) => "black",
+            Role::Spectator => "spectator",
+        }
+    }
+}
+
+// Lugar de un jugador en una sala; la clave le permite recuperarlo si se corta la conexión
+struct Seat {
+    token: String,
+    // El nombre con el que se presentó, si dio uno
+    player: Option<String>,
+    connection: Option<u64>,
+    disconnected_since: Option<Instant>,
+}
+
+struct Room {
+    // Identificador con el que se guarda la partida
+    id: u64,
+    started: u64,
+    game: ServerGame,
+    // Blancas y negras, en el orden de `color_index`
+    seats: [Option<Seat>; 2],
+    spectators: Vec<u64>,
+    clock: Option<Clock>,
+    rated: bool,
+    // Desde cuándo no hay nadie conectado a la sala
+    empty_since: Option<Instant>,
+    // Cuándo terminó; hasta entonces el resultado no está anotado en la ficha de los jugadores
+    finished: Option<u64>,
+    // Partida retomada al arrancar: el reloj y el abandono esperan a que vuelvan los dos jugadores
+    paused: bool,
+}
+
+impl Room {
+    fn new(id: u64, clock: Option<Clock>, rated: bool) -> Room {
+        Room {
+            id,
+            started: now(),
+            game: ServerGame::new(Chessboard::new()),
+            seats: [None, None],
+            spectators: Vec::new(),
+            clock,
+            rated,
+            empty_since: None,
+            finished: None,
+            paused: false,
+        }
+    }
+
+    /**
+     Vuelve a armar una partida guardada que no había terminado, repitiendo sus jugadas. Los dos
+     jugadores quedan desconectados hasta que vuelvan con su clave, y el reloj queda detenido con
+     los tiempos guardados hasta que estén los dos.
+     # Returns
+     La sala, o un mensaje si alguna jugada guardada no es legal.
+    */
+    fn restore(stored: StoredGame) -> Result<Room, String> {
+        let mut game = ServerGame::new(Chessboard::new());
+        for chess_move in &stored.moves {
+            game.play(chess_move).map_err(|error| error.message())?;
+        }
+
+        let clock = stored.clock.map(|times| {
+            let mut clock = Clock::new(Duration::ZERO, times.increment);
+            clock.set(times.white, times.black, game.board.player_turn);
+            clock.stop();
+            clock
+        });
+
+        let [white, black] = [0, 1].map(|index| {
+            stored.tokens[index].clone().map(|token| Seat {
+                token,
+                player: stored.players[index].clone(),
+                connection: None,
+                // Nadie pudo conectarse mientras el servidor estaba parado: no cuenta como abandono
+                disconnected_since: None,
+            })
+        });
+
+        Ok(Room {
+            id: stored.id,
+            started: stored.started,
+            game,
+            seats: [white, black],
+            spectators: Vec::new(),
+            clock,
+            rated: stored.rated,
+            empty_since: None,
+            finished: None,
+            paused: true,
+        })
+    }
+
+    // Pone en marcha una partida retomada cuando ya volvieron los dos jugadores
+    fn resume(&mut self) {
+        let seated = self
+            .seats
+            .iter()
+            .all(|seat| seat.as_ref().is_some_and(|seat| seat.connection.is_some()));
+        if !self.paused || !seated {
+            return;
+        }
+
+        self.paused = false;
+        if let Some(clock) = self.clock.as_mut().filter(|_| self.game.end.is_none()) {
+            clock.start(self.game.board.player_turn);
+        }
+    }
+
+    fn stored(&self, name: &str) -> StoredGame {
+        let seat_field = |field: fn(&Seat) -> Option<String>| {
+            [0, 1].map(|index| self.seats[index].as_ref().and_then(field))
+        };
+
+        StoredGame {
+            id: self.id,
+            room: name.to_string(),
+            started: self.started,
+            finished: self.finished,
+            rated: self.rated,
+            players: seat_field(|seat| seat.player.clone()),
+            tokens: seat_field(|seat| Some(seat.token.clone())),
+            clock: self.clock.as_ref().map(|clock| StoredClock {
+                white: clock.remaining(ChessPieceColor::White),
+                black: clock.remaining(ChessPieceColor::Black),
+                increment: clock.increment(),
+            }),
+            end: self.game.end,
+            moves: self.game.moves.clone(),
+        }
+    }
+
+    // Termina la partida y detiene el reloj
+    fn finish(&mut self, end: GameEnd) {
+        self.game.finish(end);
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop();
+        }
+    }
+
+    // Si al jugador en turno se le acabó el tiempo pierde la partida; devuelve si pasó
+    fn check_flag(&mut self) -> bool {
+        let flagged = self.clock.as_ref().and_then(Clock::flagged);
+        match flagged {
+            Some(color) if self.game.end.is_none() => {
+                self.finish(GameEnd::win(opposite(color), EndReason::Time));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Le pasa el reloj al rival, o lo detiene si la jugada terminó la partida
+    fn press_clock(&mut self) {
+        if let Some(clock) = self.clock.as_mut() {
+            if self.game.end.is_some() {
+                clock.stop();
+            } else {
+                clock.press();
+            }
+        }
+    }
+
+    fn new_seat(token: String, player: Option<String>, connection: u64) -> Option<Seat> {
+        Some(Seat {
+            token,
+            player,
+            connection: Some(connection),
+            disconnected_since: None,
+        })
+    }
+}
+
+struct Connection {
+    // Los mensajes se escriben en un hilo de la conexión, fuera del candado del servidor, para
+    // que un cliente que no lee no frene a las demás salas
+    outgoing: mpsc::Sender<String>,
+    place: Option<(String, Role)>,
+    player: Option<String>,
+}
+
+struct RoomServer {
+    rooms: HashMap<String, Room>,
+    connections: HashMap<u64, Connection>,
+    next_connection: u64,
+    lobby: Lobby,
+    store: Store,
+    random: Random,
+}
+
+/**
+ Servidor WebSocket de partidas en tiempo real. Cada mensaje es un objeto JSON con un campo "type":
+ * `{"type": "join", "room": "sala", "role": "player" | "spectator", "token": "..."}`: entra a una
+   sala, que se crea si no existe. Con la clave que se recibió al entrar se recupera el lugar.
+ * `{"type": "move", "move": "e2e4"}`: juega si es el turno del jugador.
+ * `{"type": "resign"}`: abandona la partida.
+ * `{"type": "state"}`: pide el estado completo.
+ * `{"type": "seek", "minutes": 5, "increment": 3, "color": "any", "rated": false}`: busca rival
+   con ese control de tiempo. Si hay una búsqueda compatible se crea la partida y los dos reciben
+   "joined"; si no, la búsqueda queda publicada hasta que se cancela con "cancel_seek".
+ * `{"type": "seeks"}` y `{"type": "games"}`: las búsquedas abiertas y las partidas en curso.
+ * `{"type": "history", "player": "ana", "date": "2026-10-19"}`: las partidas terminadas de un
+   jugador o de un día, y `{"type": "player", "name": "ana"}`: la ficha de un jugador.
+
+ Con "player" en join o seek el jugador se presenta con un nombre, que hace falta para las
+ partidas puntuables. Las partidas y las fichas se guardan en la carpeta de datos, y al arrancar
+ se retoman las partidas que no habían terminado.
+
+ El servidor responde con "joined", "error" y "state", que se envía a los dos jugadores y a todos
+ los espectadores de la sala en cada cambio. Quienes no están en una sala reciben "seeks" cada vez
+ que cambian las búsquedas. Un jugador que no vuelve en un minuto pierde la partida, o son tablas
+ si no vuelve ninguno de los dos, y las salas vacías se borran a los cinco minutos.
+ # Arguments
+ * `port` - El puerto en el que se atiende.
+ * `directory` - La carpeta donde se guardan las partidas y los jugadores.
+*/
+pub fn serve_rooms(port: u16, directory: &str) {
+    let store = match Store::open(directory) {
+        Ok(store) => store,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let listener = match TcpListener::bind(("0.0.0.0", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!("No se pudo abrir el puerto {}: {}", port, error);
+            return;
+        }
+    };
+
+    let mut server = RoomServer {
+        rooms: HashMap::new(),
+        connections: HashMap::new(),
+        next_connection: 0,
+        lobby: Lobby::default(),
+        store,
+        random: Random::from_time(),
+    };
+    server.restore_games();
+
+    println!(
+        "Salas de partidas en ws://0.0.0.0:{}/, con los datos en {}",
+        port, directory
+    );
+    let server = Arc::new(Mutex::new(server));
+
+    let maintenance = Arc::clone(&server);
+    thread::spawn(move || loop {
+        thread::sleep(MAINTENANCE_INTERVAL);
+        maintenance.lock().unwrap().tick();
+    });
+
+    accept_connections(listener, server);
+}
+
+// Atiende cada conexión en su propio hilo, hasta que se cierra el puerto
+fn accept_connections(listener: TcpListener, server: Arc<Mutex<RoomServer>>) {
+    for stream in listener.incoming().flatten() {
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            let Ok(mut socket) = WebSocket::accept(stream) else {
+                return;
+            };
+            let Ok(writer) = socket.writer() else {
+                return;
+            };
+
+            let id = server.lock().unwrap().connect(writer);
+            while let Ok(Some(message)) = socket.read() {
+                server.lock().unwrap().handle(id, &message);
+            }
+            server.lock().unwrap().disconnect(id);
+        });
+    }
+}
+
+impl RoomServer {
+    fn connect(&mut self, mut writer: WebSocketWriter) -> u64 {
+        let (outgoing, messages) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for message in messages {
+                if writer.send(&message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        self.next_connection += 1;
+        let id = self.next_connection;
+        self.connections.insert(
+            id,
+            Connection {
+                outgoing,
+                place: None,
+                player: None,
+            },
+        );
+        id
+    }
+
+    fn disconnect(&mut self, id: u64) {
+        self.leave(id);
+        self.connections.remove(&id);
+        if self.lobby.cancel(id) {
+            self.broadcast_lobby();
+        }
+    }
+
+    fn handle(&mut self, id: u64, message: &str) {
+        match json::string_field(message, "type").as_deref() {
+            Some("join") => self.join(id, message),
+            Some("move") => self.play(id, message),
+            Some("resign") => self.resign(id),
+            Some("seek") => self.seek(id, message),
+            Some("cancel_seek") => {
+                if self.lobby.cancel(id) {
+                    self.broadcast_lobby();
+                }
+            }
+            Some("seeks") => {
+                let seeks = self.lobby.json();
+                self.send(id, &seeks);
+            }
+            Some("games") => {
+                let games = self.games_json();
+                self.send(id, &games);
+            }
+            Some("history") => self.history(id, message),
+            Some("player") => {
+                let name = json::string_field(message, "name").unwrap_or_default();
+                let reply = match self.store.player(&name) {
+                    Some(record) => json::object(&[
+                        ("type", json::string("player")),
+                        ("player", record.json()),
+                    ]),
+                    None => error_json(
+                        "player_not_found",
+                        &format!("No hay partidas del jugador \"{}\"", name),
+                    ),
+                };
+                self.send(id, &reply);
+            }
+            Some("state") => match self.place(id) {
+                Some((room, _)) => {
+                    let state = self.state_json(&room);
+                    self.send(id, &state);
+                }
+                None => self.send_not_in_room(id),
+            },
+            _ => self.send_error(
+                id,
+                "unknown_message",
+                "Tipo de mensaje desconocido; se esperaba join, move, resign, state, seek, cancel_seek, seeks, games, history o player",
+            ),
+        }
+    }
+
+    fn join(&mut self, id: u64, message: &str) {
+        let Some(name) = json::string_field(message, "room").filter(|name| !name.is_empty()) else {
+            self.send_error(
+                id,
+                "missing_room",
+                "Falta el campo \"room\" con el nombre de la sala",
+            );
+            return;
+        };
+        // El nombre se guarda en una línea del archivo de la partida
+        if name.chars().count() > 40 || name.chars().any(char::is_control) {
+            self.send_error(
+                id,
+                "invalid_room",
+                "El nombre de la sala tiene hasta 40 caracteres, sin saltos de línea",
+            );
+            return;
+        }
+        if !self.identify(id, message) {
+            return;
+        }
+        let spectator = json::string_field(message, "role").as_deref() == Some("spectator");
+        let token = json::string_field(message, "token");
+
+        self.leave(id);
+        let new_token = format!("{:016x}", self.random.next_u64());
+        let player = self
+            .connections
+            .get(&id)
+            .and_then(|connection| connection.player.clone());
+        let store = &mut self.store;
+        let room = self
+            .rooms
+            .entry(name.clone())
+            .or_insert_with(|| Room::new(store.next_id(), None, false));
+
+        let seat = if spectator {
+            room.spectators.push(id);
+            Ok((Role::Spectator, None))
+        } else if let Some(token) = token {
+            // Recupera el lugar con la clave, aunque otra conexión lo esté usando
+            match room.seats.iter_mut().enumerate().find_map(|(index, seat)| {
+                seat.as_mut()
+                    .filter(|seat| seat.token == token)
+                    .map(|seat| (index, seat))
+            }) {
+                Some((index, seat)) => {
+                    seat.disconnected_since = None;
+                    if let Some(previous) = seat.connection.replace(id) {
+                        if let Some(connection) = self.connections.get_mut(&previous) {
+                            connection.place = None;
+                            let _ = connection.outgoing.send(error_json(
+                                "seat_taken",
+                                "Otra conexión entró con la clave de este jugador",
+                            ));
+                        }
+                    }
+                    Ok((Role::Player(seat_color(index)), Some(token)))
+                }
+                None => Err((
+                    "invalid_token",
+                    "La clave no corresponde a ningún jugador de la sala",
+                )),
+            }
+        } else {
+            match room.seats.iter().position(Option::is_none) {
+                Some(index) => {
+                    room.seats[index] = Room::new_seat(new_token.clone(), player, id);
+                    Ok((Role::Player(seat_color(index)), Some(new_token)))
+                }
+                None => Err((
+                    "room_full",
+                    "La sala ya tiene dos jugadores; se puede entrar como espectador",
+                )),
+            }
+        };
+
+        room.resume();
+
+        let (role, token) = match seat {
+            Ok(seat) => seat,
+            Err((code, message)) => {
+                self.send_error(id, code, message);
+                return;
+            }
+        };
+
+        self.enter(id, &name, role, token.as_deref());
+        self.broadcast(&name);
+    }
+
+    // Anota la sala de la conexión y le avisa con qué papel entró
+    fn enter(&mut self, id: u64, name: &str, role: Role, token: Option<&str>) {
+        if let Some(connection) = self.connections.get_mut(&id) {
+            connection.place = Some((name.to_string(), role));
+        }
+
+        let mut fields = vec![
+            ("type", json::string("joined")),
+            ("room", json::string(name)),
+            ("role", json::string(role.name())),
+        ];
+        if let Some(token) = token {
+            fields.push(("token", json::string(token)));
+        }
+        self.send(id, &json::object(&fields));
+    }
+
+    // Anota el nombre de jugador que trae el mensaje; avisa y devuelve `false` si no es válido
+    fn identify(&mut self, id: u64, message: &str) -> bool {
+        let Some(player) = json::string_field(message, "player") else {
+            return true;
+        };
+
+        if !valid_player_name(&player) {
+            self.send_error(
+                id,
+                "invalid_player",
+                "El nombre de jugador es una palabra de hasta 20 letras, números, - o _",
+            );
+            return false;
+        }
+        if let Some(connection) = self.connections.get_mut(&id) {
+            connection.player = Some(player);
+        }
+        true
+    }
+
+    fn seek(&mut self, id: u64, message: &str) {
+        if !self.identify(id, message) {
+            return;
+        }
+        let anonymous = self
+            .connections
+            .get(&id)
+            .is_none_or(|connection| connection.player.is_none());
+        if anonymous && json::bool_field(message, "rated") == Some(true) {
+            self.send_error(
+                id,
+                "player_required",
+                "Para jugar una partida puntuable hay que presentarse con \"player\"",
+            );
+            return;
+        }
+
+        let seek = match Seek::from_json(message, id) {
+            Ok(seek) => seek,
+            Err((code, message)) => {
+                self.send_error(id, code, message);
+                return;
+            }
+        };
+
+        match self.lobby.seek(seek, &mut self.random) {
+            SeekOutcome::Paired(pairing) => self.start_game(pairing),
+            SeekOutcome::Published(_) => {
+                // Quien está en una sala no recibe las búsquedas, pero tiene que ver la suya
+                if self.place(id).is_some() {
+                    let seeks = self.lobby.json();
+                    self.send(id, &seeks);
+                }
+            }
+        }
+        self.broadcast_lobby();
+    }
+
+    // Crea la sala de una pareja del lobby y pone en marcha el reloj de las blancas
+    fn start_game(&mut self, pairing: Pairing) {
+        // Una sala creada a mano puede tener el nombre que le tocaría a la partida
+        let (id, name) = loop {
+            let id = self.store.next_id();
+            let name = format!("partida-{}", id);
+            if !self.rooms.contains_key(&name) {
+                break (id, name);
+            }
+        };
+        self.leave(pairing.white);
+        self.leave(pairing.black);
+
+        let mut room = Room::new(id, pairing.seek.clock(), pairing.seek.rated);
+        let mut tokens = Vec::new();
+        for (index, connection) in [pairing.white, pairing.black].into_iter().enumerate() {
+            let token = format!("{:016x}", self.random.next_u64());
+            let player = self
+                .connections
+                .get(&connection)
+                .and_then(|connection| connection.player.clone());
+            room.seats[index] = Room::new_seat(token.clone(), player, connection);
+            tokens.push((connection, Role::Player(seat_color(index)), token));
+        }
+        if let Some(clock) = room.clock.as_mut() {
+            clock.start(ChessPieceColor::White);
+        }
+        self.rooms.insert(name.clone(), room);
+
+        for (connection, role, token) in tokens {
+            self.enter(connection, &name, role, Some(&token));
+        }
+        self.broadcast(&name);
+    }
+
+    fn play(&mut self, id: u64, message: &str) {
+        let Some((name, color)) = self.player_place(id) else {
+            return;
+        };
+        let Some(text) = json::string_field(message, "move") else {
+            self.send_error(
+                id,
+                "missing_move",
+                "Falta el campo \"move\", por ejemplo {\"move\": \"e2e4\"}",
+            );
+            return;
+        };
+
+        let room = self.rooms.get_mut(&name).unwrap();
+        let flagged = room.check_flag();
+        let result = if room.game.end.is_none() && room.game.board.player_turn != color {
+            Err(("not_your_turn", "No es tu turno".to_string()))
+        } else {
+            room.game
+                .play(&text)
+                .map_err(|error| (error.code(), error.message()))
+        };
+        if result.is_ok() {
+            room.press_clock();
+        }
+
+        if let Err((code, message)) = result {
+            self.send_error(id, code, &message);
+            if !flagged {
+                return;
+            }
+        }
+        self.broadcast(&name);
+    }
+
+    fn resign(&mut self, id: u64) {
+        let Some((name, color)) = self.player_place(id) else {
+            return;
+        };
+
+        let room = self.rooms.get_mut(&name).unwrap();
+        if room.game.end.is_some() {
+            let error = MoveError::GameOver;
+            self.send_error(id, error.code(), &error.message());
+            return;
+        }
+
+        room.finish(GameEnd::win(opposite(color), EndReason::Resignation));
+        self.broadcast(&name);
+    }
+
+    // Saca la conexión de su sala; el lugar del jugador queda guardado para que pueda volver
+    fn leave(&mut self, id: u64) {
+        let Some((name, _)) = self
+            .connections
+            .get_mut(&id)
+            .and_then(|connection| connection.place.take())
+        else {
+            return;
+        };
+
+        if let Some(room) = self.rooms.get_mut(&name) {
+            for seat in room.seats.iter_mut().flatten() {
+                if seat.connection == Some(id) {
+                    seat.connection = None;
+                    seat.disconnected_since = Some(Instant::now());
+                }
+            }
+            room.spectators.retain(|spectator| *spectator != id);
+            self.broadcast(&name);
+        }
+    }
+
+    fn place(&self, id: u64) -> Option<(String, Role)> {
+        self.connections.get(&id)?.place.clone()
+    }
+
+    // La sala y el color de un jugador, o avisa por qué no puede actuar como jugador
+    fn player_place(&mut self, id: u64) -> Option<(String, ChessPieceColor)> {
+        match self.place(id) {
+            Some((name, Role::Player(color))) => Some((name, color)),
+            Some((_, Role::Spectator)) => {
+                self.send_error(id, "spectator", "Los espectadores no pueden jugar");
+                None
+            }
+            None => {
+                self.send_not_in_room(id);
+                None
+            }
+        }
+    }
+
+    // Estado completo de la partida y de quiénes están en la sala
+    fn state_json(&self, name: &str) -> String {
+        let room = &self.rooms[name];
+        let seat_status = |color: ChessPieceColor| match &room.seats[color_index(color)] {
+            None => "empty",
+            Some(Seat {
+                connection: Some(_),
+                ..
+            }) => "connected",
+            Some(Seat {
+                connection: None, ..
+            }) => "disconnected",
+        };
+
+        let mut fields = vec![
+            ("type", json::string("state")),
+            ("room", json::string(name)),
+        ];
+        fields.extend(room.game.json_fields());
+        if let Some(last_move) = room.game.moves.last() {
+            fields.push(("last_move", json::string(last_move)));
+        }
+        fields.push(("white", json::string(seat_status(ChessPieceColor::White))));
+        fields.push(("black", json::string(seat_status(ChessPieceColor::Black))));
+        for (key, seat) in [
+            ("white_player", &room.seats[0]),
+            ("black_player", &room.seats[1]),
+        ] {
+            if let Some(player) = seat.as_ref().and_then(|seat| seat.player.as_deref()) {
+                fields.push((key, json::string(player)));
+            }
+        }
+        fields.push(("spectators", room.spectators.len().to_string()));
+        fields.push(("rated", room.rated.to_string()));
+        if let Some(clock) = &room.clock {
+            for (key, color) in [
+                ("white_time_ms", ChessPieceColor::White),
+                ("black_time_ms", ChessPieceColor::Black),
+            ] {
+                fields.push((key, clock.remaining(color).as_millis().to_string()));
+            }
+        }
+
+        json::object(&fields)
+    }
+
+    // Resumen de las salas para elegir cuál mirar
+    fn games_json(&self) -> String {
+        let mut names: Vec<&String> = self.rooms.keys().collect();
+        names.sort();
+
+        let games: Vec<String> = names
+            .into_iter()
+            .map(|name| {
+                let game = &self.rooms[name].game;
+                json::object(&[
+                    ("room", json::string(name)),
+                    ("status", json::string(game.status())),
+                    (
+                        "result",
+                        json::string(game.end.map_or("*", |end| end.result.to_token())),
+                    ),
+                    ("moves", game.moves.len().to_string()),
+                    ("spectators", self.rooms[name].spectators.len().to_string()),
+                ])
+            })
+            .collect();
+
+        json::object(&[
+            ("type", json::string("games")),
+            ("games", format!("[{}]", games.join(","))),
+        ])
+    }
+
+    /**
+     Revisa todas las salas: termina las partidas en las que se acabó el tiempo o un jugador no
+     volvió a tiempo, y borra las salas que quedaron vacías.
+    */
+    fn tick(&mut self) {
+        let now = Instant::now();
+
+        let store = &self.store;
+        self.rooms.retain(|_, room| {
+            let occupied = !room.spectators.is_empty()
+                || room
+                    .seats
+                    .iter()
+                    .flatten()
+                    .any(|seat| seat.connection.is_some());
+            if occupied {
+                room.empty_since = None;
+                return true;
+            }
+            if now.duration_since(*room.empty_since.get_or_insert(now)) < EMPTY_ROOM_TIMEOUT {
+                return true;
+            }
+            // Una partida que se deja sin terminar nunca tuvo a los dos jugadores: no se guarda
+            if room.game.end.is_none() {
+                store.remove_game(room.id);
+            }
+            false
+        });
+
+        let mut finished = Vec::new();
+        for (name, room) in self.rooms.iter_mut() {
+            if room.check_flag() {
+                finished.push(name.clone());
+                continue;
+            }
+            // Solo se pierde por abandono si hay rival sentado, la partida sigue y no espera a que
+            // vuelvan los jugadores de una partida retomada
+            if room.paused || room.game.end.is_some() || room.seats.iter().any(Option::is_none) {
+                continue;
+            }
+
+            let abandoned: Vec<usize> = (0..2)
+                .filter(|index| {
+                    room.seats[*index]
+                        .as_ref()
+                        .and_then(|seat| seat.disconnected_since)
+                        .is_some_and(|since| now.duration_since(since) >= ABANDON_TIMEOUT)
+                })
+                .collect();
+            // Si se fueron los dos nadie gana: la partida termina en tablas
+            let end = match abandoned[..] {
+                [index] => GameEnd::win(opposite(seat_color(index)), EndReason::Disconnection),
+                [_, _] => GameEnd::draw(EndReason::Disconnection),
+                _ => continue,
+            };
+            room.finish(end);
+            finished.push(name.clone());
+        }
+
+        for name in finished {
+            self.broadcast(&name);
+        }
+    }
+
+    // Envía el estado a los jugadores conectados y a los espectadores de la sala, y lo guarda
+    fn broadcast(&mut self, name: &str) {
+        self.save(name);
+        let state = self.state_json(name);
+        let room = &self.rooms[name];
+        let recipients: Vec<u64> = room
+            .seats
+            .iter()
+            .flatten()
+            .filter_map(|seat| seat.connection)
+            .chain(room.spectators.iter().copied())
+            .collect();
+
+        for id in recipients {
+            self.send(id, &state);
+        }
+    }
+
+    // Guarda la partida de la sala, y al terminar anota el resultado en la ficha de los jugadores
+    fn save(&mut self, name: &str) {
+        let room = self.rooms.get_mut(name).unwrap();
+        let just_finished = room.game.end.is_some() && room.finished.is_none();
+        if just_finished {
+            room.finished = Some(now());
+        }
+
+        let stored = room.stored(name);
+        let mut result = self.store.save_game(&stored);
+        if just_finished {
+            result = result.and_then(|_| self.store.record_result(&stored));
+        }
+        if let Err(error) = result {
+            println!("{}", error);
+        }
+    }
+
+    // Retoma las partidas guardadas que no terminaron
+    fn restore_games(&mut self) {
+        for stored in self.store.load_games() {
+            if stored.end.is_some() || self.rooms.contains_key(&stored.room) {
+                continue;
+            }
+
+            let (id, name) = (stored.id, stored.room.clone());
+            match Room::restore(stored) {
+                Ok(room) => {
+                    println!("Se retoma la partida {} en la sala {}", id, name);
+                    self.rooms.insert(name, room);
+                }
+                Err(error) => println!("No se pudo retomar la partida {}: {}", id, error),
+            }
+        }
+    }
+
+    // Las partidas terminadas de un jugador, de un día (AAAA-MM-DD) o de los dos
+    fn history(&mut self, id: u64, message: &str) {
+        let player = json::string_field(message, "player");
+        let day = json::string_field(message, "date");
+        let games: Vec<String> = self
+            .store
+            .history(player.as_deref(), day.as_deref())
+            .iter()
+            .map(StoredGame::json)
+            .collect();
+
+        let reply = json::object(&[
+            ("type", json::string("history")),
+            ("games", format!("[{}]", games.join(","))),
+        ]);
+        self.send(id, &reply);
+    }
+
+    // Envía las búsquedas abiertas a las conexiones que no están en una sala
+    fn broadcast_lobby(&mut self) {
+        let seeks = self.lobby.json();
+        for connection in self.connections.values_mut() {
+            if connection.place.is_none() {
+                let _ = connection.outgoing.send(seeks.clone());
+            }
+        }
+    }
+
+    // Los errores de escritura se ignoran: el hilo que lee la conexión detecta el corte
+    fn send(&mut self, id: u64, message: &str) {
+        if let Some(connection) = self.connections.get(&id) {
+            let _ = connection.outgoing.send(message.to_string());
+        }
+    }
+
+    fn send_error(&mut self, id: u64, code: &str, message: &str) {
+        self.send(id, &error_json(code, message));
+    }
+
+    fn send_not_in_room(&mut self, id: u64) {
+        self.send_error(
+            id,
+            "not_in_room",
+            "Primero hay que entrar a una sala con join",
+        );
+    }
+}
+
+fn seat_color(index: usize) -> ChessPieceColor {
+    if index == 0 {
+        ChessPieceColor::White
+    } else {
+        ChessPieceColor::Black
+    }
+}
+
+fn error_json(code: &str, message: &str) -> String {
+    json::object(&[
+        ("type", json::string("error")),
+        ("code", json::string(code)),
+        ("message", json::string(message)),
+    ])
+}
+
+enum ClientEvent {
+    Input(String),
+    InputClosed,
+    Message(String),
+    Closed,
+}
+
+/**
+ Cliente de salas sin tablero, para probar el servidor o manejarlo desde otro programa. Cada línea
+ de la entrada es una jugada o una orden (ver `client_message`), y cada mensaje del servidor se
+ muestra tal cual en una línea.
+ # Arguments
+ * `address` - La dirección del servidor, con el puerto si no es el de siempre.
+ * `room` - El nombre de la sala, o `None` para quedarse en el lobby.
+ * `spectator` - Si se entra como espectador en lugar de jugador.
+ * `token` - La clave que dio el servidor, para recuperar el lugar después de un corte.
+ * `player` - El nombre con el que se presenta, que se envía al entrar y al buscar rival.
+*/
+pub fn run_client(
+    address: &str,
+    room: Option<&str>,
+    spectator: bool,
+    token: Option<&str>,
+    player: Option<&str>,
+) {
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_ROOMS_PORT)
+    };
+
+    let mut socket = match WebSocket::connect(&address, "/") {
+        Ok(socket) => socket,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let mut writer = match socket.writer() {
+        Ok(writer) => writer,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    if let Some(room) = room {
+        let mut join = vec![
+            ("type", json::string("join")),
+            ("room", json::string(room)),
+            (
+                "role",
+                json::string(if spectator { "spectator" } else { "player" }),
+            ),
+        ];
+        if let Some(token) = token {
+            join.push(("token", json::string(token)));
+        }
+        if let Some(player) = player {
+            join.push(("player", json::string(player)));
+        }
+        if let Err(error) = writer.send(&json::object(&join)) {
+            println!("{}", error);
+            return;
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    spawn_line_reader(
+        BufReader::new(std::io::stdin()),
+        sender.clone(),
+        ClientEvent::Input,
+        ClientEvent::InputClosed,
+    );
+    thread::spawn(move || {
+        while let Ok(Some(message)) = socket.read() {
+            if sender.send(ClientEvent::Message(message)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(ClientEvent::Closed);
+    });
+
+    for event in receiver {
+        match event {
+            ClientEvent::Input(line) => {
+                let message = match line.trim() {
+                    "" => continue,
+                    "salir" => break,
+                    text => match client_message(text, player) {
+                        Ok(message) => message,
+                        Err(error) => {
+                            println!("{}", error);
+                            continue;
+                        }
+                    },
+                };
+                if let Err(error) = writer.send(&message) {
+                    println!("{}", error);
+                    return;
+                }
+            }
+            ClientEvent::InputClosed => break,
+            ClientEvent::Message(message) => println!("{}", message),
+            ClientEvent::Closed => {
+                println!("El servidor cerró la conexión");
+                return;
+            }
+        }
+    }
+
+    writer.close();
+}
+
+/**
+ Traduce una línea del cliente al mensaje del servidor:
+ * `abandonar`, `estado`, `cancelar`, `busquedas` y `partidas`.
+ * `buscar <minutos> [incremento] [blanco|negro] [puntuable]`: busca rival en el lobby.
+ * `mirar <sala>`: entra a una sala como espectador.
+ * `historial [jugador] [AAAA-MM-DD]` y `ficha <jugador>`: consultan las partidas terminadas.
+ * Cualquier otra cosa es una jugada, por ejemplo "e2e4".
+ # Arguments
+ * `line` - La línea que se escribió.
+ * `player` - El nombre del jugador, que acompaña a las búsquedas.
+ # Returns
+ El mensaje en JSON, o cómo se usa la orden si le faltan datos.
+*/
+fn client_message(line: &str, player: Option<&str>) -> Result<String, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let message_type = |kind: &str| Ok(json::object(&[("type", json::string(kind))]));
+
+    match command {
+        "abandonar" => message_type("resign"),
+        "estado" => message_type("state"),
+        "cancelar" => message_type("cancel_seek"),
+        "busquedas" => message_type("seeks"),
+        "partidas" => message_type("games"),
+        "buscar" => {
+            let usage = "Uso: buscar <minutos> [incremento] [blanco|negro] [puntuable]";
+            let mut fields = vec![("type", json::string("seek"))];
+            let mut numbers = Vec::new();
+
+            for word in words {
+                match word {
+                    "blanco" => fields.push(("color", json::string("white"))),
+                    "negro" => fields.push(("color", json::string("black"))),
+                    "puntuable" => fields.push(("rated", "true".to_string())),
+                    number => match number.parse::<f64>() {
+                        Ok(number) => numbers.push(number),
+                        Err(_) => return Err(usage.to_string()),
+                    },
+                }
+            }
+
+            match numbers.as_slice() {
+                [minutes] => fields.push(("minutes", minutes.to_string())),
+                [minutes, increment] => {
+                    fields.push(("minutes", minutes.to_string()));
+                    fields.push(("increment", increment.to_string()));
+                }
+                _ => return Err(usage.to_string()),
+            }
+            if let Some(player) = player {
+                fields.push(("player", json::string(player)));
+            }
+            Ok(json::object(&fields))
+        }
+        "historial" => {
+            let mut fields = vec![("type", json::string("history"))];
+            for word in words {
+                // Las fechas empiezan con el año; todo lo demás es un nombre
+                let key = if word.starts_with(|character: char| character.is_ascii_digit()) {
+                    "date"
+                } else {
+                    "player"
+                };
+                fields.push((key, json::string(word)));
+            }
+            Ok(json::object(&fields))
+        }
+        "ficha" => match words.next() {
+            Some(name) => Ok(json::object(&[
+                ("type", json::string("player")),
+                ("name", json::string(name)),
+            ])),
+            None => Err("Uso: ficha <jugador>".to_string()),
+        },
+        "mirar" => match words.next() {
+            Some(room) => Ok(json::object(&[
+                ("type", json::string("join")),
+                ("room", json::string(room)),
+                ("role", json::string("spectator")),
+            ])),
+            None => Err("Uso: mirar <sala>".to_string()),
+        },
+        _ => Ok(json::object(&[
+            ("type", json::string("move")),
+            ("move", json::string(line)),
+        ])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IA::pgn::GameResult;
+
+    fn server(directory: &str) -> RoomServer {
+        let directory = std::env::temp_dir().join(directory);
+        let _ = std::fs::remove_dir_all(&directory);
+        RoomServer {
+            rooms: HashMap::new(),
+            connections: HashMap::new(),
+            next_connection: 0,
+            lobby: Lobby::default(),
+            store: Store::open(directory.to_str().unwrap()).ok().unwrap(),
+            random: Random::from_time(),
+        }
+    }
+
+    // Una sala con los dos jugadores sentados, desconectados hace tanto como se indica
+    fn room_with_absences(absences: [Option<Duration>; 2]) -> Room {
+        let mut room = Room::new(1, None, true);
+        for (index, absence) in absences.into_iter().enumerate() {
+            room.seats[index] = Some(Seat {
+                token: format!("clave-{}", index),
+                player: Some(format!("jugador{}", index)),
+                connection: None,
+                disconnected_since: absence.map(|absence| Instant::now() - absence),
+            });
+        }
+        room
+    }
+
+    fn result_after_tick(directory: &str, absences: [Option<Duration>; 2]) -> Option<GameEnd> {
+        let mut server = server(directory);
+        server
+            .rooms
+            .insert("sala".to_string(), room_with_absences(absences));
+        server.tick();
+        server.rooms["sala"].game.end
+    }
+
+    #[test]
+    fn only_one_absent_player_loses_by_disconnection() {
+        let gone = Some(ABANDON_TIMEOUT + Duration::from_secs(1));
+        let end = result_after_tick("ajedrez-salas-abandono", [None, gone]).unwrap();
+        assert!(end.result == GameResult::WhiteWins);
+        assert!(end.reason == EndReason::Disconnection);
+
+        let short = Some(Duration::from_secs(1));
+        assert!(result_after_tick("ajedrez-salas-espera", [short, None]).is_none());
+    }
+
+    #[test]
+    fn both_absent_players_draw() {
+        let gone = Some(ABANDON_TIMEOUT + Duration::from_secs(1));
+        let end = result_after_tick("ajedrez-salas-tablas", [gone, gone]).unwrap();
+        assert!(end.result == GameResult::Draw);
+    }
+
+    fn stored_game() -> StoredGame {
+        StoredGame {
+            id: 7,
+            room: "sala".to_string(),
+            started: 0,
+            finished: None,
+            rated: true,
+            players: [Some("ana".to_string()), Some("beto".to_string())],
+            tokens: [
+                Some("clave-blancas".to_string()),
+                Some("clave-negras".to_string()),
+            ],
+            clock: Some(StoredClock {
+                white: Duration::from_secs(60),
+                black: Duration::from_secs(60),
+                increment: Duration::ZERO,
+            }),
+            end: None,
+            moves: vec!["e2e4".to_string()],
+        }
+    }
+
+    #[test]
+    fn restored_game_waits_for_both_players() {
+        let mut server = server("ajedrez-salas-retomada");
+        let room = Room::restore(stored_game()).ok().unwrap();
+        // El tiempo que el servidor estuvo parado no cuenta para el abandono
+        assert!(room
+            .seats
+            .iter()
+            .flatten()
+            .all(|seat| seat.disconnected_since.is_none()));
+        let black_time = |server: &RoomServer| {
+            server.rooms["sala"]
+                .clock
+                .as_ref()
+                .unwrap()
+                .remaining(ChessPieceColor::Black)
+        };
+        server.rooms.insert("sala".to_string(), room);
+        let before = black_time(&server);
+
+        thread::sleep(Duration::from_millis(50));
+        server.tick();
+        assert!(server.rooms["sala"].game.end.is_none());
+        assert_eq!(black_time(&server), before);
+
+        // Vuelve uno solo: la partida sigue detenida
+        let room = server.rooms.get_mut("sala").unwrap();
+        room.seats[0].as_mut().unwrap().connection = Some(1);
+        room.resume();
+        assert!(room.paused);
+
+        room.seats[1].as_mut().unwrap().connection = Some(2);
+        room.resume();
+        assert!(!room.paused);
+        thread::sleep(Duration::from_millis(50));
+        assert!(
+            room.clock
+                .as_ref()
+                .unwrap()
+                .remaining(ChessPieceColor::Black)
+                < before
+        );
+    }
+
+    // Lee mensajes hasta el primero del tipo pedido que cumpla la condición
+    fn wait_for(socket: &mut WebSocket, kind: &str, accept: impl Fn(&str) -> bool) -> String {
+        loop {
+            let message = socket.read().unwrap().unwrap();
+            if json::string_field(&message, "type").as_deref() == Some(kind) && accept(&message) {
+                return message;
+            }
+        }
+    }
+
+    fn join(address: &str, message: &str) -> (WebSocket, String) {
+        let mut socket = WebSocket::connect(address, "/").unwrap();
+        socket.writer().unwrap().send(message).unwrap();
+        let joined = wait_for(&mut socket, "joined", |_| true);
+        (socket, joined)
+    }
+
+    #[test]
+    fn headless_clients_play_watch_and_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = Arc::new(Mutex::new(server("ajedrez-salas-red")));
+        thread::spawn(move || accept_connections(listener, server));
+
+        let player = json::object(&[
+            ("type", json::string("join")),
+            ("room", json::string("amigos")),
+            ("role", json::string("player")),
+        ]);
+        let (mut white, joined) = join(&address, &player);
+        assert_eq!(
+            json::string_field(&joined, "role").as_deref(),
+            Some("white")
+        );
+        let token = json::string_field(&joined, "token").unwrap();
+        let (_black, joined) = join(&address, &player);
+        assert_eq!(
+            json::string_field(&joined, "role").as_deref(),
+            Some("black")
+        );
+        let (mut spectator, _) = join(&address, &client_message("mirar amigos", None).unwrap());
+
+        white
+            .writer()
+            .unwrap()
+            .send(&client_message("e2e4", None).unwrap())
+            .unwrap();
+        let moved =
+            |message: &str| json::string_field(message, "last_move").as_deref() == Some("e2e4");
+        wait_for(&mut spectator, "state", moved);
+
+        // Con la clave, otra conexión recupera el lugar y el estado completo
+        let reconnect = json::object(&[
+            ("type", json::string("join")),
+            ("room", json::string("amigos")),
+            ("token", json::string(&token)),
+        ]);
+        let (mut again, joined) = join(&address, &reconnect);
+        assert_eq!(
+            json::string_field(&joined, "role").as_deref(),
+            Some("white")
+        );
+        let state = wait_for(&mut again, "state", moved);
+        assert_eq!(
+            json::string_field(&state, "black").as_deref(),
+            Some("connected")
+        );
+        wait_for(&mut white, "error", |message| {
+            json::string_field(message, "code").as_deref() == Some("seat_taken")
+        });
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::commands::option_value;
+use crate::IA::pgn::GameResult;
+
+use super::game::{EndReason, GameEnd};
+use super::json;
+
+pub const DEFAULT_STORE_DIRECTORY: &str = "datos-salas";
+
+// Una partida por archivo, con su identificador como nombre
+const GAMES_DIRECTORY: &str = "partidas";
+const PLAYERS_FILE: &str = "jugadores.txt";
+
+const INITIAL_RATING: i32 = 1500;
+// Cuánto puede cambiar la puntuación en una partida
+const RATING_K: f64 = 32.0;
+
+// Los tiempos de una partida con reloj al momento de guardarla
+pub struct StoredClock {
+    pub white: Duration,
+    pub black: Duration,
+    pub increment: Duration,
+}
+
+/**
+ Partida del servidor de salas tal como se guarda: quiénes juegan, las claves para volver, el
+ reloj, el final y las jugadas en coordenadas desde la posición inicial.
+*/
+pub struct StoredGame {
+    pub id: u64,
+    pub room: String,
+    // Segundos desde 1970, en UTC
+    pub started: u64,
+    pub finished: Option<u64>,
+    pub rated: bool,
+    // Blancas y negras, en el orden de `color_index`
+    pub players: [Option<String>; 2],
+    pub tokens: [Option<String>; 2],
+    pub clock: Option<StoredClock>,
+    pub end: Option<GameEnd>,
+    pub moves: Vec<String>,
+}
+
+impl StoredGame {
+    // Una línea por campo, con el nombre adelante
+    fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("id {}", self.id),
+            format!("room {}", self.room),
+            format!("started {}", self.started),
+            format!("rated {}", self.rated),
+        ];
+
+        if let Some(finished) = self.finished {
+            lines.push(format!("finished {}", finished));
+        }
+        for (key, value) in [("white", &self.players[0]), ("black", &self.players[1])] {
+            if let Some(value) = value {
+                lines.push(format!("{} {}", key, value));
+            }
+        }
+        for (key, value) in [
+            ("white_token", &self.tokens[0]),
+            ("black_token", &self.tokens[1]),
+        ] {
+            if let Some(value) = value {
+                lines.push(format!("{} {}", key, value));
+            }
+        }
+        if let Some(clock) = &self.clock {
+            lines.push(format!(
+                "clock {} {} {}",
+                clock.white.as_millis(),
+                clock.black.as_millis(),
+                clock.increment.as_millis()
+            ));
+        }
+        if let Some(end) = self.end {
+            lines.push(format!("result {}", end.result.to_token()));
+            lines.push(format!("reason {}", end.reason.name()));
+        }
+        lines.push(format!("moves {}", self.moves.join(" ")));
+
+        lines.join("\n") + "\n"
+    }
+
+    fn from_text(text: &str) -> Result<StoredGame, String> {
+        let mut fields = HashMap::new();
+        for line in text.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            fields.insert(key, value);
+        }
+
+        let number = |key: &str| -> Result<u64, String> {
+            fields
+                .get(key)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| format!("Falta el campo {}", key))
+        };
+        let text = |key: &str| fields.get(key).map(|value| value.to_string());
+
+        let clock = match fields.get("clock") {
+            Some(value) => {
+                let times: Vec<u64> = value
+                    .split_whitespace()
+                    .map(|time| {
+                        time.parse()
+                            .map_err(|_| format!("Reloj inválido: {}", value))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let [white, black, increment] = times[..] else {
+                    return Err(format!("Reloj inválido: {}", value));
+                };
+                Some(StoredClock {
+                    white: Duration::from_millis(white),
+                    black: Duration::from_millis(black),
+                    increment: Duration::from_millis(increment),
+                })
+            }
+            None => None,
+        };
+
+        let end = match (fields.get("result"), fields.get("reason")) {
+            (Some(result), Some(reason)) => Some(GameEnd {
+                result: GameResult::from_token(result)
+                    .ok_or_else(|| format!("Resultado inválido: {}", result))?,
+                reason: EndReason::from_name(reason)
+                    .ok_or_else(|| format!("Motivo inválido: {}", reason))?,
+            }),
+            _ => None,
+        };
+
+        Ok(StoredGame {
+            id: number("id")?,
+            room: text("room").ok_or("Falta el campo room")?,
+            started: number("started")?,
+            finished: number("finished").ok(),
+            rated: text("rated").as_deref() == Some("true"),
+            players: [text("white"), text("black")],
+            tokens: [text("white_token"), text("black_token")],
+            clock,
+            end,
+            moves: fields
+                .get("moves")
+                .map(|moves| moves.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    // Resumen para las consultas, sin las claves de los jugadores
+    pub fn json(&self) -> String {
+        let name =
+            |player: &Option<String>| player.as_deref().map_or("null".to_string(), json::string);
+
+        let mut fields = vec![
+            ("id", self.id.to_string()),
+            ("room", json::string(&self.room)),
+            ("white", name(&self.players[0])),
+            ("black", name(&self.players[1])),
+            ("rated", self.rated.to_string()),
+            ("started", json::string(&date_time(self.started))),
+        ];
+        if let Some(finished) = self.finished {
+            fields.push(("finished", json::string(&date_time(finished))));
+        }
+        if let Some(end) = self.end {
+            fields.push(("result", json::string(end.result.to_token())));
+            fields.push(("reason", json::string(end.reason.name())));
+        }
+        fields.push(("moves", json::string_array(&self.moves)));
+
+        json::object(&fields)
+    }
+
+    // Si la jugó el jugador y terminó ese día; sin filtro se acepta cualquiera
+    fn matches(&self, player: Option<&str>, day: Option<&str>) -> bool {
+        let Some(finished) = self.finished else {
+            return false;
+        };
+
+        player.is_none_or(|player| self.players.iter().flatten().any(|name| name == player))
+            && day.is_none_or(|day| date(finished) == day)
+    }
+}
+
+#[derive(Clone)]
+pub struct PlayerRecord {
+    pub name: String,
+    pub rating: i32,
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl PlayerRecord {
+    fn new(name: &str) -> PlayerRecord {
+        PlayerRecord {
+            name: name.to_string(),
+            rating: INITIAL_RATING,
+            games: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+
+    pub fn json(&self) -> String {
+        json::object(&[
+            ("name", json::string(&self.name)),
+            ("rating", self.rating.to_string()),
+            ("games", self.games.to_string()),
+            ("wins", self.wins.to_string()),
+            ("draws", self.draws.to_string()),
+            ("losses", self.losses.to_string()),
+        ])
+    }
+
+    // Suma una partida con el puntaje que obtuvo: 1 si ganó, 0.5 si empató y 0 si perdió
+    fn add_game(&mut self, score: f64) {
+        self.games += 1;
+        if score == 1.0 {
+            self.wins += 1;
+        } else if score == 0.0 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+/**
+ Almacén en archivos de texto de las partidas y los jugadores del servidor de salas, para poder
+ reiniciarlo sin perder las partidas en curso y consultar las terminadas.
+*/
+pub struct Store {
+    directory: PathBuf,
+    players: HashMap<String, PlayerRecord>,
+    next_id: u64,
+}
+
+impl Store {
+    /**
+     Abre el almacén de la carpeta, creándola si no existe.
+     # Arguments
+     * `directory` - La carpeta donde se guardan las partidas y los jugadores.
+     # Returns
+     El almacén, o un mensaje si no se pudo crear la carpeta o leer los jugadores.
+    */
+    pub fn open(directory: &str) -> Result<Store, String> {
+        let directory = PathBuf::from(directory);
+        fs::create_dir_all(directory.join(GAMES_DIRECTORY))
+            .map_err(|error| format!("No se pudo crear {}: {}", directory.display(), error))?;
+
+        let mut players = HashMap::new();
+        let players_path = directory.join(PLAYERS_FILE);
+        if players_path.exists() {
+            let text = fs::read_to_string(&players_path).map_err(|error| {
+                format!("No se pudo leer {}: {}", players_path.display(), error)
+            })?;
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let record = parse_player(line).ok_or_else(|| {
+                    format!("Línea inválida en {}: {}", players_path.display(), line)
+                })?;
+                players.insert(record.name.clone(), record);
+            }
+        }
+
+        let mut store = Store {
+            directory,
+            players,
+            next_id: 1,
+        };
+        store.next_id = store
+            .game_paths()
+            .iter()
+            .filter_map(|path| path.file_stem()?.to_str()?.parse::<u64>().ok())
+            .max()
+            .map_or(1, |id| id + 1);
+
+        Ok(store)
+    }
+
+    // Un identificador que ninguna partida guardada usó
+    pub fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    // Escribe primero un archivo temporal para no dejar la partida a medias si algo falla
+    pub fn save_game(&self, game: &StoredGame) -> Result<(), String> {
+        let path = self.game_path(game.id);
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, game.to_text())
+            .and_then(|_| fs::rename(&temporary, &path))
+            .map_err(|error| format!("No se pudo guardar {}: {}", path.display(), error))
+    }
+
+    pub fn remove_game(&self, id: u64) {
+        let _ = fs::remove_file(self.game_path(id));
+    }
+
+    /**
+     Lee todas las partidas guardadas. Los archivos que no se pueden leer se informan y se saltean.
+     # Returns
+     Las partidas, ordenadas por identificador.
+    */
+    pub fn load_games(&self) -> Vec<StoredGame> {
+        let mut games = Vec::new();
+
+        for path in self.game_paths() {
+            let game = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| StoredGame::from_text(&text));
+            match game {
+                Ok(game) => games.push(game),
+                Err(error) => println!("Se ignora {}: {}", path.display(), error),
+            }
+        }
+
+        games.sort_by_key(|game| game.id);
+        games
+    }
+
+    /**
+     Busca las partidas terminadas de un jugador, de un día o de los dos.
+     # Arguments
+     * `player` - El nombre del jugador, o `None` para todos.
+     * `day` - La fecha en que terminaron como AAAA-MM-DD (UTC), o `None` para cualquiera.
+     # Returns
+     Las partidas en el orden en que terminaron.
+    */
+    pub fn history(&self, player: Option<&str>, day: Option<&str>) -> Vec<StoredGame> {
+        let mut games: Vec<StoredGame> = self
+            .load_games()
+            .into_iter()
+            .filter(|game| game.matches(player, day))
+            .collect();
+        games.sort_by_key(|game| game.finished);
+        games
+    }
+
+    pub fn player(&self, name: &str) -> Option<&PlayerRecord> {
+        self.players.get(name)
+    }
+
+    /**
+     Anota el resultado de una partida terminada en la ficha de los dos jugadores, y si es
+     puntuable les actualiza la puntuación con la fórmula de Elo.
+     # Arguments
+     * `game` - La partida; si falta alguno de los nombres no se anota nada.
+    */
+    pub fn record_result(&mut self, game: &StoredGame) -> Result<(), String> {
+        let ([Some(white), Some(black)], Some(end)) = (&game.players, game.end) else {
+            return Ok(());
+        };
+
+        let white_score = match end.result {
+            GameResult::WhiteWins => 1.0,
+            GameResult::BlackWins => 0.0,
+            _ => 0.5,
+        };
+
+        let mut records = [white, black].map(|name| {
+            self.players
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| PlayerRecord::new(name))
+        });
+
+        if game.rated {
+            let expected =
+                1.0 / (1.0 + 10f64.powf((records[1].rating - records[0].rating) as f64 / 400.0));
+            let change = (RATING_K * (white_score - expected)).round() as i32;
+            records[0].rating += change;
+            records[1].rating -= change;
+        }
+        records[0].add_game(white_score);
+        records[1].add_game(1.0 - white_score);
+
+        for record in records {
+            self.players.insert(record.name.clone(), record);
+        }
+        self.save_players()
+    }
+
+    fn save_players(&self) -> Result<(), String> {
+        let mut records: Vec<&PlayerRecord> = self.players.values().collect();
+        records.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let text: String = records
+            .iter()
+            .map(|record| {
+                format!(
+                    "{} {} {} {} {} {}\n",
+                    record.name,
+                    record.rating,
+                    record.games,
+                    record.wins,
+                    record.draws,
+                    record.losses
+                )
+            })
+            .collect();
+
+        let path = self.directory.join(PLAYERS_FILE);
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, text)
+            .and_then(|_| fs::rename(&temporary, &path))
+            .map_err(|error| format!("No se pudo guardar {}: {}", path.display(), error))
+    }
+
+    fn game_path(&self, id: u64) -> PathBuf {
+        self.directory
+            .join(GAMES_DIRECTORY)
+            .join(format!("{}.txt", id))
+    }
+
+    fn game_paths(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(self.directory.join(GAMES_DIRECTORY)) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+            .collect()
+    }
+}
+
+// Lista las partidas guardadas por un servidor y la ficha del jugador si se pidió uno
+pub fn show_history(args: &[String]) {
+    let directory = option_value(args, "--datos").map_or(DEFAULT_STORE_DIRECTORY, String::as_str);
+    let player = option_value(args, "--jugador").map(String::as_str);
+    let day = option_value(args, "--fecha").map(String::as_str);
+
+    let store = match Store::open(directory) {
+        Ok(store) => store,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let games = store.history(player, day);
+    if games.is_empty() {
+        println!("No hay partidas terminadas con esos datos");
+    }
+    for game in &games {
+        let name = |index: usize| game.players[index].as_deref().unwrap_or("anónimo");
+        let end = game.end.map_or(String::new(), |end| end.describe());
+        println!(
+            "{}  {}  {} - {}  {}  {} jugadas{}",
+            game.id,
+            game.finished.map_or(String::new(), date),
+            name(0),
+            name(1),
+            end,
+            game.moves.len(),
+            if game.rated { ", puntuable" } else { "" }
+        );
+    }
+
+    if let Some(record) = player.and_then(|player| store.player(player)) {
+        println!(
+            "{}: puntuación {}, {} partidas (+{} ={} -{})",
+            record.name, record.rating, record.games, record.wins, record.draws, record.losses
+        );
+    }
+}
+
+// Una línea de jugadores.txt: nombre, puntuación, partidas, victorias, tablas y derrotas
+fn parse_player(line: &str) -> Option<PlayerRecord> {
+    let mut words = line.split_whitespace();
+    let name = words.next()?;
+    let mut numbers = words.map(|word| word.parse::<i64>().ok());
+    let mut next = || numbers.next().flatten();
+
+    Some(PlayerRecord {
+        name: name.to_string(),
+        rating: next()? as i32,
+        games: next()? as u32,
+        wins: next()? as u32,
+        draws: next()? as u32,
+        losses: next()? as u32,
+    })
+}
+
+// Un nombre de jugador es una palabra de letras, números, "-" o "_", para guardarlo en una línea
+pub fn valid_player_name(name: &str) -> bool {
+    (1..=20).contains(&name.chars().count())
+        && name
+            .chars()
+            .all(|character| character.is_alphanumeric() || character == '-' || character == '_')
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+// La fecha como AAAA-MM-DD en UTC
+pub fn date(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn date_time(seconds: u64) -> String {
+    let time = seconds % 86400;
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        date(seconds),
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// Año, mes y día de un día contado desde 1970-01-01 (algoritmo de Howard Hinnant)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
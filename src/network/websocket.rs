@@ -0,0 +1,463 @@
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+
+use crate::IA::random::Random;
+
+use super::http::{error_json, read_request, write_response};
+
+// Se agrega a la clave del cliente antes de calcular la respuesta del saludo (RFC 6455)
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// Tamaño máximo de un mensaje, sumando sus fragmentos
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+// Código del marco de cierre cuando el otro extremo no respeta el protocolo
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/**
+ Conexión WebSocket de texto sobre TCP, del lado del servidor o del cliente. La lectura y la
+ escritura van por separado para poder enviar mensajes desde otros hilos.
+*/
+pub struct WebSocket {
+    reader: BufReader<TcpStream>,
+    writer: WebSocketWriter,
+}
+
+pub struct WebSocketWriter {
+    stream: TcpStream,
+    // El cliente enmascara todo lo que envía, el servidor no
+    mask: Option<Random>,
+}
+
+impl WebSocket {
+    /**
+     Completa el saludo del lado del servidor a partir de la petición HTTP de actualización.
+     # Arguments
+     * `stream` - La conexión recién aceptada.
+     # Returns
+     La conexión lista para enviar y recibir mensajes, o un mensaje si la petición no pedía un
+     WebSocket, en cuyo caso ya se respondió con un error 400.
+    */
+    pub fn accept(stream: TcpStream) -> Result<WebSocket, String> {
+        let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+        let mut reader = BufReader::new(stream);
+        let request = read_request(&mut reader)?;
+
+        let upgrade = request
+            .header("Upgrade")
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+        let Some(key) = request.header("Sec-WebSocket-Key").filter(|_| upgrade) else {
+            let message = "Se esperaba una conexión WebSocket";
+            write_response(&mut writer, 400, &error_json("websocket_required", message));
+            return Err(message.to_string());
+        };
+
+        write!(
+            writer,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        )
+        .map_err(|error| error.to_string())?;
+
+        Ok(WebSocket {
+            reader,
+            writer: WebSocketWriter {
+                stream: writer,
+                mask: None,
+            },
+        })
+    }
+
+    /**
+     Se conecta a un servidor WebSocket y completa el saludo del lado del cliente.
+     # Arguments
+     * `address` - La dirección del servidor con el puerto, por ejemplo "127.0.0.1:9090".
+     * `path` - La ruta que se pide, por ejemplo "/".
+     # Returns
+     La conexión, o un mensaje si no se pudo conectar o el servidor no aceptó el saludo.
+    */
+    pub fn connect(address: &str, path: &str) -> Result<WebSocket, String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|error| format!("No se pudo conectar a {}: {}", address, error))?;
+        let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+        let mut reader = BufReader::new(stream);
+
+        let mut random = Random::from_time();
+        let key_bytes: Vec<u8> = (0..2)
+            .flat_map(|_| random.next_u64().to_be_bytes())
+            .collect();
+        let key = base64(&key_bytes);
+
+        write!(
+            writer,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, address, key
+        )
+        .map_err(|error| error.to_string())?;
+
+        // La respuesta tiene la misma forma que una petición: la línea inicial y las cabeceras,
+        // con el código de estado en el lugar de la ruta
+        let response = read_request(&mut reader)?;
+        if response.path != "101"
+            || response.header("Sec-WebSocket-Accept") != Some(&accept_key(&key))
+        {
+            return Err(format!("{} no aceptó la conexión WebSocket", address));
+        }
+
+        Ok(WebSocket {
+            reader,
+            writer: WebSocketWriter {
+                stream: writer,
+                mask: Some(random),
+            },
+        })
+    }
+
+    // Otro extremo de escritura de la misma conexión, para enviar desde otro hilo
+    pub fn writer(&self) -> Result<WebSocketWriter, String> {
+        let stream = self
+            .writer
+            .stream
+            .try_clone()
+            .map_err(|error| error.to_string())?;
+        let mask = self.writer.mask.as_ref().map(|_| Random::from_time());
+        Ok(WebSocketWriter { stream, mask })
+    }
+
+    /**
+     Espera el siguiente mensaje de texto. Los ping se responden solos y los fragmentos se unen.
+     # Returns
+     El texto, `None` si el otro extremo cerró la conexión, o un mensaje si se cortó o envió algo
+     inválido.
+    */
+    pub fn read(&mut self) -> Result<Option<String>, String> {
+        let mut message = Vec::new();
+
+        loop {
+            let Some((fin, opcode, payload)) = self.read_frame()? else {
+                return Ok(None);
+            };
+
+            match opcode {
+                OPCODE_TEXT | OPCODE_CONTINUATION => {
+                    if message.len() + payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(format!("El mensaje supera los {} bytes", MAX_MESSAGE_SIZE));
+                    }
+                    message.extend_from_slice(&payload);
+                    if fin {
+                        return String::from_utf8(message)
+                            .map(Some)
+                            .map_err(|_| "El mensaje no es texto UTF-8".to_string());
+                    }
+                }
+                OPCODE_PING => self.writer.send_frame(OPCODE_PONG, &payload)?,
+                OPCODE_CLOSE => {
+                    let _ = self.writer.send_frame(OPCODE_CLOSE, &payload);
+                    return Ok(None);
+                }
+                // Los pong y los mensajes binarios no se usan
+                _ => {}
+            }
+        }
+    }
+
+    // Lee un marco completo: si es el último fragmento, el tipo y el contenido sin máscara
+    fn read_frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, String> {
+        let mut header = [0; 2];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            // Cerrar sin el marco de cierre también termina la conexión
+            Err(_) => return Ok(None),
+        }
+
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+
+        // El cliente tiene que enmascarar todo lo que envía y el servidor nada (RFC 6455, 5.1)
+        if masked != self.writer.mask.is_none() {
+            let _ = self
+                .writer
+                .send_frame(OPCODE_CLOSE, &CLOSE_PROTOCOL_ERROR.to_be_bytes());
+            return Err("El marco no respeta la máscara del protocolo".to_string());
+        }
+
+        let length = match header[1] & 0x7F {
+            126 => {
+                let mut length = [0; 2];
+                self.read_bytes(&mut length)?;
+                u16::from_be_bytes(length) as u64
+            }
+            127 => {
+                let mut length = [0; 8];
+                self.read_bytes(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => length as u64,
+        };
+
+        // Se compara antes de convertir, para que un largo enorme no se recorte en 32 bits
+        if length > MAX_MESSAGE_SIZE as u64 {
+            return Err(format!("El mensaje supera los {} bytes", MAX_MESSAGE_SIZE));
+        }
+        let length = length as usize;
+
+        let mut mask = [0; 4];
+        if masked {
+            self.read_bytes(&mut mask)?;
+        }
+
+        let mut payload = vec![0; length];
+        self.read_bytes(&mut payload)?;
+        if masked {
+            for (index, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[index % 4];
+            }
+        }
+
+        Ok(Some((fin, opcode, payload)))
+    }
+
+    fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<(), String> {
+        self.reader
+            .read_exact(buffer)
+            .map_err(|error| format!("Se cortó la conexión: {}", error))
+    }
+}
+
+impl WebSocketWriter {
+    pub fn send(&mut self, text: &str) -> Result<(), String> {
+        self.send_frame(OPCODE_TEXT, text.as_bytes())
+    }
+
+    // Avisa al otro extremo que la conexión se cierra
+    pub fn close(&mut self) {
+        let _ = self.send_frame(OPCODE_CLOSE, &[]);
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), String> {
+        let mut frame = vec![0x80 | opcode];
+        let mask_bit = if self.mask.is_some() { 0x80 } else { 0 };
+
+        match payload.len() {
+            length if length < 126 => frame.push(mask_bit | length as u8),
+            length if length <= u16::MAX as usize => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+
+        match self.mask.as_mut() {
+            Some(random) => {
+                let mask = (random.next_u64() as u32).to_be_bytes();
+                frame.extend_from_slice(&mask);
+                frame.extend(
+                    payload
+                        .iter()
+                        .enumerate()
+                        .map(|(index, byte)| byte ^ mask[index % 4]),
+                );
+            }
+            None => frame.extend_from_slice(payload),
+        }
+
+        self.stream
+            .write_all(&frame)
+            .and_then(|_| self.stream.flush())
+            .map_err(|error| format!("No se pudo enviar el mensaje: {}", error))
+    }
+}
+
+// Respuesta del servidor a la clave del saludo: base64 del SHA-1 de la clave con el GUID
+fn accept_key(key: &str) -> String {
+    base64(&sha1(
+        format!("{}{}", key.trim(), HANDSHAKE_GUID).as_bytes(),
+    ))
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let value = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (value >> (18 - index * 6)) & 0x3F;
+                encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// SHA-1, que el saludo de WebSocket exige aunque ya no sirva como función criptográfica
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (index, word) in block.chunks(4).enumerate() {
+            words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for index in 16..80 {
+            words[index] =
+                (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16])
+                    .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Acepta una conexión en un puerto libre y devuelve la dirección y el hilo que la atiende
+    fn serve_once<T: Send + 'static>(
+        handle: impl FnOnce(WebSocket) -> T + Send + 'static,
+    ) -> (String, thread::JoinHandle<T>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle(WebSocket::accept(stream).ok().unwrap())
+        });
+        (address, server)
+    }
+
+    // Cliente sin máscara: completa el saludo a mano y devuelve la conexión cruda
+    fn raw_client(address: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            address
+        )
+        .unwrap();
+
+        let mut response = Vec::new();
+        let mut byte = [0];
+        while !response.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            response.push(byte[0]);
+        }
+        assert!(response.starts_with(b"HTTP/1.1 101"));
+        stream
+    }
+
+    #[test]
+    fn accept_key_matches_the_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn client_and_server_exchange_messages() {
+        let (address, server) = serve_once(|mut socket| {
+            let message = socket.read().unwrap().unwrap();
+            socket.writer.send(&format!("eco: {}", message)).unwrap();
+            socket.read().unwrap()
+        });
+
+        let mut client = WebSocket::connect(&address, "/").unwrap();
+        let long = "e2e4 ".repeat(100);
+        client.writer.send(&long).unwrap();
+        assert_eq!(client.read().unwrap(), Some(format!("eco: {}", long)));
+
+        client.writer.close();
+        assert_eq!(client.read().unwrap(), None);
+        assert_eq!(server.join().unwrap(), None);
+    }
+
+    #[test]
+    fn server_rejects_unmasked_frames() {
+        let (address, server) = serve_once(|mut socket| socket.read());
+
+        let mut client = raw_client(&address);
+        client.write_all(&[0x81, 0x02, b'h', b'i']).unwrap();
+
+        assert!(server.join().unwrap().is_err());
+        let mut close = [0; 4];
+        client.read_exact(&mut close).unwrap();
+        assert_eq!(close, [0x88, 0x02, 0x03, 0xEA]);
+    }
+
+    #[test]
+    fn server_rejects_huge_lengths_before_reading_them() {
+        let (address, server) = serve_once(|mut socket| socket.read());
+
+        let mut client = raw_client(&address);
+        // 2^32 + 1 bytes, que en 32 bits quedaría en 1
+        let mut frame = vec![0x81, 0x80 | 127];
+        frame.extend_from_slice(&((1u64 << 32) + 1).to_be_bytes());
+        frame.extend_from_slice(&[0; 4]);
+        frame.push(b'x');
+        client.write_all(&frame).unwrap();
+
+        assert!(server.join().unwrap().is_err());
+    }
+}
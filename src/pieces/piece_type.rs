@@ -23,7 +23,9 @@ pub enum Message {
     PieceBlockingTheWay,
     CannotLeaveKingInCheck,
     NoPieceInStartingPosition,
-    CannotCastle
+    CannotCastle,
+    // Carga de posiciones
+    InvalidFen,
 }
 
 impl Message {
@@ -40,6 +42,25 @@ impl Message {
             Message::CannotLeaveKingInCheck => "No puedes dejar al rey en jaque".to_string(),
             Message::NoPieceInStartingPosition => "No hay una pieza en la posición de inicio".to_string(),
             Message::CannotCastle => "No puedes realizar enroque".to_string(),
+            Message::InvalidFen => "La posición FEN no es válida".to_string(),
+        }
+    }
+
+    // Identificador estable del mensaje, para las respuestas que leen otros programas
+    pub fn code(&self) -> &'static str {
+        match self {
+            Message::Success => "success",
+            Message::Check => "check",
+            Message::CheckMate => "checkmate",
+            Message::PieceNotYourColor => "piece_not_your_color",
+            Message::CannotMoveToOccupiedSameColor => "cannot_move_to_occupied_same_color",
+            Message::CannotCaptureKing => "cannot_capture_king",
+            Message::CannotMovePieceToPosition => "cannot_move_piece_to_position",
+            Message::PieceBlockingTheWay => "piece_blocking_the_way",
+            Message::CannotLeaveKingInCheck => "cannot_leave_king_in_check",
+            Message::NoPieceInStartingPosition => "no_piece_in_starting_position",
+            Message::CannotCastle => "cannot_castle",
+            Message::InvalidFen => "invalid_fen",
         }
     }
 }
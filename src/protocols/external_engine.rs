@@ -0,0 +1,410 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::IA::move_generator::{find_move, Move};
+use crate::IA::search::{RootMove, SearchLimits, MATE_SCORE};
+
+// Cuánto se espera a que el motor termine después de "quit" antes de cerrarlo a la fuerza
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Resultado de una búsqueda del motor externo, con las variantes en la escala del motor propio
+pub struct ExternalResult {
+    pub best_move: Option<Move>,
+    pub depth: u32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    // Variantes de la mejor a la peor, según el último "info" de cada una
+    pub root_moves: Vec<RootMove>,
+}
+
+/**
+ Motor UCI externo que corre como otro proceso, por ejemplo Stockfish. Se le envía la posición
+ en FEN antes de cada búsqueda, así que no necesita seguir la partida.
+*/
+pub struct ExternalEngine {
+    pub name: String,
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    // Variantes que se pidieron con MultiPV la última vez
+    lines: usize,
+}
+
+impl ExternalEngine {
+    /**
+     Inicia el motor y espera a que termine de presentarse con "uciok".
+     # Arguments
+     * `command` - El programa y sus argumentos separados por espacios, por ejemplo "stockfish".
+     # Returns
+     El motor listo para buscar, o un mensaje si no se pudo iniciar o no habla UCI.
+    */
+    pub fn start(command: &str) -> Result<ExternalEngine, String> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or("No se indicó el programa del motor")?;
+
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("No se pudo iniciar {}: {}", program, error))?;
+
+        let input = child.stdin.take().unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+
+        let mut engine = ExternalEngine {
+            name: program.to_string(),
+            child,
+            input,
+            output,
+            lines: 1,
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    // Cambia una opción del motor, por ejemplo "Threads" o "Skill Level"
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.wait_ready()
+    }
+
+    // Avisa que empieza otra partida para que el motor olvide lo que aprendió
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    /**
+     Busca en la posición hasta los límites indicados y espera el "bestmove".
+     # Arguments
+     * `chessboard` - La posición, que se envía en FEN.
+     * `limits` - El tiempo y los nodos, o la profundidad si no hay ninguno de los dos.
+     * `lines` - Cuántas variantes se piden con MultiPV.
+     # Returns
+     La jugada con las variantes informadas, o un mensaje si el motor falló o jugó algo ilegal.
+    */
+    pub fn search(
+        &mut self,
+        chessboard: &Chessboard,
+        limits: SearchLimits,
+        lines: usize,
+    ) -> Result<ExternalResult, String> {
+        if lines != self.lines {
+            self.set_option("MultiPV", &lines.to_string())?;
+            self.lines = lines;
+        }
+
+        let start = Instant::now();
+        self.send(&format!("position fen {}", chessboard.to_fen()))?;
+        self.send(&go_command(limits))?;
+
+        let mut result = ExternalResult {
+            best_move: None,
+            depth: 0,
+            nodes: 0,
+            elapsed: Duration::ZERO,
+            root_moves: Vec::new(),
+        };
+
+        loop {
+            let line = self.read_line()?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            match tokens.first().copied() {
+                Some("info") => read_info(chessboard, &tokens, &mut result),
+                Some("bestmove") => {
+                    result.elapsed = start.elapsed();
+                    // "0000" o "(none)" indican que no hay movimientos legales
+                    result.best_move = match tokens.get(1).copied() {
+                        None | Some("0000") | Some("(none)") => None,
+                        Some(coordinates) => Some(
+                            find_move(chessboard, coordinates)
+                                .map(|(chess_move, _)| chess_move)
+                                .ok_or_else(|| {
+                                    format!(
+                                        "{} jugó un movimiento ilegal: {}",
+                                        self.name, coordinates
+                                    )
+                                })?,
+                        ),
+                    };
+                    return Ok(result);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn wait_ready(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.input, "{}", command)
+            .and_then(|_| self.input.flush())
+            .map_err(|error| format!("No se pudo escribir a {}: {}", self.name, error))
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        match self.output.read_line(&mut line) {
+            Ok(0) => Err(format!("{} terminó inesperadamente", self.name)),
+            Ok(_) => Ok(line),
+            Err(error) => Err(format!("No se pudo leer de {}: {}", self.name, error)),
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// La profundidad solo se pide sin límite de tiempo ni de nodos, para no cortar antes la búsqueda
+fn go_command(limits: SearchLimits) -> String {
+    let mut go = "go".to_string();
+    if let Some(time) = limits.time {
+        go.push_str(&format!(" movetime {}", time.as_millis()));
+    }
+    if let Some(nodes) = limits.nodes {
+        go.push_str(&format!(" nodes {}", nodes));
+    }
+    if limits.time.is_none() && limits.nodes.is_none() {
+        go.push_str(&format!(" depth {}", limits.depth));
+    }
+    go
+}
+
+/**
+ Lee una línea "info" y guarda la profundidad, los nodos y la variante que informa. Las jugadas
+ de la variante se comprueban sobre el tablero y la variante se corta en la primera ilegal.
+*/
+fn read_info(chessboard: &Chessboard, tokens: &[&str], result: &mut ExternalResult) {
+    let value = |name: &str| {
+        tokens
+            .iter()
+            .position(|token| *token == name)
+            .and_then(|index| tokens.get(index + 1))
+    };
+
+    if let Some(depth) = value("depth").and_then(|depth| depth.parse().ok()) {
+        result.depth = depth;
+    }
+    if let Some(nodes) = value("nodes").and_then(|nodes| nodes.parse().ok()) {
+        result.nodes = nodes;
+    }
+
+    let score = tokens
+        .iter()
+        .position(|token| *token == "score")
+        .and_then(
+            |index| match (tokens.get(index + 1), tokens.get(index + 2)) {
+                (Some(&"cp"), Some(cp)) => cp.parse().ok(),
+                (Some(&"mate"), Some(moves)) => moves.parse().ok().map(mate_score),
+                _ => None,
+            },
+        );
+
+    let Some(pv_index) = tokens.iter().position(|token| *token == "pv") else {
+        return;
+    };
+    let Some(score) = score else {
+        return;
+    };
+
+    let mut board = chessboard.clone();
+    let mut principal_variation = Vec::new();
+    for coordinates in tokens[pv_index + 1..].iter() {
+        let Some((chess_move, new_board)) = find_move(&board, coordinates) else {
+            break;
+        };
+        principal_variation.push(chess_move);
+        board = new_board;
+    }
+
+    let Some(chess_move) = principal_variation.first().copied() else {
+        return;
+    };
+
+    let line = value("multipv")
+        .and_then(|line| line.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let root_move = RootMove {
+        chess_move,
+        score,
+        principal_variation,
+    };
+
+    // Una profundidad nueva de la primera variante invalida el orden de las demás
+    if line == 1 {
+        result.root_moves.truncate(1);
+    }
+    // Una variante que llega antes que las anteriores no tiene lugar todavía y se descarta
+    if line <= result.root_moves.len() {
+        result.root_moves[line - 1] = root_move;
+    } else if line == result.root_moves.len() + 1 {
+        result.root_moves.push(root_move);
+    }
+}
+
+// Convierte "mate N" a la escala del motor propio, con N en jugadas y negativo si recibe mate
+fn mate_score(moves: i32) -> i32 {
+    if moves > 0 {
+        MATE_SCORE - (moves * 2 - 1)
+    } else {
+        -(MATE_SCORE + moves * 2)
+    }
+}
+
+// Un motor de mentira escrito en sh, que responde siempre lo mismo
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const STUB: &str = r#"while read line; do
+  case "$line" in
+    uci) echo "id name Stub 1.0"; echo "option name MultiPV type spin default 1 min 1 max 5"; echo "uciok" ;;
+    isready) echo "readyok" ;;
+    go*) echo "info depth 3 nodes 1234 score cp 25 multipv 1 pv e2e4 e7e5 e1c1"
+         echo "info depth 3 nodes 1300 score mate -2 multipv 2 pv f2f3 e7e5"
+         echo "bestmove $1" ;;
+    quit) exit 0 ;;
+  esac
+done
+"#;
+
+    // Cada prueba escribe su propio archivo, porque corren en paralelo
+    fn stub(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ajedrez-motor-{}.sh", name));
+        fs::write(&path, STUB).unwrap();
+        path
+    }
+
+    fn limits() -> SearchLimits {
+        SearchLimits {
+            depth: 3,
+            time: None,
+            nodes: None,
+        }
+    }
+
+    fn start(name: &str, best_move: &str) -> ExternalEngine {
+        let command = format!("sh {} {}", stub(name).display(), best_move);
+        ExternalEngine::start(&command).ok().unwrap()
+    }
+
+    #[test]
+    fn handshake_reads_the_engine_name() {
+        let mut engine = start("saludo", "e2e4");
+        assert_eq!(engine.name, "Stub 1.0");
+        assert!(engine.new_game().is_ok());
+        assert!(engine.set_option("Hash", "16").is_ok());
+    }
+
+    #[test]
+    fn reads_bestmove_and_variations() {
+        let mut engine = start("variantes", "e2e4");
+        let chessboard = Chessboard::new();
+        let result = engine.search(&chessboard, limits(), 2).ok().unwrap();
+
+        assert_eq!(
+            result.best_move.map(Move::to_coordinates).as_deref(),
+            Some("e2e4")
+        );
+        assert_eq!(result.depth, 3);
+        assert_eq!(result.nodes, 1300);
+        assert_eq!(result.root_moves.len(), 2);
+        assert_eq!(result.root_moves[0].score, 25);
+        // La variante se corta en la primera jugada ilegal
+        assert_eq!(result.root_moves[0].principal_variation.len(), 2);
+        assert_eq!(result.root_moves[1].score, -(MATE_SCORE - 4));
+    }
+
+    #[test]
+    fn depth_is_only_sent_without_other_limits() {
+        assert_eq!(go_command(limits()), "go depth 3");
+
+        let timed = SearchLimits {
+            time: Some(Duration::from_millis(500)),
+            ..limits()
+        };
+        assert_eq!(go_command(timed), "go movetime 500");
+
+        let counted = SearchLimits {
+            nodes: Some(10_000),
+            ..limits()
+        };
+        assert_eq!(go_command(counted), "go nodes 10000");
+    }
+
+    #[test]
+    fn out_of_order_variations_are_skipped() {
+        let chessboard = Chessboard::new();
+        let mut result = ExternalResult {
+            best_move: None,
+            depth: 0,
+            nodes: 0,
+            elapsed: Duration::ZERO,
+            root_moves: Vec::new(),
+        };
+
+        let mut read = |line: &str| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            read_info(&chessboard, &tokens, &mut result);
+        };
+        read("info depth 4 score cp 30 multipv 1 pv e2e4");
+        read("info depth 4 score cp 10 multipv 3 pv g1f3");
+        read("info depth 4 score cp 20 multipv 2 pv d2d4");
+
+        assert_eq!(result.root_moves.len(), 2);
+        assert_eq!(result.root_moves[0].chess_move.to_coordinates(), "e2e4");
+        assert_eq!(result.root_moves[1].chess_move.to_coordinates(), "d2d4");
+    }
+
+    #[test]
+    fn null_and_illegal_bestmoves() {
+        let chessboard = Chessboard::new();
+
+        let mut engine = start("nula", "0000");
+        let result = engine.search(&chessboard, limits(), 1).ok().unwrap();
+        assert!(result.best_move.is_none());
+
+        let mut engine = start("ilegal", "e1c1");
+        assert!(engine.search(&chessboard, limits(), 1).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::IA::search::SearchResult;
+
+pub mod external_engine;
+pub mod uci;
+pub mod xboard;
+
+// Lo que recibe el ciclo principal de un protocolo: líneas de la interfaz o el fin de una búsqueda
+pub enum Event {
+    Line(String),
+    SearchDone(Box<SearchResult>),
+}
+
+/**
+ Lee la entrada estándar en otro hilo, para poder recibir órdenes mientras el motor busca. Al
+ cerrarse la entrada se envía "quit".
+ # Arguments
+ * `sender` - Donde se envía cada línea leída.
+*/
+pub fn spawn_input_reader(sender: Sender<Event>) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(Event::Line(line)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(Event::Line("quit".to_string()));
+    });
+}
+
+// Escribe una línea para la interfaz sin esperar a que se llene el búfer
+pub fn send(message: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", message);
+    let _ = stdout.flush();
+}
@@ -0,0 +1,530 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::pieces::piece_type::ChessPieceColor;
+use crate::IA::move_generator::{find_move, Move};
+use crate::IA::opening_book::OpeningBook;
+use crate::IA::random::Random;
+use crate::IA::search::{
+    Engine, SearchConfig, SearchInfo, SearchLimits, SearchResult, DEFAULT_HASH_MB, MATE_SCORE,
+    MAX_PLY,
+};
+use crate::IA::strength::{choose_move, DifficultyLevel, Strength};
+use crate::IA::syzygy::Tablebase;
+
+use super::{send, spawn_input_reader, Event};
+
+// Profundidad máxima cuando la búsqueda solo la limita el tiempo
+const MAX_UCI_DEPTH: u32 = 32;
+
+// Tiempo que se reserva para la comunicación con la interfaz en cada jugada
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+// Jugadas que se suponen restantes si la interfaz no envía movestogo
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// Cada cuánto se revisa el reloj mientras se espera a la búsqueda
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Parámetros del comando go
+#[derive(Default)]
+struct GoOptions {
+    white_time: Option<u64>,
+    black_time: Option<u64>,
+    white_increment: u64,
+    black_increment: u64,
+    moves_to_go: Option<u32>,
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    move_time: Option<u64>,
+    infinite: bool,
+    ponder: bool,
+}
+
+impl GoOptions {
+    fn parse(tokens: &[&str]) -> GoOptions {
+        let mut options = GoOptions::default();
+        let value = |index: usize| tokens.get(index + 1).and_then(|value| value.parse().ok());
+
+        for (index, token) in tokens.iter().enumerate() {
+            match *token {
+                "wtime" => options.white_time = value(index),
+                "btime" => options.black_time = value(index),
+                "winc" => options.white_increment = value(index).unwrap_or(0),
+                "binc" => options.black_increment = value(index).unwrap_or(0),
+                "movestogo" => options.moves_to_go = value(index).map(|moves: u64| moves as u32),
+                "depth" => options.depth = value(index).map(|depth: u64| depth as u32),
+                // Un mate en N se encuentra a 2N - 1 medias jugadas
+                "mate" => options.depth = value(index).map(|moves: u64| moves as u32 * 2),
+                "nodes" => options.nodes = value(index),
+                "movetime" => options.move_time = value(index),
+                "infinite" => options.infinite = true,
+                "ponder" => options.ponder = true,
+                _ => {}
+            }
+        }
+
+        options
+    }
+
+    // Tiempo para pensar esta jugada según el reloj del jugador en turno
+    fn allotted_time(&self, color: ChessPieceColor) -> Option<Duration> {
+        if let Some(move_time) = self.move_time {
+            return Some(Duration::from_millis(move_time).saturating_sub(MOVE_OVERHEAD));
+        }
+
+        let (time, increment) = match color {
+            ChessPieceColor::White => (self.white_time?, self.white_increment),
+            ChessPieceColor::Black => (self.black_time?, self.black_increment),
+        };
+
+        let moves = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1) as u64;
+        let allotted = Duration::from_millis(time / moves + increment * 3 / 4);
+        let available = Duration::from_millis(time).saturating_sub(MOVE_OVERHEAD);
+
+        Some(allotted.min(available).max(Duration::from_millis(1)))
+    }
+}
+
+// Estado del motor entre comandos
+struct UciEngine {
+    engine: Engine,
+    board: Chessboard,
+    random: Random,
+    limit_strength: bool,
+    elo: u32,
+}
+
+/**
+ Habla el protocolo UCI por la entrada y la salida estándar, para usar el motor desde cualquier
+ interfaz gráfica o administrador de torneos. Termina con "quit" o al cerrarse la entrada.
+*/
+pub fn run_uci() {
+    let (sender, receiver) = mpsc::channel();
+
+    spawn_input_reader(sender.clone());
+
+    let mut uci = UciEngine::new();
+
+    while let Ok(event) = receiver.recv() {
+        let Event::Line(line) = event else {
+            continue;
+        };
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                send(&format!(
+                    "id name {} {}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                ));
+                send("id author CrisGO0510");
+                send(&format!(
+                    "option name Hash type spin default {} min 1 max 4096",
+                    DEFAULT_HASH_MB
+                ));
+                send("option name Threads type spin default 1 min 1 max 256");
+                send("option name Ponder type check default false");
+                send("option name BookFile type string default <empty>");
+                send("option name SyzygyPath type string default <empty>");
+                send("option name UCI_LimitStrength type check default false");
+                send(&format!(
+                    "option name UCI_Elo type spin default {} min {} max {}",
+                    uci.elo,
+                    DifficultyLevel::Beginner.elo(),
+                    DifficultyLevel::Master.elo()
+                ));
+                send("uciok");
+            }
+            Some("isready") => send("readyok"),
+            Some("ucinewgame") => {
+                uci.engine.clear();
+                uci.board = Chessboard::new();
+            }
+            Some("setoption") => uci.set_option(&tokens),
+            Some("position") => uci.set_position(&tokens),
+            Some("go") => {
+                let options = GoOptions::parse(&tokens);
+                if !uci.go(&options, &sender, &receiver) {
+                    break;
+                }
+            }
+            Some("quit") => break,
+            // "stop" y "ponderhit" sin búsqueda en curso no hacen nada
+            _ => {}
+        }
+    }
+}
+
+impl UciEngine {
+    fn new() -> UciEngine {
+        UciEngine {
+            engine: Engine::new(SearchConfig::default()),
+            board: Chessboard::new(),
+            random: Random::from_time(),
+            limit_strength: false,
+            elo: DifficultyLevel::Intermediate.elo(),
+        }
+    }
+
+    // setoption name <nombre> [value <valor>]
+    fn set_option(&mut self, tokens: &[&str]) {
+        let Some((name, value)) = parse_option(tokens) else {
+            send("info string Se esperaba setoption name <nombre> [value <valor>]");
+            return;
+        };
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse() {
+                Ok(size_mb) => self.engine.set_hash_size(size_mb),
+                Err(_) => send(&format!("info string Hash inválido: {}", value)),
+            },
+            "threads" => match value.parse() {
+                Ok(threads) => self.engine.set_threads(threads),
+                Err(_) => send(&format!("info string Threads inválido: {}", value)),
+            },
+            "bookfile" if !value.is_empty() && value != "<empty>" => {
+                match OpeningBook::open(&value) {
+                    Ok(book) => self.engine.set_book(book),
+                    Err(error) => send(&format!(
+                        "info string No se pudo abrir {}: {}",
+                        value, error
+                    )),
+                }
+            }
+            "syzygypath" if !value.is_empty() && value != "<empty>" => {
+                match Tablebase::open(&value) {
+                    Ok(tablebase) => {
+                        send(&format!(
+                            "info string Tablas de finales de hasta {} piezas",
+                            tablebase.max_pieces()
+                        ));
+                        self.engine.set_tablebase(Arc::new(tablebase));
+                    }
+                    Err(error) => send(&format!("info string {}", error)),
+                }
+            }
+            "uci_limitstrength" => self.limit_strength = value == "true",
+            "uci_elo" => match value.parse() {
+                Ok(elo) => self.elo = elo,
+                Err(_) => send(&format!("info string UCI_Elo inválido: {}", value)),
+            },
+            // Ponder solo avisa que la interfaz puede mandar "go ponder"
+            _ => {}
+        }
+    }
+
+    // position startpos|fen <fen> [moves <movimientos>]
+    fn set_position(&mut self, tokens: &[&str]) {
+        let moves_index = tokens
+            .iter()
+            .position(|token| *token == "moves")
+            .unwrap_or(tokens.len());
+
+        let board = match tokens.get(1).copied() {
+            Some("startpos") => Ok(Chessboard::new()),
+            Some("fen") => Chessboard::from_fen(&tokens[2..moves_index].join(" ")),
+            _ => return,
+        };
+
+        let Ok(mut board) = board else {
+            send("info string FEN inválido");
+            return;
+        };
+
+        for coordinates in tokens.iter().skip(moves_index + 1) {
+            match find_move(&board, coordinates) {
+                Some((_, new_board)) => board = new_board,
+                None => {
+                    send(&format!("info string Movimiento ilegal: {}", coordinates));
+                    break;
+                }
+            }
+        }
+
+        self.board = board;
+    }
+
+    /**
+     Busca en otro hilo mientras sigue atendiendo a la interfaz, hasta que termina la búsqueda o
+     llega "stop". Con "go infinite" o "go ponder" la jugada se envía recién con "stop" o
+     "ponderhit".
+     # Returns
+     `false` si llegó "quit" durante la búsqueda.
+    */
+    fn go(
+        &mut self,
+        options: &GoOptions,
+        sender: &Sender<Event>,
+        receiver: &mpsc::Receiver<Event>,
+    ) -> bool {
+        let color = self.board.player_turn;
+        let waits_for_stop = options.infinite || options.ponder;
+        let limits = SearchLimits {
+            depth: options.depth.unwrap_or(MAX_UCI_DEPTH),
+            time: if waits_for_stop {
+                None
+            } else {
+                options.allotted_time(color)
+            },
+            nodes: options.nodes,
+        };
+        let strength = self.limit_strength.then(|| Strength::from_elo(self.elo));
+
+        let stop = self.engine.stop_handle();
+        let (engine, board, random) = (&mut self.engine, &self.board, &mut self.random);
+        let mut keep_running = true;
+
+        thread::scope(|scope| {
+            let search_sender = sender.clone();
+            scope.spawn(move || {
+                let result = match strength {
+                    Some(strength) => choose_move(engine, board, &strength, random),
+                    None => engine.search_with_info(board, limits, &mut send_info),
+                };
+                let _ = search_sender.send(Event::SearchDone(Box::new(result)));
+            });
+
+            // La jugada se guarda hasta "stop", o hasta "ponderhit" si no es una búsqueda infinita
+            let mut holding = waits_for_stop;
+            let mut held_result = None;
+            // Con ponderhit el tiempo empieza a correr desde ese momento
+            let mut deadline: Option<Instant> = None;
+
+            loop {
+                let event = match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            stop.store(true, Ordering::Relaxed);
+                            deadline = None;
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                match event {
+                    Event::SearchDone(result) => {
+                        if holding && keep_running {
+                            held_result = Some(result);
+                            continue;
+                        }
+                        send_best_move(&result);
+                        break;
+                    }
+                    Event::Line(line) => match line.trim() {
+                        "stop" => {
+                            holding = false;
+                            stop.store(true, Ordering::Relaxed);
+                            if let Some(result) = held_result.take() {
+                                send_best_move(&result);
+                                break;
+                            }
+                        }
+                        "ponderhit" => {
+                            holding = options.infinite;
+                            if let Some(result) = held_result.take() {
+                                send_best_move(&result);
+                                break;
+                            }
+                            deadline = options
+                                .allotted_time(color)
+                                .map(|time| Instant::now() + time);
+                        }
+                        "isready" => send("readyok"),
+                        "quit" => {
+                            keep_running = false;
+                            stop.store(true, Ordering::Relaxed);
+                            if held_result.is_some() {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        });
+
+        stop.store(false, Ordering::Relaxed);
+        keep_running
+    }
+}
+
+/**
+ Separa el nombre y el valor de un comando setoption. El nombre puede tener espacios y termina
+ donde empieza "value".
+ # Arguments
+ * `tokens` - Las palabras del comando.
+ # Returns
+ El nombre y el valor, vacío si no tiene, o `None` si falta el nombre.
+*/
+fn parse_option(tokens: &[&str]) -> Option<(String, String)> {
+    let start = tokens.iter().position(|token| *token == "name")? + 1;
+    let end = tokens[start..]
+        .iter()
+        .position(|token| *token == "value")
+        .map_or(tokens.len(), |index| start + index);
+
+    if start >= end {
+        return None;
+    }
+
+    let value = tokens.get(end + 1..).unwrap_or_default().join(" ");
+    Some((tokens[start..end].join(" "), value))
+}
+
+fn send_info(info: &SearchInfo) {
+    let millis = info.elapsed.as_millis().max(1) as u64;
+    let principal_variation: Vec<String> = info
+        .principal_variation
+        .iter()
+        .map(|chess_move| chess_move.to_coordinates())
+        .collect();
+
+    send(&format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        uci_score(info.score),
+        info.nodes,
+        info.nodes * 1000 / millis,
+        millis,
+        principal_variation.join(" ")
+    ));
+}
+
+// Las puntuaciones de mate se informan en jugadas, positivas si el motor da mate
+fn uci_score(score: i32) -> String {
+    if score.abs() >= MATE_SCORE - MAX_PLY as i32 {
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        return if score > 0 {
+            format!("mate {}", moves)
+        } else {
+            format!("mate -{}", moves.max(1))
+        };
+    }
+
+    format!("cp {}", score)
+}
+
+fn send_best_move(result: &SearchResult) {
+    let coordinates = |chess_move: Move| chess_move.to_coordinates();
+
+    match (result.best_move, result.principal_variation.get(1)) {
+        (Some(best_move), Some(reply)) => send(&format!(
+            "bestmove {} ponder {}",
+            coordinates(best_move),
+            coordinates(*reply)
+        )),
+        (Some(best_move), None) => send(&format!("bestmove {}", coordinates(best_move))),
+        // Sin movimientos legales se responde con el movimiento nulo
+        (None, _) => send("bestmove 0000"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(line: &str) -> Vec<&str> {
+        line.split_whitespace().collect()
+    }
+
+    #[test]
+    fn plays_the_position_moves() {
+        let mut uci = UciEngine::new();
+        uci.set_position(&tokens("position startpos moves e2e4 e7e5 g1f3"));
+        let expected =
+            Chessboard::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+                .ok()
+                .unwrap();
+        assert_eq!(uci.board.to_fen(), expected.to_fen());
+
+        // Un movimiento ilegal deja la posición hasta la jugada anterior
+        uci.set_position(&tokens("position startpos moves e2e4 e2e4 e7e5"));
+        assert!(uci.board.player_turn == ChessPieceColor::Black);
+
+        uci.set_position(&tokens(
+            "position fen 8/8/8/8/8/8/4K3/4k2R w - - 0 1 moves e2d3",
+        ));
+        assert!(uci.board.to_fen().starts_with("8/8/8/8/8/3K4/8/4k2R b - -"));
+    }
+
+    #[test]
+    fn parses_the_go_options() {
+        let options = GoOptions::parse(&tokens(
+            "go wtime 60000 btime 30000 winc 1000 movestogo 20 depth 7",
+        ));
+        assert_eq!(options.white_time, Some(60000));
+        assert_eq!(options.black_time, Some(30000));
+        assert_eq!(options.white_increment, 1000);
+        assert_eq!(options.moves_to_go, Some(20));
+        assert_eq!(options.depth, Some(7));
+        assert!(!options.infinite);
+
+        // 60000 / 20 + 1000 * 3 / 4
+        assert_eq!(
+            options.allotted_time(ChessPieceColor::White),
+            Some(Duration::from_millis(3750))
+        );
+        assert_eq!(
+            options.allotted_time(ChessPieceColor::Black),
+            Some(Duration::from_millis(1500))
+        );
+
+        let options = GoOptions::parse(&tokens("go infinite"));
+        assert!(options.infinite);
+        assert_eq!(options.allotted_time(ChessPieceColor::White), None);
+    }
+
+    #[test]
+    fn stop_ends_an_infinite_search() {
+        let mut uci = UciEngine::new();
+        let (sender, receiver) = mpsc::channel();
+        let stopper = sender.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let _ = stopper.send(Event::Line("stop".to_string()));
+        });
+
+        assert!(uci.go(
+            &GoOptions::parse(&tokens("go infinite")),
+            &sender,
+            &receiver
+        ));
+        assert!(!uci.engine.stop_handle().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn parses_option_names_and_values() {
+        let option = |line: &str| parse_option(&tokens(line));
+        assert_eq!(
+            option("setoption name Hash value 32"),
+            Some(("Hash".to_string(), "32".to_string()))
+        );
+        assert_eq!(
+            option("setoption name Clear Hash"),
+            Some(("Clear Hash".to_string(), String::new()))
+        );
+        assert_eq!(
+            option("setoption name BookFile value libros/mi libro.bin"),
+            Some(("BookFile".to_string(), "libros/mi libro.bin".to_string()))
+        );
+        assert_eq!(option("setoption value 5"), None);
+        assert_eq!(option("setoption name value 5"), None);
+        assert_eq!(option("setoption"), None);
+
+        let mut uci = UciEngine::new();
+        uci.set_option(&tokens("setoption value 5"));
+        uci.set_option(&tokens("setoption name UCI_LimitStrength value true"));
+        uci.set_option(&tokens("setoption name UCI_Elo value 1200"));
+        assert!(uci.limit_strength);
+        assert_eq!(uci.elo, 1200);
+    }
+}
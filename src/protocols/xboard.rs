@@ -0,0 +1,468 @@
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::chessboard::chessboard::Chessboard;
+use crate::chessboard::chessboard_validation::is_check;
+use crate::pieces::piece_type::ChessPieceColor;
+use crate::IA::match_runner::insufficient_material;
+use crate::IA::move_generator::{find_move, generate_moves};
+use crate::IA::search::{
+    opposite_color, Engine, SearchConfig, SearchInfo, SearchLimits, MATE_SCORE, MAX_PLY,
+};
+use crate::IA::zobrist;
+
+use super::{send, spawn_input_reader, Event};
+
+// Profundidad máxima cuando la búsqueda solo la limita el tiempo
+const MAX_XBOARD_DEPTH: u32 = 32;
+
+// Tiempo que se reserva para la comunicación con la interfaz en cada jugada
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+// Jugadas que se suponen restantes cuando el control de tiempo no las fija
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// Las puntuaciones de mate se informan como 100000 más las jugadas hasta el mate
+const XBOARD_MATE_SCORE: i32 = 100_000;
+
+// Control de tiempo de "level", "st" y "sd"
+#[derive(Default)]
+struct TimeControl {
+    // Jugadas por período; 0 si todo el tiempo es para la partida
+    moves_per_session: u32,
+    increment: Duration,
+    // Segundos fijos por jugada de "st"
+    move_time: Option<Duration>,
+    // Profundidad máxima de "sd"
+    depth: Option<u32>,
+    // Reloj propio según el último "time", en centésimas de segundo
+    clock: Option<u64>,
+}
+
+impl TimeControl {
+    // Tiempo para pensar sabiendo cuántas jugadas hizo ya el motor
+    fn allotted_time(&self, engine_moves: u32) -> Option<Duration> {
+        if let Some(move_time) = self.move_time {
+            return Some(move_time.saturating_sub(MOVE_OVERHEAD));
+        }
+
+        let clock = Duration::from_millis(self.clock? * 10);
+        let moves = match self.moves_per_session {
+            0 => DEFAULT_MOVES_TO_GO,
+            session => session - engine_moves % session,
+        };
+
+        let allotted = clock / moves + self.increment * 3 / 4;
+        let available = clock.saturating_sub(MOVE_OVERHEAD);
+
+        Some(allotted.min(available).max(Duration::from_millis(1)))
+    }
+}
+
+// Estado del motor entre comandos
+struct XboardEngine {
+    engine: Engine,
+    // Posiciones de la partida desde la inicial, para deshacer y detectar repeticiones
+    history: Vec<Chessboard>,
+    // Bando que juega el motor; `None` en modo force
+    engine_color: Option<ChessPieceColor>,
+    time_control: TimeControl,
+    engine_moves: u32,
+    post: bool,
+    game_over: bool,
+}
+
+/**
+ Habla el protocolo xboard (CECP versión 2) por la entrada y la salida estándar, para las
+ interfaces y herramientas que no conocen UCI. Termina con "quit" o al cerrarse la entrada.
+*/
+pub fn run_xboard() {
+    let (sender, receiver) = mpsc::channel();
+    spawn_input_reader(sender.clone());
+
+    let mut xboard = XboardEngine::new();
+
+    // Órdenes que llegaron mientras el motor pensaba y quedan para después de su jugada
+    let mut pending = VecDeque::new();
+
+    loop {
+        let line = match pending.pop_front() {
+            Some(line) => line,
+            None => match receiver.recv() {
+                Ok(Event::Line(line)) => line,
+                Ok(Event::SearchDone(_)) => continue,
+                Err(_) => break,
+            },
+        };
+
+        if !xboard.handle(&line) {
+            break;
+        }
+
+        if xboard.engine_to_move() && !xboard.think(&sender, &receiver, &mut pending) {
+            break;
+        }
+    }
+}
+
+impl XboardEngine {
+    // Una partida nueva desde la posición inicial, con el motor llevando las negras
+    fn new() -> Self {
+        XboardEngine {
+            engine: Engine::new(SearchConfig::default()),
+            history: vec![Chessboard::new()],
+            engine_color: Some(ChessPieceColor::Black),
+            time_control: TimeControl::default(),
+            engine_moves: 0,
+            post: false,
+            game_over: false,
+        }
+    }
+
+    fn board(&self) -> &Chessboard {
+        self.history.last().unwrap()
+    }
+
+    fn engine_to_move(&self) -> bool {
+        !self.game_over && self.engine_color == Some(self.board().player_turn)
+    }
+
+    /**
+     Atiende una orden de la interfaz.
+     # Returns
+     `false` si llegó "quit".
+    */
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let argument = |index: usize| tokens.get(index).copied().unwrap_or("");
+
+        match tokens.first().copied() {
+            Some("protover") => {
+                send(&format!(
+                    "feature myname=\"{} {}\" ping=1 setboard=1 usermove=1 san=0 time=1 draw=0 sigint=0 sigterm=0 colors=0 analyze=0 reuse=1 done=1",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                ));
+            }
+            Some("new") => {
+                self.engine.clear();
+                self.set_board(Chessboard::new());
+                self.engine_color = Some(ChessPieceColor::Black);
+                self.time_control.depth = None;
+            }
+            Some("variant") => send(&format!("Error (variante no soportada): {}", argument(1))),
+            Some("quit") => return false,
+            Some("force") => self.engine_color = None,
+            Some("go") => self.engine_color = Some(self.board().player_turn),
+            Some("playother") => self.engine_color = Some(opposite_color(self.board().player_turn)),
+            Some("level") => self.set_level(&tokens),
+            Some("st") => match argument(1).parse() {
+                Ok(seconds) => self.time_control.move_time = Some(Duration::from_secs(seconds)),
+                Err(_) => send(&format!("Error (tiempo inválido): {}", line)),
+            },
+            Some("sd") => match argument(1).parse() {
+                Ok(depth) => self.time_control.depth = Some(depth),
+                Err(_) => send(&format!("Error (profundidad inválida): {}", line)),
+            },
+            Some("time") => self.time_control.clock = argument(1).parse().ok(),
+            Some("usermove") => self.user_move(argument(1)),
+            Some("ping") => send(&format!("pong {}", argument(1))),
+            Some("result") => {
+                self.game_over = true;
+                self.engine_color = None;
+            }
+            Some("setboard") => match Chessboard::from_fen(&tokens[1..].join(" ")) {
+                Ok(chessboard) => self.set_board(chessboard),
+                Err(_) => send("tellusererror FEN inválido"),
+            },
+            Some("undo") => self.take_back(1),
+            Some("remove") => self.take_back(2),
+            Some("post") => self.post = true,
+            Some("nopost") => self.post = false,
+            // "xboard", "otim", "hard", "easy", "accepted", "computer", "name" y los demás no
+            // cambian nada en este motor
+            _ => {}
+        }
+
+        true
+    }
+
+    fn set_board(&mut self, chessboard: Chessboard) {
+        self.history = vec![chessboard];
+        self.engine_moves = 0;
+        self.game_over = false;
+    }
+
+    // level <jugadas> <minutos[:segundos]> <incremento>
+    fn set_level(&mut self, tokens: &[&str]) {
+        let (Some(moves), Some(base), Some(increment)) = (
+            tokens.get(1).and_then(|moves| moves.parse().ok()),
+            tokens.get(2).and_then(|base| parse_base_time(base)),
+            tokens
+                .get(3)
+                .and_then(|increment| increment.parse::<f64>().ok())
+                .filter(|increment| increment.is_finite() && *increment >= 0.0),
+        ) else {
+            send(&format!(
+                "Error (control de tiempo inválido): {}",
+                tokens.join(" ")
+            ));
+            return;
+        };
+
+        self.time_control.moves_per_session = moves;
+        self.time_control.increment = Duration::from_secs_f64(increment);
+        self.time_control.move_time = None;
+        // Hasta que llegue "time" el reloj es el tiempo base
+        self.time_control.clock = Some(base.as_millis() as u64 / 10);
+    }
+
+    fn user_move(&mut self, coordinates: &str) {
+        if self.game_over {
+            send(&format!(
+                "Illegal move (la partida terminó): {}",
+                coordinates
+            ));
+            return;
+        }
+
+        match find_move(self.board(), coordinates) {
+            Some((_, new_chessboard)) => {
+                self.history.push(new_chessboard);
+                self.report_result();
+            }
+            None => send(&format!("Illegal move: {}", coordinates)),
+        }
+    }
+
+    fn take_back(&mut self, plies: usize) {
+        let plies = plies.min(self.history.len() - 1);
+        self.history.truncate(self.history.len() - plies);
+        self.game_over = false;
+    }
+
+    /**
+     Busca en otro hilo mientras sigue atendiendo a la interfaz. Con "?" juega de inmediato lo
+     mejor que encontró; las órdenes que cambian la partida cancelan la jugada y el resto espera
+     a que termine.
+     # Returns
+     `false` si llegó "quit" durante la búsqueda.
+    */
+    fn think(
+        &mut self,
+        sender: &Sender<Event>,
+        receiver: &Receiver<Event>,
+        pending: &mut VecDeque<String>,
+    ) -> bool {
+        let limits = SearchLimits {
+            depth: self.time_control.depth.unwrap_or(MAX_XBOARD_DEPTH),
+            time: self.time_control.allotted_time(self.engine_moves),
+            nodes: None,
+        };
+        let post = self.post;
+        let stop = self.engine.stop_handle();
+        let chessboard = self.board().clone();
+        let engine = &mut self.engine;
+
+        let mut keep_running = true;
+        let mut cancelled = false;
+
+        let result = thread::scope(|scope| {
+            let search_sender = sender.clone();
+            scope.spawn(move || {
+                let mut post_info = |info: &SearchInfo| {
+                    if post {
+                        send_post(info);
+                    }
+                };
+                let result = engine.search_with_info(&chessboard, limits, &mut post_info);
+                let _ = search_sender.send(Event::SearchDone(Box::new(result)));
+            });
+
+            loop {
+                let line = match receiver.recv() {
+                    Ok(Event::SearchDone(result)) => return Some(result),
+                    Ok(Event::Line(line)) => line,
+                    Err(_) => return None,
+                };
+
+                match line.split_whitespace().next() {
+                    Some("?") => stop.store(true, Ordering::Relaxed),
+                    Some("quit") => {
+                        keep_running = false;
+                        cancelled = true;
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    Some("new" | "force" | "result" | "setboard" | "undo" | "remove") => {
+                        cancelled = true;
+                        stop.store(true, Ordering::Relaxed);
+                        pending.push_back(line);
+                    }
+                    _ => pending.push_back(line),
+                }
+            }
+        });
+
+        stop.store(false, Ordering::Relaxed);
+
+        if cancelled {
+            return keep_running;
+        }
+
+        match result.and_then(|result| result.best_move) {
+            Some(best_move) => {
+                let (_, new_chessboard) = find_move(self.board(), &best_move.to_coordinates())
+                    .expect("la búsqueda devuelve movimientos legales");
+                send(&format!("move {}", best_move.to_coordinates()));
+                self.history.push(new_chessboard);
+                self.engine_moves += 1;
+                self.report_result();
+            }
+            // Sin movimientos legales la partida ya terminó
+            None => self.report_result(),
+        }
+
+        keep_running
+    }
+
+    // Avisa el resultado si la partida terminó por mate, ahogado, repetición o material
+    fn report_result(&mut self) {
+        let chessboard = self.board();
+
+        let result = if generate_moves(chessboard, false).is_empty() {
+            if is_check(chessboard, chessboard.player_turn).is_some() {
+                match chessboard.player_turn {
+                    ChessPieceColor::White => "0-1 {Black mates}",
+                    ChessPieceColor::Black => "1-0 {White mates}",
+                }
+            } else {
+                "1/2-1/2 {Stalemate}"
+            }
+        } else if self.repetitions() >= 3 {
+            "1/2-1/2 {Draw by repetition}"
+        } else if insufficient_material(chessboard) {
+            "1/2-1/2 {Insufficient material}"
+        } else {
+            return;
+        };
+
+        send(result);
+        self.game_over = true;
+    }
+
+    // Veces que apareció la posición actual en la partida
+    fn repetitions(&self) -> usize {
+        let current = zobrist::hash(self.board());
+        self.history
+            .iter()
+            .filter(|chessboard| zobrist::hash(chessboard) == current)
+            .count()
+    }
+}
+
+// El tiempo base de "level" viene en minutos, o como "minutos:segundos"
+fn parse_base_time(base: &str) -> Option<Duration> {
+    let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+    let seconds = minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+// ply puntuación tiempo(centésimas) nodos variante
+fn send_post(info: &SearchInfo) {
+    let principal_variation: Vec<String> = info
+        .principal_variation
+        .iter()
+        .map(|chess_move| chess_move.to_coordinates())
+        .collect();
+
+    send(&format!(
+        "{} {} {} {} {}",
+        info.depth,
+        xboard_score(info.score),
+        info.elapsed.as_millis() / 10,
+        info.nodes,
+        principal_variation.join(" ")
+    ));
+}
+
+fn xboard_score(score: i32) -> i32 {
+    if score.abs() >= MATE_SCORE - MAX_PLY as i32 {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        return score.signum() * (XBOARD_MATE_SCORE + moves);
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_sets_the_time_control() {
+        let mut xboard = XboardEngine::new();
+        assert!(xboard.handle("level 40 5:30 2.5"));
+
+        let time_control = &xboard.time_control;
+        assert_eq!(time_control.moves_per_session, 40);
+        assert_eq!(time_control.increment, Duration::from_millis(2500));
+        assert_eq!(time_control.clock, Some(33_000));
+        assert_eq!(time_control.move_time, None);
+    }
+
+    #[test]
+    fn level_rejects_invalid_increments() {
+        let mut xboard = XboardEngine::new();
+
+        for line in [
+            "level 0 5 -1",
+            "level 0 5 NaN",
+            "level 0 5 inf",
+            "level 0 x 0",
+            "level 0 5",
+        ] {
+            assert!(xboard.handle(line));
+            assert_eq!(xboard.time_control.clock, None);
+            assert_eq!(xboard.time_control.increment, Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn st_and_sd_set_fixed_limits() {
+        let mut xboard = XboardEngine::new();
+        xboard.handle("st 3");
+        xboard.handle("sd 7");
+        assert_eq!(xboard.time_control.move_time, Some(Duration::from_secs(3)));
+        assert_eq!(xboard.time_control.depth, Some(7));
+
+        xboard.handle("sd x");
+        assert_eq!(xboard.time_control.depth, Some(7));
+
+        // "new" vuelve a la profundidad sin límite, pero no cambia el tiempo por jugada
+        xboard.handle("new");
+        assert_eq!(xboard.time_control.depth, None);
+        assert_eq!(xboard.time_control.move_time, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn usermove_plays_only_legal_moves() {
+        let mut xboard = XboardEngine::new();
+        xboard.handle("force");
+
+        xboard.handle("usermove e2e5");
+        assert_eq!(xboard.history.len(), 1);
+
+        xboard.handle("usermove e2e4");
+        assert_eq!(xboard.history.len(), 2);
+        assert!(xboard.board().player_turn == ChessPieceColor::Black);
+
+        xboard.handle("playother");
+        assert!(xboard.engine_color == Some(ChessPieceColor::White));
+
+        xboard.handle("undo");
+        assert_eq!(xboard.history.len(), 1);
+    }
+}